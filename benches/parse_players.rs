@@ -0,0 +1,63 @@
+// Times full parses of the characters in examples/ that parse, the way the tools read them.
+// Run with `cargo bench --bench parse_players`.
+use std::{
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use d2_itemsorter::{
+    item::info::{ItemDb, MapItemDb},
+    save::{SaveFile, SaveKind},
+};
+
+const ITERATIONS: u32 = 500;
+
+fn time<T>(name: &str, mut f: impl FnMut() -> T) {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        std::hint::black_box(f());
+    }
+    let elapsed: Duration = start.elapsed() / ITERATIONS;
+    println!("{name}: {elapsed:?} per iteration");
+}
+
+fn main() {
+    let item_db: Rc<dyn ItemDb> = Rc::new(MapItemDb::from_data_dir("data/items"));
+    let mut paths: Vec<PathBuf> = std::fs::read_dir("examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| SaveKind::from_extension(path) == Some(SaveKind::Player))
+        .collect();
+    paths.sort();
+    let saves: Vec<(PathBuf, Vec<u8>)> = paths
+        .into_iter()
+        .map(|path| {
+            let bytes = std::fs::read(&path).unwrap();
+            (path, bytes)
+        })
+        .filter(
+            |(path, bytes)| match SaveFile::parse(SaveKind::Player, bytes, item_db.clone()) {
+                Ok(_) => true,
+                Err(error) => {
+                    println!("skipping {}: {}", path.display(), error);
+                    false
+                }
+            },
+        )
+        .collect();
+
+    for (path, bytes) in &saves {
+        time(&path.display().to_string(), || {
+            SaveFile::parse(SaveKind::Player, bytes, item_db.clone()).unwrap()
+        });
+    }
+    time("all examples", || {
+        for (_, bytes) in &saves {
+            std::hint::black_box(
+                SaveFile::parse(SaveKind::Player, bytes, item_db.clone()).unwrap(),
+            );
+        }
+    });
+}
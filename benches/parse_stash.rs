@@ -0,0 +1,162 @@
+// Times full parses of stash_example.sss with the BitVecReader, page by page and as a NewStash,
+// and the reads and searches they are made of, next to the same reads and searches done bit by
+// bit, the way the reader did them before it loaded words. Run with
+// `cargo bench --bench parse_stash`.
+//
+// Loading words alone barely speeds up a full parse. Running the page by page parse below against
+// builds of the reader before and after it loaded words, five interleaved runs each, gave
+// 39-53ms (46ms on average) before and 39-48ms (41ms) after. Parses were then dominated by the
+// context snapshots taken on every peek, and only got faster once the context slots were typed.
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use d2_itemsorter::{
+    bitsy::{context, BitReader, BitVecReader, MyBitSlice, MyBitVec},
+    item::{
+        info::{ItemDb, MapItemDb},
+        properties::MapPropertyDb,
+        ItemList,
+    },
+    stash::NewStash,
+};
+
+const ITERATIONS: u32 = 50;
+
+// Readers are built from the bytes, as cloning a MyBitVec goes bit by bit.
+fn reader(bytes: &[u8]) -> BitVecReader {
+    BitVecReader::new(MyBitVec::from_vec(bytes.to_vec()))
+}
+
+fn parse_stash(bytes: &[u8], item_db: &Rc<dyn ItemDb>) -> usize {
+    let mut reader =
+        BitVecReader::with_item_db(MyBitVec::from_vec(bytes.to_vec()), item_db.clone());
+    reader.set_property_db(Rc::new(MapPropertyDb::new()));
+    reader.set_context(&context::VERSION, 96);
+
+    let _header: [u8; 14] = reader.read().unwrap();
+    let mut item_count = 0;
+    while reader.index() < bytes.len() * 8 {
+        let _page_header: [u8; 3] = reader.read().unwrap();
+        let items: ItemList = reader.read().unwrap();
+        item_count += items.items().len();
+    }
+    item_count
+}
+
+// The whole file as a NewStash, like the tools read it.
+fn parse_new_stash(bytes: &[u8], item_db: &Rc<dyn ItemDb>) -> usize {
+    let mut reader =
+        BitVecReader::with_item_db(MyBitVec::from_vec(bytes.to_vec()), item_db.clone());
+    reader.set_property_db(Rc::new(MapPropertyDb::new()));
+    let stash: NewStash = reader.read().unwrap();
+    stash
+        .pages()
+        .iter()
+        .map(|page| page.items().items().len())
+        .sum()
+}
+
+// The reads and search of the reader before it loaded words, for comparison.
+fn read_int_by_bit(bits: &MyBitSlice, index: usize, bit_count: usize) -> u32 {
+    let mut result = 0;
+    for offset in 0..bit_count {
+        result |= (bits[index + offset] as u32) << offset;
+    }
+    result
+}
+
+fn read_bits_by_bit(bits: &MyBitSlice, index: usize, bit_count: usize) -> MyBitVec {
+    let mut result = MyBitVec::new();
+    for bit in bits[index..].iter().take(bit_count) {
+        result.push(*bit);
+    }
+    result
+}
+
+fn search_by_bit(bits: &MyBitSlice, needle: &MyBitSlice, offset: usize) -> Option<usize> {
+    (offset..bits.len().saturating_sub(needle.len()) + 1)
+        .find(|start| bits[*start..].starts_with(needle))
+}
+
+// Calls `read` with each index of `bit_count` bits that fits in the file.
+fn read_all<T>(bit_len: usize, bit_count: usize, mut read: impl FnMut(usize) -> T) {
+    let mut index = 0;
+    while index + bit_count <= bit_len {
+        std::hint::black_box(read(index));
+        index += bit_count;
+    }
+}
+
+fn time<T>(name: &str, mut f: impl FnMut() -> T) {
+    f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        std::hint::black_box(f());
+    }
+    let elapsed: Duration = start.elapsed() / ITERATIONS;
+    println!("{name}: {elapsed:?} per iteration");
+}
+
+fn main() {
+    let item_db: Rc<dyn ItemDb> = Rc::new(MapItemDb::from_data_dir("data/items"));
+    let bytes = std::fs::read("stash_example.sss").unwrap();
+
+    println!(
+        "stash_example.sss: {} bytes, {} items",
+        bytes.len(),
+        parse_stash(&bytes, &item_db)
+    );
+    time("full stash parse", || parse_stash(&bytes, &item_db));
+    time("full NewStash parse", || parse_new_stash(&bytes, &item_db));
+
+    let bits = MyBitVec::from_vec(bytes.clone());
+    for bit_count in [7, 27] {
+        time(
+            &format!("{bit_count} bit reads over the whole file"),
+            || {
+                let mut reader = reader(&bytes);
+                let mut sum = 0u32;
+                while let Ok(value) = reader.read_int::<u32>(bit_count) {
+                    sum = sum.wrapping_add(value);
+                }
+                sum
+            },
+        );
+        time(&format!("{bit_count} bit reads, bit by bit"), || {
+            read_all(bits.len(), bit_count, |index| {
+                read_int_by_bit(&bits, index, bit_count)
+            })
+        });
+    }
+
+    time("64 bit read_bits over the whole file", || {
+        let mut reader = reader(&bytes);
+        while reader.read_bits(64).is_ok() {}
+    });
+    time("64 bit read_bits, bit by bit", || {
+        read_all(bits.len(), 64, |index| read_bits_by_bit(&bits, index, 64))
+    });
+
+    let needle = MyBitVec::from_vec(b"JM".to_vec());
+    time("search for every JM", || {
+        let reader = reader(&bytes);
+        let mut count = 0;
+        let mut offset = 0;
+        while let Some(found) = reader.search(&needle, offset) {
+            count += 1;
+            offset = found + 1;
+        }
+        count
+    });
+    time("search for every JM, bit by bit", || {
+        let mut count = 0;
+        let mut offset = 0;
+        while let Some(found) = search_by_bit(&bits, &needle, offset) {
+            count += 1;
+            offset = found + 1;
+        }
+        count
+    });
+}
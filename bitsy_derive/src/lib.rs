@@ -0,0 +1,175 @@
+//! `#[derive(Bitsy)]` for structs with named fields.
+//!
+//! Fields are read and written in declaration order through `read_field`, so errors get the field
+//! name prepended to their path and annotating readers record them. Supported attributes:
+//!
+//! - `#[bitsy(magic = "HEADER")]` on the struct: reads and checks a constant before the fields, as
+//!   a field named `magic`, and writes it back.
+//! - `#[bitsy(padding)]` on the struct or on a field: byte-aligns after the last field or after
+//!   that field.
+//! - `#[bitsy(if = "expr")]` on an `Option` field: only reads the field when `expr` is true. The
+//!   expression can use `reader` and any earlier field.
+//! - `#[bitsy(context = "context::KEY")]` on a field: stores the field's value in the reader
+//!   context once it has been read.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, Fields, Ident, LitStr, Result,
+};
+
+#[proc_macro_derive(Bitsy, attributes(bitsy))]
+pub fn derive_bitsy(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct StructOptions {
+    magic: Option<Expr>,
+    padding: bool,
+}
+
+#[derive(Default)]
+struct FieldOptions {
+    condition: Option<Expr>,
+    context: Option<Expr>,
+    padding: bool,
+}
+
+fn parse_expr(value: &LitStr) -> Result<Expr> {
+    value.parse()
+}
+
+fn struct_options(attrs: &[Attribute]) -> Result<StructOptions> {
+    let mut options = StructOptions::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("bitsy")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("magic") {
+                options.magic = Some(parse_expr(&meta.value()?.parse()?)?);
+                Ok(())
+            } else if meta.path.is_ident("padding") {
+                options.padding = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `magic` or `padding`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+fn field_options(attrs: &[Attribute]) -> Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("bitsy")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("if") {
+                options.condition = Some(parse_expr(&meta.value()?.parse()?)?);
+                Ok(())
+            } else if meta.path.is_ident("context") {
+                options.context = Some(parse_expr(&meta.value()?.parse()?)?);
+                Ok(())
+            } else if meta.path.is_ident("padding") {
+                options.padding = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `if`, `context` or `padding`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "Bitsy can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Bitsy can only be derived for structs",
+            ))
+        }
+    };
+    let options = struct_options(&input.attrs)?;
+
+    let bitsy = quote!(::d2_itemsorter::bitsy);
+    let prepend_path = quote!(#bitsy::error::BitsyErrorExt::prepend_path);
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut names: Vec<&Ident> = Vec::new();
+
+    if let Some(magic) = &options.magic {
+        let type_name = name.to_string();
+        reads.push(quote! {
+            #bitsy::BitReader::read_named(reader, "magic", |reader| {
+                #bitsy::read_magic(reader, &#magic, #type_name)
+            })?;
+        });
+        writes.push(quote! {
+            #bitsy::BitWriter::write(writer, &#magic)?;
+        });
+    }
+
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let path = ident.to_string();
+        let field_options = field_options(&field.attrs)?;
+
+        let read = quote!(#bitsy::BitReader::read_field(reader, #path)?);
+        reads.push(match &field_options.condition {
+            Some(condition) => quote! {
+                let #ident: #ty = if #condition { Some(#read) } else { None };
+            },
+            None => quote! {
+                let #ident: #ty = #read;
+            },
+        });
+        if let Some(key) = &field_options.context {
+            reads.push(quote! {
+                #bitsy::BitReader::set_context(reader, &#key, ::core::clone::Clone::clone(&#ident));
+            });
+        }
+        writes.push(quote! {
+            #prepend_path(#bitsy::BitWriter::write(writer, &self.#ident), #path)?;
+        });
+        if field_options.padding {
+            reads.push(quote!(#bitsy::BitReader::read_padding(reader)?;));
+            writes.push(quote!(#bitsy::BitWriter::write_padding(writer)?;));
+        }
+        names.push(ident);
+    }
+
+    if options.padding {
+        reads.push(quote!(#bitsy::BitReader::read_padding(reader)?;));
+        writes.push(quote!(#bitsy::BitWriter::write_padding(writer)?;));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #bitsy::Bitsy for #name #ty_generics #where_clause {
+            fn parse<R: #bitsy::BitReader>(reader: &mut R) -> #bitsy::result::BitsyResult<Self> {
+                #(#reads)*
+                Ok(Self { #(#names),* })
+            }
+
+            fn write_to<W: #bitsy::BitWriter>(&self, writer: &mut W) -> #bitsy::result::BitsyResult<()> {
+                #(#writes)*
+                Ok(())
+            }
+        }
+    })
+}
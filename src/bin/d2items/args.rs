@@ -0,0 +1,85 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{Failure, USAGE};
+
+// Options every command takes.
+const COMMON_FLAGS: [&str; 1] = ["lenient"];
+const COMMON_OPTIONS: [&str; 1] = ["data"];
+
+// The arguments of a command: positional ones, `--flag`s and `--option value`s.
+pub struct Args {
+    positional: VecDeque<String>,
+    flags: HashSet<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    pub fn parse(args: Vec<String>, flags: &[&str], options: &[&str]) -> Result<Self, Failure> {
+        let mut parsed = Args {
+            positional: VecDeque::new(),
+            flags: HashSet::new(),
+            options: HashMap::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Every command shows the usage of all of them.
+            if arg == "--help" || arg == "-h" {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => {
+                    parsed.positional.push_back(arg);
+                    continue;
+                }
+            };
+            if flags.contains(&name) || COMMON_FLAGS.contains(&name) {
+                parsed.flags.insert(name.to_string());
+            } else if options.contains(&name) || COMMON_OPTIONS.contains(&name) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Failure::usage(format!("Missing value for --{}", name)))?;
+                parsed.options.insert(name.to_string(), value);
+            } else {
+                return Err(Failure::usage(format!("Unknown option --{}", name)));
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn positional(&mut self, name: &str) -> Result<String, Failure> {
+        self.positional
+            .pop_front()
+            .ok_or_else(|| Failure::usage(format!("Missing {}", name)))
+    }
+
+    // All remaining positional arguments, at least one.
+    pub fn rest(&mut self, name: &str) -> Result<Vec<String>, Failure> {
+        if self.positional.is_empty() {
+            return Err(Failure::usage(format!("Missing {}", name)));
+        }
+        Ok(self.positional.drain(..).collect())
+    }
+
+    // All remaining positional arguments, if any.
+    pub fn remaining(&mut self) -> Vec<String> {
+        self.positional.drain(..).collect()
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    // Fails on positional arguments that no one asked for.
+    pub fn finish(&self) -> Result<(), Failure> {
+        match self.positional.front() {
+            Some(arg) => Err(Failure::usage(format!("Unexpected argument '{}'", arg))),
+            None => Ok(()),
+        }
+    }
+}
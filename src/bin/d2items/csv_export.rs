@@ -0,0 +1,52 @@
+use std::{fs::File, io::Write, path::Path};
+
+use d2_itemsorter::{
+    export::{write_csv, CsvRow},
+    save::find_saves,
+};
+
+use crate::{args::Args, files::Session, CommandResult, Failure};
+
+// Files that can't be parsed are reported and left out, so that one broken save doesn't stop the
+// export of all the others.
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &[], &["output"])?;
+    let paths = args.rest("file or directory")?;
+    let mut session = Session::new(&args);
+    let mut saves = Vec::new();
+    for path in &paths {
+        saves.extend(find_saves(Path::new(path)).map_err(|error| Failure::input(error.kind()))?);
+    }
+
+    let mut rows = Vec::new();
+    let mut all_parsed = true;
+    for save in &saves {
+        let path = save.display().to_string();
+        match session.load(&path) {
+            Ok(loaded) => rows.extend(CsvRow::all(&path, &loaded.save)),
+            Err(Failure::Input(message)) => {
+                eprintln!("d2items: {}", message);
+                all_parsed = false;
+            }
+            Err(failure) => return Err(failure),
+        }
+    }
+
+    let output: Box<dyn Write> =
+        match args.option("output") {
+            Some(output) => Box::new(File::create(output).map_err(|error| {
+                Failure::output(format!("Could not write {}: {}", output, error))
+            })?),
+            None => Box::new(std::io::stdout()),
+        };
+    write_csv(output, &rows).map_err(|error| Failure::output(error.kind()))?;
+    if let Some(output) = args.option("output") {
+        eprintln!(
+            "Wrote {} items from {} files to {}",
+            rows.len(),
+            saves.len(),
+            output
+        );
+    }
+    Ok(all_parsed)
+}
@@ -0,0 +1,99 @@
+use d2_itemsorter::{diff::SaveDiff, save::SaveFile};
+use serde_json::Value;
+
+use crate::{args::Args, files::Session, CommandResult, Failure};
+
+// More differences than this are only counted.
+const MAX_SHOWN: usize = 50;
+
+fn collect_differences(path: &str, old: &Value, new: &Value, differences: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            for (key, old_value) in old_fields {
+                let field = format!("{}.{}", path, key);
+                match new_fields.get(key) {
+                    Some(new_value) => {
+                        collect_differences(&field, old_value, new_value, differences)
+                    }
+                    None => differences.push(format!("{}: removed", field)),
+                }
+            }
+            for key in new_fields
+                .keys()
+                .filter(|key| !old_fields.contains_key(*key))
+            {
+                differences.push(format!("{}.{}: added", path, key));
+            }
+        }
+        (Value::Array(old_values), Value::Array(new_values)) => {
+            for (index, (old_value, new_value)) in old_values.iter().zip(new_values).enumerate() {
+                collect_differences(
+                    &format!("{}[{}]", path, index),
+                    old_value,
+                    new_value,
+                    differences,
+                );
+            }
+            if old_values.len() != new_values.len() {
+                differences.push(format!(
+                    "{}: {} entries instead of {}",
+                    path,
+                    new_values.len(),
+                    old_values.len()
+                ));
+            }
+        }
+        _ if old != new => differences.push(format!("{}: {} -> {}", path, old, new)),
+        _ => {}
+    }
+}
+
+// Every field of the two files that differs, as named in the JSON export.
+fn raw_differences(old: &SaveFile, new: &SaveFile) -> Result<Vec<String>, Failure> {
+    let old = serde_json::to_value(old).map_err(Failure::output)?;
+    let new = serde_json::to_value(new).map_err(Failure::output)?;
+    let mut differences = Vec::new();
+    collect_differences("", &old, &new, &mut differences);
+    Ok(differences)
+}
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &["json", "raw"], &[])?;
+    let old_path = args.positional("first file")?;
+    let new_path = args.positional("second file")?;
+    args.finish()?;
+    if args.flag("json") && args.flag("raw") {
+        return Err(Failure::usage("Use either --json or --raw"));
+    }
+    let mut session = Session::new(&args);
+    let old = session.load(&old_path)?;
+    let new = session.load(&new_path)?;
+
+    if args.flag("raw") {
+        let differences = raw_differences(&old.save, &new.save)?;
+        for difference in differences.iter().take(MAX_SHOWN) {
+            println!("{}", difference);
+        }
+        if differences.len() > MAX_SHOWN {
+            println!("... and {} more", differences.len() - MAX_SHOWN);
+        }
+        return Ok(differences.is_empty());
+    }
+    let diff = SaveDiff::new(&old.save, &new.save);
+    if args.flag("json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&diff).map_err(Failure::output)?
+        );
+    } else {
+        for line in diff.to_lines() {
+            println!("{}", line);
+        }
+        // Changes the summary doesn't cover, like to unknown fields, are still worth knowing of.
+        if diff.is_empty() && !raw_differences(&old.save, &new.save)?.is_empty() {
+            println!("Only fields shown with --raw differ.");
+            return Ok(false);
+        }
+    }
+    Ok(diff.is_empty())
+}
@@ -0,0 +1,114 @@
+use std::io::IsTerminal;
+
+use d2_itemsorter::{
+    bitsy::{
+        dump::{render_dump, DumpOptions},
+        error::BitsyErrorExt,
+        MyBitVec,
+    },
+    save::SaveFile,
+};
+
+use crate::{
+    args::Args,
+    files::{describe_item, describe_kind, describe_place, item_ref, Loaded, Session},
+    CommandResult, Failure,
+};
+
+// Byte offsets can be given in decimal or in hex with a 0x prefix.
+fn parse_offset(args: &Args, name: &str) -> Result<Option<usize>, Failure> {
+    let arg = match args.option(name) {
+        Some(arg) => arg,
+        None => return Ok(None),
+    };
+    let offset = match arg.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    offset
+        .map(Some)
+        .map_err(|_| Failure::usage(format!("Invalid byte offset '{}' for --{}", arg, name)))
+}
+
+fn list_items(loaded: &Loaded) {
+    print!("{}: {}", loaded.path.display(), describe_kind(loaded.kind));
+    if let SaveFile::Player(player) = &loaded.save {
+        print!(" {}, version {}", player.name(), player.version);
+    }
+    println!();
+    for (list_index, (label, list)) in loaded.save.item_lists().into_iter().enumerate() {
+        println!(
+            "[{}] {}: {} items",
+            list_index + 1,
+            label,
+            list.items().len()
+        );
+        for (index, item) in list.items().iter().enumerate() {
+            println!(
+                "  {}  {}  {}",
+                item_ref(list_index, index),
+                describe_item(item),
+                describe_place(item)
+            );
+        }
+        for opaque in list.opaque_items() {
+            println!(
+                "  {} unparsed item entries at bit {}",
+                opaque.entry_count(),
+                opaque.start()
+            );
+        }
+    }
+}
+
+// Parses the file again while recording which bits belong to which field. Unlike `load` this
+// doesn't stop at errors, so the dump shows how far parsing got.
+fn hex_dump(session: &mut Session, args: &Args, path: &str) -> CommandResult {
+    let bytes = d2_itemsorter::save::read_file(path.as_ref())
+        .map_err(|error| Failure::input(error.kind()))?;
+    let kind = d2_itemsorter::save::SaveKind::detect(path.as_ref(), &bytes)
+        .map_err(|error| Failure::input(error.kind()))?;
+    let mut reader = SaveFile::reader(kind, &bytes, session.item_db()?);
+    reader.record_annotations();
+    let result = SaveFile::read_with(kind, &mut reader);
+    let annotations = reader.take_annotations();
+    let bits = MyBitVec::from_vec(bytes);
+
+    if args.flag("annotations") {
+        let json = annotations
+            .as_ref()
+            .map(|annotations| annotations.to_json())
+            .transpose()
+            .map_err(Failure::output)?;
+        println!("{}", json.unwrap_or_else(|| "null".to_string()));
+    } else {
+        let options = DumpOptions {
+            color: std::io::stdout().is_terminal() && !args.flag("no-color"),
+            bytes: Some(
+                parse_offset(args, "from")?.unwrap_or(0)
+                    ..parse_offset(args, "to")?.unwrap_or(usize::MAX),
+            ),
+        };
+        print!("{}", render_dump(&bits, annotations.as_ref(), &options));
+    }
+    if let Err(error) = result {
+        return Err(Failure::input(format!(
+            "Parsing stopped with an error: {}",
+            error.with_source(&bits)
+        )));
+    }
+    Ok(true)
+}
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &["hex", "no-color", "annotations"], &["from", "to"])?;
+    let path = args.positional("file")?;
+    args.finish()?;
+    let mut session = Session::new(&args);
+    if args.flag("hex") || args.flag("annotations") {
+        return hex_dump(&mut session, &args, &path);
+    }
+    let loaded = session.load(&path)?;
+    list_items(&loaded);
+    Ok(true)
+}
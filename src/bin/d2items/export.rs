@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use d2_itemsorter::export::Export;
+
+use crate::{
+    args::Args,
+    files::{write_save, Session},
+    CommandResult, Failure,
+};
+
+pub fn run_export(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &[], &["output"])?;
+    let path = args.positional("file")?;
+    args.finish()?;
+    let loaded = Session::new(&args).load(&path)?;
+    let export = Export::new(loaded.kind, &loaded.bytes, loaded.save);
+    let json = export.to_json().map_err(Failure::output)?;
+    match args.option("output") {
+        Some(output) => std::fs::write(output, json + "\n")
+            .map_err(|error| Failure::output(format!("Could not write {}: {}", output, error)))?,
+        None => println!("{}", json),
+    }
+    Ok(true)
+}
+
+pub fn run_import(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &[], &["output", "backups", "journal"])?;
+    let path = args.positional("json file")?;
+    args.finish()?;
+    let json = std::fs::read_to_string(&path)
+        .map_err(|error| Failure::input(format!("Could not read {}: {}", path, error)))?;
+    let export = Export::from_json(&json)
+        .map_err(|error| Failure::input(format!("Could not read {}: {}", path, error.kind())))?;
+    let (bytes, unchanged) = export.to_bytes().map_err(|error| {
+        Failure::input(format!("Could not build a file from {}: {}", path, error))
+    })?;
+    if unchanged {
+        println!("{} gives back the exported file exactly.", path);
+    } else {
+        println!(
+            "{} gives a file of {} bytes that differs from the exported one.",
+            path,
+            bytes.len()
+        );
+    }
+    if args.option("output").is_none() {
+        println!("Nothing was written. Use --output to save the file.");
+        return Ok(true);
+    }
+    let mut session = Session::new(&args);
+    session.begin("import", &format!("Imported {}", path));
+    write_save(
+        &args,
+        &mut session,
+        Path::new(&path),
+        export.kind,
+        &export.save,
+    )?;
+    Ok(true)
+}
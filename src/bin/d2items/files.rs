@@ -0,0 +1,310 @@
+// Loading and writing files for the commands, and how items are shown.
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use d2_itemsorter::{
+    bitsy::{
+        error::{BitsyError, BitsyErrorExt},
+        MyBitVec,
+    },
+    item::{
+        info::{ItemDb, MapItemDb},
+        Container, NewItem,
+    },
+    journal::{Journal, Operation},
+    safe_write::{self, WriteOptions},
+    save::{read_file, SaveFile, SaveKind},
+    transfer::PanelSizes,
+};
+
+use crate::{args::Args, Failure};
+
+const DEFAULT_DATA_DIR: &str = "data/items";
+// In the home directory.
+const DEFAULT_JOURNAL: &str = ".d2items/journal.jsonl";
+
+pub struct Loaded {
+    pub path: PathBuf,
+    pub kind: SaveKind,
+    pub bytes: Vec<u8>,
+    pub save: SaveFile,
+}
+
+// What the commands share: the item data, loaded once, how to read files and the operation that
+// files are written for.
+pub struct Session {
+    data_dir: PathBuf,
+    lenient: bool,
+    item_db: Option<Rc<dyn ItemDb>>,
+    journal: Option<PathBuf>,
+    operation: Option<Operation>,
+}
+
+impl Session {
+    pub fn new(args: &Args) -> Self {
+        Self {
+            data_dir: PathBuf::from(args.option("data").unwrap_or(DEFAULT_DATA_DIR)),
+            lenient: args.flag("lenient"),
+            item_db: None,
+            journal: args.option("journal").map(PathBuf::from),
+            operation: None,
+        }
+    }
+
+    // The journal given with --journal, or the one in the home directory.
+    pub fn journal(&self) -> Result<Journal, Failure> {
+        if let Some(path) = &self.journal {
+            return Ok(Journal::new(path));
+        }
+        std::env::var_os("HOME")
+            .map(|home| Journal::new(PathBuf::from(home).join(DEFAULT_JOURNAL)))
+            .ok_or_else(|| Failure::usage("There is no home directory, use --journal"))
+    }
+
+    // Files written from now on are recorded in the journal together, so that `undo` restores
+    // them all.
+    pub fn begin(&mut self, command: &str, description: &str) {
+        self.operation = Some(Operation::new(command, description));
+    }
+
+    pub fn item_db(&mut self) -> Result<Rc<dyn ItemDb>, Failure> {
+        if let Some(item_db) = &self.item_db {
+            return Ok(item_db.clone());
+        }
+        if !self.data_dir.join("items.csv").is_file() {
+            return Err(Failure::input(format!(
+                "No item data in {}, use --data to say where it is",
+                self.data_dir.display()
+            )));
+        }
+        let item_db: Rc<dyn ItemDb> = Rc::new(MapItemDb::from_data_dir(&self.data_dir));
+        self.item_db = Some(item_db.clone());
+        Ok(item_db)
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<Loaded, Failure> {
+        let path = PathBuf::from(path);
+        let bytes = read_file(&path).map_err(|error| Failure::input(error.kind()))?;
+        let kind = SaveKind::detect(&path, &bytes).map_err(|error| Failure::input(error.kind()))?;
+        let item_db = self.item_db()?;
+        let failed = |error: d2_itemsorter::bitsy::error::BitsyError| {
+            let error = error.with_source(&MyBitVec::from_vec(bytes.clone()));
+            Failure::input(format!("Could not parse {}: {}", path.display(), error))
+        };
+        let save = if self.lenient {
+            let (save, diagnostics) =
+                SaveFile::parse_lenient(kind, &bytes, item_db).map_err(failed)?;
+            for diagnostic in diagnostics {
+                eprintln!("warning: {}: {}", path.display(), diagnostic);
+            }
+            save
+        } else {
+            SaveFile::parse(kind, &bytes, item_db).map_err(failed)?
+        };
+        Ok(Loaded {
+            path,
+            kind,
+            bytes,
+            save,
+        })
+    }
+}
+
+// How many earlier versions of a file are kept, with --backups.
+pub fn write_options(args: &Args) -> Result<WriteOptions, Failure> {
+    let mut options = WriteOptions::default();
+    if let Some(backups) = args.option("backups") {
+        options.backups = backups
+            .parse()
+            .map_err(|_| Failure::usage(format!("Invalid number of backups '{}'", backups)))?;
+    }
+    Ok(options)
+}
+
+// Writes over the input with --write, or to the path given with --output. Without either nothing
+// is written.
+pub fn save_changes(args: &Args, session: &mut Session, loaded: &Loaded) -> Result<(), Failure> {
+    save_all_changes(args, session, &[loaded])
+}
+
+// Changes to several files, which are all checked before any is written so that a failed check
+// doesn't leave the change half done.
+pub fn save_all_changes(
+    args: &Args,
+    session: &mut Session,
+    loaded: &[&Loaded],
+) -> Result<(), Failure> {
+    let saves: Vec<(&Path, SaveKind, &SaveFile)> = loaded
+        .iter()
+        .map(|loaded| (loaded.path.as_path(), loaded.kind, &loaded.save))
+        .collect();
+    write_saves(args, session, &saves)
+}
+
+pub fn write_save(
+    args: &Args,
+    session: &mut Session,
+    path: &Path,
+    kind: SaveKind,
+    save: &SaveFile,
+) -> Result<(), Failure> {
+    write_saves(args, session, &[(path, kind, save)])
+}
+
+// Saves are only written once they parse back the same, and the file they replace is backed up,
+// see `safe_write`.
+fn write_saves(
+    args: &Args,
+    session: &mut Session,
+    saves: &[(&Path, SaveKind, &SaveFile)],
+) -> Result<(), Failure> {
+    let output = match (args.option("output"), args.flag("write")) {
+        (Some(_), true) => return Err(Failure::usage("Use either --write or --output")),
+        (Some(_), false) if saves.len() > 1 => {
+            return Err(Failure::usage(
+                "Several files are changed, use --write instead of --output",
+            ))
+        }
+        (Some(output), false) => Some(PathBuf::from(output)),
+        (None, true) => None,
+        (None, false) => {
+            println!("Nothing was written. Use --write to save the changes.");
+            return Ok(());
+        }
+    };
+    let could_not_write = |target: &Path, error: BitsyError| {
+        Failure::output(format!(
+            "Could not write {}: {}",
+            target.display(),
+            error.kind()
+        ))
+    };
+    let item_db = session.item_db()?;
+    let verified = saves
+        .iter()
+        .map(|&(path, kind, save)| {
+            let target = output.clone().unwrap_or_else(|| path.to_path_buf());
+            match safe_write::verified_bytes(kind, save, item_db.clone()) {
+                Ok(bytes) => Ok((target, bytes)),
+                Err(error) => Err(could_not_write(&target, error)),
+            }
+        })
+        .collect::<Result<Vec<_>, Failure>>()?;
+
+    let mut options = write_options(args)?;
+    let journal = match session.operation {
+        Some(_) => Some(session.journal()?),
+        None => None,
+    };
+    if let Some(journal) = &journal {
+        options = journal.write_options(&options).map_err(|error| {
+            Failure::input(format!(
+                "Could not read {}: {}",
+                journal.path().display(),
+                error.kind()
+            ))
+        })?;
+    }
+    for (target, bytes) in verified {
+        let written = safe_write::write_file(&target, bytes, &options)
+            .map_err(|error| could_not_write(&target, error))?;
+        println!("Wrote {} ({} bytes)", target.display(), written.bytes.len());
+        if let Some(backup) = &written.backup {
+            println!("The previous file is kept as {}", backup.display());
+        }
+        if let (Some(operation), Some(journal)) = (&session.operation, &journal) {
+            journal
+                .record(&operation.entry(&written))
+                .map_err(|error| {
+                    Failure::output(format!(
+                        "{} was written, but could not be recorded for undo: {}",
+                        target.display(),
+                        error.kind()
+                    ))
+                })?;
+        }
+    }
+    Ok(())
+}
+
+// Items are referred to as LIST:ITEM, both counted from 1.
+pub fn item_ref(list: usize, item: usize) -> String {
+    format!("{}:{}", list + 1, item + 1)
+}
+
+pub fn parse_item_ref(arg: &str) -> Result<(usize, usize), Failure> {
+    let invalid = || Failure::usage(format!("Invalid item '{}', expected LIST:ITEM", arg));
+    let (list, item) = arg.split_once(':').ok_or_else(invalid)?;
+    let list: usize = list.parse().map_err(|_| invalid())?;
+    let item: usize = item.parse().map_err(|_| invalid())?;
+    Ok((
+        list.checked_sub(1).ok_or_else(invalid)?,
+        item.checked_sub(1).ok_or_else(invalid)?,
+    ))
+}
+
+// Sizes of panels are given as WIDTHxHEIGHT.
+pub fn parse_size(arg: &str) -> Result<(u8, u8), Failure> {
+    let invalid = || Failure::usage(format!("Invalid size '{}', expected WIDTHxHEIGHT", arg));
+    let (width, height) = arg.split_once('x').ok_or_else(invalid)?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+pub const PANEL_SIZE_OPTIONS: [&str; 4] = ["inventory", "cube", "stash", "page"];
+
+// Panels are the size of the game's unless given, for saves of mods with bigger ones.
+pub fn panel_sizes(args: &Args, save: &SaveFile) -> Result<PanelSizes, Failure> {
+    let mut sizes = PanelSizes::for_version(save.version());
+    for panel in PANEL_SIZE_OPTIONS {
+        if let Some(size) = args.option(panel) {
+            let size = parse_size(size)?;
+            match panel {
+                "inventory" => sizes.inventory = size,
+                "cube" => sizes.cube = size,
+                "stash" => sizes.stash = size,
+                _ => sizes.page = size,
+            }
+        }
+    }
+    Ok(sizes)
+}
+
+pub fn describe_place(item: &NewItem) -> String {
+    let (x, y) = item.position();
+    match item.container() {
+        Container::Equipped => format!("equipped (slot {})", item.equipped_slot()),
+        Container::Belt | Container::Socket => format!("{} (slot {})", item.container().name(), x),
+        Container::Other => format!(
+            "mode {} location {} ({}, {})",
+            item.mode(),
+            item.location(),
+            x,
+            y
+        ),
+        container => format!("{} ({}, {})", container.name(), x, y),
+    }
+}
+
+pub fn describe_item(item: &NewItem) -> String {
+    let mut description = item.description();
+    if let Some(code) = item.code() {
+        description.push_str(&format!(" [{}]", code.trim_end()));
+    }
+    if let Some(quality) = item.quality() {
+        description.push_str(&format!(" {:?}", quality).to_lowercase());
+    }
+    description
+}
+
+pub fn describe_kind(kind: SaveKind) -> &'static str {
+    match kind {
+        SaveKind::Player => "character",
+        SaveKind::SharedStash => "shared stash",
+        SaveKind::PersonalStash => "personal stash",
+    }
+}
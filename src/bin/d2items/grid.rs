@@ -0,0 +1,90 @@
+use std::io::IsTerminal;
+
+use d2_itemsorter::{
+    item::Container,
+    render::{render_grid, GridOptions, BELT_SIZE},
+    save::SaveFile,
+};
+
+use crate::{
+    args::Args,
+    files::{panel_sizes, Session, PANEL_SIZE_OPTIONS},
+    CommandResult, Failure,
+};
+
+const CONTAINERS: [Container; 4] = [
+    Container::Inventory,
+    Container::Cube,
+    Container::Stash,
+    Container::Belt,
+];
+
+// Characters have panels, stashes have pages, and without any given all of them are drawn.
+fn panels(save: &SaveFile, names: &[String]) -> Result<Vec<(usize, Container)>, Failure> {
+    match save {
+        SaveFile::Player(_) if names.is_empty() => {
+            Ok(CONTAINERS.iter().map(|&container| (0, container)).collect())
+        }
+        SaveFile::Player(_) => names
+            .iter()
+            .map(|name| {
+                CONTAINERS
+                    .iter()
+                    .find(|container| container.name() == name)
+                    .map(|&container| (0, container))
+                    .ok_or_else(|| {
+                        Failure::usage(format!(
+                            "Invalid panel '{}', expected inventory, cube, stash or belt",
+                            name
+                        ))
+                    })
+            })
+            .collect(),
+        SaveFile::Stash(stash) if names.is_empty() => Ok((0..stash.pages().len())
+            .map(|page| (page, Container::Stash))
+            .collect()),
+        SaveFile::Stash(stash) => names
+            .iter()
+            .map(|name| match name.parse::<usize>() {
+                Ok(page) if page > 0 && page <= stash.pages().len() => {
+                    Ok((page - 1, Container::Stash))
+                }
+                _ => Err(Failure::usage(format!(
+                    "Invalid page '{}', the stash has {} pages",
+                    name,
+                    stash.pages().len()
+                ))),
+            })
+            .collect(),
+    }
+}
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &["ascii", "no-color"], &PANEL_SIZE_OPTIONS)?;
+    let path = args.positional("file")?;
+    let names = args.remaining();
+    let loaded = Session::new(&args).load(&path)?;
+    let sizes = panel_sizes(&args, &loaded.save)?;
+    let options = GridOptions {
+        color: std::io::stdout().is_terminal() && !args.flag("no-color"),
+        unicode: !args.flag("ascii"),
+    };
+
+    let lists = loaded.save.item_lists();
+    for (number, (list, container)) in panels(&loaded.save, &names)?.into_iter().enumerate() {
+        let (name, items) = &lists[list];
+        let (title, size) = match container {
+            Container::Inventory => (container.name().to_string(), sizes.inventory),
+            Container::Cube => (container.name().to_string(), sizes.cube),
+            Container::Belt => (container.name().to_string(), BELT_SIZE),
+            _ if matches!(loaded.save, SaveFile::Stash(_)) => (name.clone(), sizes.page),
+            _ => (container.name().to_string(), sizes.stash),
+        };
+        if number > 0 {
+            println!();
+        }
+        println!("{} ({}x{})", title, size.0, size.1);
+        print!("{}", render_grid(list, items, container, size, &options));
+    }
+    Ok(true)
+}
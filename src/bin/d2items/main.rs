@@ -0,0 +1,163 @@
+// Inspects and edits Diablo II characters (.d2s) and PlugY stashes (.sss, .d2x).
+//
+// Files are only ever written by commands given --write or --output. Run `d2items help` for the
+// commands and exit codes.
+use std::{fmt::Display, process::ExitCode};
+
+mod args;
+mod csv_export;
+mod diff;
+mod dump;
+mod export;
+mod files;
+mod grid;
+mod move_item;
+mod search;
+mod sort;
+mod undo;
+mod validate;
+
+const USAGE: &str = "\
+Usage: d2items <command> [options]
+
+Commands:
+  dump <file> [--hex [--from BYTE] [--to BYTE] [--no-color]] [--annotations]
+      Lists the items of a file, or shows its bytes annotated with the fields they belong to.
+  sort <file> [--write | --output PATH]
+      Lays out the items of every stash page again, grouped by type.
+  search <query> <file or directory>...
+      Lists the items that match a query, like
+      'quality:unique name~\"shako\" eth:no sockets>=2 prop:\"Fire Resist\">=20'. Fields are name,
+      code, guid, quality, container, eth, runeword, identified, personalized, sockets, ilvl,
+      defense, quantity, id and prop. A word on its own matches names and codes, and a - in
+      front of a term negates it. Names are those of bases, runewords and personalizations, as
+      set and unique items are only known by the number given with id.
+  export <file> [--output PATH]
+      Prints the file as JSON, in the format described in docs/export-format.md.
+  import <json> [--output PATH]
+      Builds a file back from exported JSON, and tells whether it is the file exported.
+  csv <file or directory>... [--output PATH]
+      Writes one CSV row per item of every save found, looking through directories.
+  validate <file or directory>... [--inventory WxH] [--cube WxH] [--stash WxH] [--page WxH]
+      Checks that files parse, are written back unchanged and have valid checksums, that items
+      fit their panels without overlapping, that socketed items, durability, quantities and
+      attributes are possible, and that no GUID is used twice in any of the files. Each problem
+      is listed with the item it is about. The sizes of panels can be given for mods.
+  diff <file> <file> [--json | --raw]
+      Lists the items added, removed, moved (matched by GUID) or changed between two files, and
+      for characters the changes to attributes, gold, quests and waypoints. --json prints them
+      as JSON, and --raw lists every field of the files that differs instead.
+  move <file> <item> <destination> [--to inventory|cube|stash | --page N] [--size WxH]
+       [--dry-run | --write]
+      Moves an item, given as LIST:ITEM like in `dump`, with what is socketed into it, to the
+      first free spot of a character's inventory (by default), cube or stash, or of a stash page.
+      The destination can be the same file. --size changes the size of the panel it goes to, for
+      mods with larger ones. If there is no room, nothing is moved.
+  grid <file> [<panel>...] [--inventory WxH] [--cube WxH] [--stash WxH] [--page WxH] [--ascii]
+       [--no-color]
+      Draws panels with a letter in the cells of each item and a legend of the items, coloured
+      by quality on terminals. Panels are inventory, cube, stash and belt for characters, and
+      page numbers for stashes, all of them by default. Cells taken twice show a !.
+  undo [--dry-run] [--force]
+      Puts back the files written by the last sort, import or move, and forgets it, so that the
+      one before can be undone next. Files changed since are left alone unless --force is given.
+
+Options for every command:
+  --data DIR   Where the item data is, data/items by default.
+  --lenient    Keeps items that can't be parsed instead of failing.
+
+Files of the right kind are recognized by their first bytes, or else by their extension.
+
+Saves written by sort, import and move are first checked to parse back to what was meant to be
+written, and replace the file in one step. The file replaced is copied to a backups directory
+next to it, where the last 5 copies of each file are kept, or as many as --backups N says, along
+with any that undo still needs. What was written is recorded for undo in
+~/.d2items/journal.jsonl, or the file given with --journal.
+
+Exit codes:
+  0  Success.
+  1  The command ran, but found problems, differences or nothing.
+  2  The command line is wrong, or asks for something that isn't supported.
+  3  An input could not be read or parsed.
+  4  An output could not be written.";
+
+#[derive(Debug)]
+pub enum Failure {
+    Usage(String),
+    Input(String),
+    Output(String),
+}
+
+impl Failure {
+    pub fn usage(message: impl Display) -> Self {
+        Failure::Usage(message.to_string())
+    }
+
+    pub fn input(message: impl Display) -> Self {
+        Failure::Input(message.to_string())
+    }
+
+    pub fn output(message: impl Display) -> Self {
+        Failure::Output(message.to_string())
+    }
+
+    fn exit_code(&self) -> u8 {
+        match self {
+            Failure::Usage(_) => 2,
+            Failure::Input(_) => 3,
+            Failure::Output(_) => 4,
+        }
+    }
+}
+
+// `Ok(false)` is for commands that ran but whose answer is negative, like `diff` finding
+// differences.
+pub type CommandResult = Result<bool, Failure>;
+
+fn run(command: &str, args: Vec<String>) -> CommandResult {
+    match command {
+        "dump" => dump::run(args),
+        "sort" => sort::run(args),
+        "search" => search::run(args),
+        "export" => export::run_export(args),
+        "import" => export::run_import(args),
+        "csv" => csv_export::run(args),
+        "validate" => validate::run(args),
+        "diff" => diff::run(args),
+        "move" => move_item::run(args),
+        "grid" => grid::run(args),
+        "undo" => undo::run(args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        _ => Err(Failure::usage(format!("Unknown command '{}'", command))),
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let command = match args.next() {
+        Some(command) => command,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&command, args.collect()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(failure) => {
+            match &failure {
+                Failure::Usage(message) => {
+                    eprintln!("d2items: {}", message);
+                    eprintln!("Run `d2items help` for usage.");
+                }
+                Failure::Input(message) | Failure::Output(message) => {
+                    eprintln!("d2items: {}", message)
+                }
+            }
+            ExitCode::from(failure.exit_code())
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs;
+
+use d2_itemsorter::{
+    bitsy::error::{BitsyError, BitsyErrorKind},
+    save::SaveFile,
+    transfer::{self, Destination, PanelSizes, Placement},
+};
+
+use crate::{
+    args::Args,
+    files::{describe_item, parse_item_ref, parse_size, save_all_changes, save_changes, Session},
+    CommandResult, Failure,
+};
+
+// Moves that can't be done are the user's to fix, anything else is a problem with the file.
+fn failure(error: BitsyError) -> Failure {
+    match error.kind() {
+        BitsyErrorKind::InvalidAction(message) => Failure::usage(message),
+        _ => Failure::input(error),
+    }
+}
+
+fn parse_destination(args: &Args, target: &SaveFile) -> Result<Destination, Failure> {
+    let page = args
+        .option("page")
+        .map(|page| match page.parse::<usize>() {
+            Ok(page) if page > 0 => Ok(page - 1),
+            _ => Err(Failure::usage(format!("Invalid page '{}'", page))),
+        })
+        .transpose()?;
+    match (target, args.option("to"), page) {
+        (SaveFile::Stash(_), None, page) => Ok(Destination::Page(page)),
+        (SaveFile::Stash(_), Some(_), _) => Err(Failure::usage(
+            "Stashes only have pages, use --page instead of --to",
+        )),
+        (SaveFile::Player(_), _, Some(_)) => Err(Failure::usage(
+            "Characters have no pages, use --to instead of --page",
+        )),
+        (SaveFile::Player(_), to, None) => match to.unwrap_or("inventory") {
+            "inventory" => Ok(Destination::Inventory),
+            "cube" => Ok(Destination::Cube),
+            "stash" => Ok(Destination::Stash),
+            to => Err(Failure::usage(format!(
+                "Invalid destination '{}', expected inventory, cube or stash",
+                to
+            ))),
+        },
+    }
+}
+
+// The size given with --size replaces that of the panel the item goes to.
+fn panel_sizes(args: &Args, target: &SaveFile, to: Destination) -> Result<PanelSizes, Failure> {
+    let mut sizes = PanelSizes::for_version(target.version());
+    if let Some(size) = args.option("size") {
+        let size = parse_size(size)?;
+        match to {
+            Destination::Inventory => sizes.inventory = size,
+            Destination::Cube => sizes.cube = size,
+            Destination::Stash => sizes.stash = size,
+            Destination::Page(_) => sizes.page = size,
+        }
+    }
+    Ok(sizes)
+}
+
+fn describe_placement(target: &SaveFile, placement: &Placement) -> String {
+    let place = match target {
+        SaveFile::Player(_) => placement.container.name().to_string(),
+        SaveFile::Stash(_) => target.item_lists()[placement.list].0.clone(),
+    };
+    format!("{} ({}, {})", place, placement.x, placement.y)
+}
+
+// Whether two paths name the same file, like `a.sss` and `./a.sss`. Loading it twice would write
+// the item removed and then the copy with it added, duplicating it.
+fn same_file(a: &str, b: &str) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(
+        args,
+        &["write", "dry-run"],
+        &["to", "page", "size", "backups", "journal"],
+    )?;
+    let source_path = args.positional("file")?;
+    let (list, index) = parse_item_ref(&args.positional("item")?)?;
+    let destination_path = args.positional("destination")?;
+    args.finish()?;
+    if args.flag("dry-run") && args.flag("write") {
+        return Err(Failure::usage("Use either --dry-run or --write"));
+    }
+
+    let mut session = Session::new(&args);
+    let mut source = session.load(&source_path)?;
+    let mut destination = if same_file(&source_path, &destination_path) {
+        None
+    } else {
+        Some(session.load(&destination_path)?)
+    };
+    let target = destination
+        .as_ref()
+        .map_or(&source.save, |loaded| &loaded.save);
+    let to = parse_destination(&args, target)?;
+    let sizes = panel_sizes(&args, target, to)?;
+
+    let item = source
+        .save
+        .item_lists()
+        .get(list)
+        .and_then(|(_, items)| items.items().get(index))
+        .map(describe_item)
+        .ok_or_else(|| {
+            Failure::usage(format!(
+                "There is no item {} in list {}",
+                index + 1,
+                list + 1
+            ))
+        })?;
+    let placement = transfer::plan(
+        &source.save,
+        list,
+        index,
+        destination.as_ref().map(|loaded| &loaded.save),
+        to,
+        &sizes,
+    )
+    .map_err(failure)?;
+    let placement = match placement {
+        Some(placement) => placement,
+        None => {
+            println!("There is no room for {} in {}", item, destination_path);
+            return Ok(false);
+        }
+    };
+    let place = describe_placement(target, &placement);
+    if args.flag("dry-run") {
+        println!("Would move {} to {} {}", item, destination_path, place);
+        return Ok(true);
+    }
+
+    transfer::apply(
+        &mut source.save,
+        list,
+        index,
+        destination.as_mut().map(|loaded| &mut loaded.save),
+        &placement,
+    )
+    .map_err(failure)?;
+    let description = format!(
+        "Moved {} from {} to {} {}",
+        item, source_path, destination_path, place
+    );
+    println!("{}", description);
+    session.begin("move", &description);
+
+    match &destination {
+        Some(destination) => save_all_changes(&args, &mut session, &[&source, destination])?,
+        None => save_changes(&args, &mut session, &source)?,
+    }
+    Ok(true)
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+
+use d2_itemsorter::{bitsy::error::BitsyErrorKind, query::Query, save::find_saves};
+
+use crate::{
+    args::Args,
+    files::{describe_item, describe_place, item_ref, Session},
+    CommandResult, Failure,
+};
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &[], &[])?;
+    let query = args.positional("query")?;
+    let query = Query::parse(&query).map_err(|error| match error.kind() {
+        BitsyErrorKind::InvalidData(message) => {
+            Failure::usage(format!("Invalid query: {}", message))
+        }
+        kind => Failure::usage(kind),
+    })?;
+    let paths = args.rest("file or directory")?;
+    let mut session = Session::new(&args);
+    let mut found = false;
+    for path in paths {
+        for save in find_saves(Path::new(&path)).map_err(|error| Failure::input(error.kind()))? {
+            let path = save.display().to_string();
+            // One broken save doesn't stop the search through the others.
+            let loaded = match session.load(&path) {
+                Ok(loaded) => loaded,
+                Err(Failure::Input(message)) => {
+                    eprintln!("d2items: {}", message);
+                    continue;
+                }
+                Err(failure) => return Err(failure),
+            };
+            for (list_index, (label, list)) in loaded.save.item_lists().into_iter().enumerate() {
+                for (index, item) in list.items().iter().enumerate() {
+                    let reference = item_ref(list_index, index);
+                    if query.matches(item) {
+                        found = true;
+                        println!(
+                            "{}  {}  {}  {}  {}",
+                            path,
+                            label,
+                            describe_place(item),
+                            reference,
+                            describe_item(item)
+                        );
+                    }
+                    for socketed in item.socketed_items() {
+                        if query.matches(socketed) {
+                            found = true;
+                            println!(
+                                "{}  {}  {} in {}  {}  {}",
+                                path,
+                                label,
+                                describe_place(socketed),
+                                describe_place(item),
+                                reference,
+                                describe_item(socketed)
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(found)
+}
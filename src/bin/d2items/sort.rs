@@ -0,0 +1,44 @@
+use d2_itemsorter::{
+    save::{page_label, SaveFile},
+    sort::sort_items,
+    stash::{PAGE_HEIGHT, PAGE_WIDTH},
+};
+
+use crate::{
+    args::Args,
+    files::{save_changes, Session},
+    CommandResult, Failure,
+};
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &["write"], &["output", "backups", "journal"])?;
+    let path = args.positional("file")?;
+    args.finish()?;
+    let mut session = Session::new(&args);
+    let mut loaded = session.load(&path)?;
+    let stash = match &mut loaded.save {
+        SaveFile::Stash(stash) => stash,
+        SaveFile::Player(_) => {
+            return Err(Failure::usage("Only stashes can be sorted for now"));
+        }
+    };
+    let mut sorted_any = false;
+    for (index, page) in stash.pages_mut().iter_mut().enumerate() {
+        let label = page_label(index, page.name());
+        // Nothing is moved on pages that can't be sorted as a whole.
+        match sort_items(page.items_mut(), PAGE_WIDTH, PAGE_HEIGHT) {
+            Ok(()) => {
+                sorted_any = true;
+                println!("{}: sorted", label);
+            }
+            Err(error) => println!("{}: left as is: {}", label, error.kind()),
+        }
+    }
+    if !sorted_any {
+        println!("No page could be sorted.");
+        return Ok(false);
+    }
+    session.begin("sort", &format!("Sorted {}", path));
+    save_changes(&args, &mut session, &loaded)?;
+    Ok(true)
+}
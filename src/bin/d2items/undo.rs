@@ -0,0 +1,59 @@
+use d2_itemsorter::{
+    bitsy::error::{BitsyError, BitsyErrorKind},
+    journal::Undone,
+};
+
+use crate::{
+    args::Args,
+    files::{write_options, Session},
+    CommandResult, Failure,
+};
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let args = Args::parse(args, &["dry-run", "force"], &["backups", "journal"])?;
+    args.finish()?;
+    let journal = Session::new(&args).journal()?;
+    let failed = |error: BitsyError| match error.kind() {
+        BitsyErrorKind::InvalidAction(message) => Failure::usage(message),
+        kind => Failure::input(format!(
+            "Could not undo with {}: {}",
+            journal.path().display(),
+            kind
+        )),
+    };
+
+    let entries = journal.plan_undo(args.flag("force")).map_err(failed)?;
+    let last = match entries.first() {
+        Some(last) => last,
+        None => {
+            println!("Nothing to undo in {}", journal.path().display());
+            return Ok(false);
+        }
+    };
+    if args.flag("dry-run") {
+        println!("Would undo {}: {}", last.command, last.description);
+        for entry in &entries {
+            match &entry.backup {
+                Some(backup) => println!(
+                    "  {} would be restored from {}",
+                    entry.path.display(),
+                    backup.display()
+                ),
+                None => println!("  {} would be removed", entry.path.display()),
+            }
+        }
+        return Ok(true);
+    }
+
+    let undone = journal
+        .undo(args.flag("force"), &write_options(&args)?)
+        .map_err(failed)?;
+    println!("Undid {}: {}", last.command, last.description);
+    for file in undone {
+        match file {
+            Undone::Restored(path) => println!("  Restored {}", path.display()),
+            Undone::Removed(path) => println!("  Removed {}", path.display()),
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use d2_itemsorter::{
+    save::{find_saves, SaveFile},
+    validate::{duplicate_guids, validate},
+};
+
+use crate::{
+    args::Args,
+    files::{panel_sizes, Session, PANEL_SIZE_OPTIONS},
+    CommandResult, Failure,
+};
+
+pub fn run(args: Vec<String>) -> CommandResult {
+    let mut args = Args::parse(args, &[], &PANEL_SIZE_OPTIONS)?;
+    let paths = args.rest("file or directory")?;
+    let mut session = Session::new(&args);
+    let mut files = Vec::new();
+    for path in &paths {
+        files.extend(find_saves(Path::new(path)).map_err(|error| Failure::input(error.kind()))?);
+    }
+
+    let mut valid = true;
+    let mut saves = Vec::new();
+    for file in &files {
+        let path = file.display().to_string();
+        let problems = match session.load(&path) {
+            Ok(loaded) => {
+                let sizes = panel_sizes(&args, &loaded.save)?;
+                let problems: Vec<String> = validate(&loaded.save, &loaded.bytes, &sizes)
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect();
+                saves.push((path.clone(), loaded.save));
+                problems
+            }
+            // Files that can't be parsed are invalid, not a reason to stop.
+            Err(Failure::Input(message)) => vec![message],
+            Err(failure) => return Err(failure),
+        };
+        if problems.is_empty() {
+            println!("{}: ok", path);
+        }
+        for problem in &problems {
+            println!("{}: {}", path, problem);
+        }
+        valid &= problems.is_empty();
+    }
+
+    let saves: Vec<(String, &SaveFile)> = saves
+        .iter()
+        .map(|(path, save)| (path.clone(), save))
+        .collect();
+    for (guid, users) in duplicate_guids(&saves) {
+        let users: Vec<String> = users
+            .iter()
+            .map(|(path, item)| format!("{} {}", path, item))
+            .collect();
+        println!("GUID {} is used by {}", guid, users.join(", "));
+        valid = false;
+    }
+    Ok(valid)
+}
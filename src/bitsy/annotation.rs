@@ -0,0 +1,238 @@
+use serde::Serialize;
+
+use super::error::PathSegment;
+
+// The bits a parsed field was read from, along with the fields parsed inside it. Paths use the
+// same format as error paths, e.g. `.items[4].extended_info.quality`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Annotation {
+    pub path: String,
+    pub start: usize,
+    pub end: usize,
+    // False if parsing the field failed, in which case `end` is where the parser gave up.
+    pub complete: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Annotation>,
+}
+
+impl Annotation {
+    fn new(path: String, start: usize) -> Self {
+        Self {
+            path,
+            start,
+            end: start,
+            complete: true,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn bit_count(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn find(&self, path: &str) -> Option<&Annotation> {
+        if self.path == path {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(path))
+    }
+
+    // The annotations without children, in bit order.
+    pub fn leaves(&self) -> Vec<&Annotation> {
+        if self.children.is_empty() {
+            return vec![self];
+        }
+        self.children
+            .iter()
+            .flat_map(|child| child.leaves())
+            .collect()
+    }
+
+    // The most nested annotation covering `bit`.
+    pub fn innermost_at(&self, bit: usize) -> Option<&Annotation> {
+        if bit < self.start || bit >= self.end {
+            return None;
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.innermost_at(bit))
+            .or(Some(self))
+    }
+}
+
+// Builds the annotation tree while parsing. Every `begin` is matched by an `end` once the field
+// has been read, whether it succeeded or not.
+#[derive(Debug)]
+pub(crate) struct Annotator {
+    stack: Vec<Annotation>,
+}
+
+impl Annotator {
+    pub fn new(start: usize) -> Self {
+        Self {
+            stack: vec![Annotation::new(String::new(), start)],
+        }
+    }
+
+    pub fn begin(&mut self, segment: PathSegment, index: usize) {
+        let path = format!("{}{}", self.stack[self.stack.len() - 1].path, segment);
+        self.stack.push(Annotation::new(path, index));
+    }
+
+    pub fn end(&mut self, index: usize, complete: bool) {
+        if self.stack.len() < 2 {
+            return;
+        }
+        let mut annotation = self.stack.remove(self.stack.len() - 1);
+        annotation.end = index;
+        annotation.complete = complete;
+        let parent_index = self.stack.len() - 1;
+        let parent = &mut self.stack[parent_index];
+        match parent.children.last_mut() {
+            // Lists are read one element at a time, so consecutive reads of the same field are
+            // merged back into one.
+            Some(previous)
+                if previous.path == annotation.path
+                    && previous.end == annotation.start
+                    && previous.complete =>
+            {
+                previous.end = annotation.end;
+                previous.complete = annotation.complete;
+                previous.children.extend(annotation.children);
+            }
+            _ => parent.children.push(annotation),
+        }
+    }
+
+    pub fn finish(mut self, index: usize) -> Annotation {
+        while self.stack.len() > 1 {
+            self.end(index, false);
+        }
+        let mut root = self.stack.remove(0);
+        root.end = index;
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitsy::{context, structs::BitsyInt, testutils::bits, BitReader, BitVecReader, Bitsy},
+        item::{ear::Ear, info::MapItemDb, properties::MapPropertyDb, ItemList},
+    };
+    use std::rc::Rc;
+
+    #[derive(Debug, Bitsy)]
+    struct Inner {
+        a: BitsyInt<u8, 3>,
+        b: bool,
+    }
+
+    #[derive(Debug, Bitsy)]
+    struct Outer {
+        first: bool,
+        inner: Inner,
+        last: BitsyInt<u8, 4>,
+    }
+
+    #[test]
+    fn it_records_nested_fields() {
+        let mut reader = BitVecReader::new(bits("1 101 1 0110"));
+        reader.record_annotations();
+
+        let _: Outer = reader.read().unwrap();
+        let root = reader.take_annotations().unwrap();
+
+        assert_eq!((0, 9), (root.start, root.end));
+        let paths: Vec<_> = root
+            .leaves()
+            .iter()
+            .map(|a| (a.path.as_str(), a.start, a.end))
+            .collect();
+        assert_eq!(
+            vec![
+                (".first", 0, 1),
+                (".inner.a", 1, 4),
+                (".inner.b", 4, 5),
+                (".last", 5, 9)
+            ],
+            paths
+        );
+        assert_eq!(".inner.a", root.innermost_at(1).unwrap().path);
+        assert_eq!(".inner.b", root.innermost_at(4).unwrap().path);
+        assert_eq!(4, root.find(".inner").unwrap().bit_count());
+    }
+
+    #[test]
+    fn it_marks_failed_fields() {
+        let mut reader = BitVecReader::new(bits("1 10"));
+        reader.record_annotations();
+
+        assert!(reader.read::<Outer>().is_err());
+        let root = reader.take_annotations().unwrap();
+
+        let inner = root.find(".inner").unwrap();
+        assert!(!inner.complete);
+        assert!(!root.find(".inner.a").unwrap().complete);
+        assert!(root.find(".first").unwrap().complete);
+    }
+
+    #[test]
+    fn it_ignores_peeks() {
+        let mut reader = BitVecReader::new(bits("1 101 1 0110"));
+        reader.record_annotations();
+
+        let _: Outer = reader.peek().unwrap();
+        let root = reader.take_annotations().unwrap();
+
+        assert!(root.children.is_empty());
+    }
+
+    #[test]
+    fn it_exports_json() {
+        let mut reader = BitVecReader::new(bits("100 1100100 01000010 00010110 00000000"));
+        reader.set_context(&context::VERSION, 99);
+        reader.record_annotations();
+
+        let _: Ear = reader.read().unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&reader.take_annotations().unwrap().to_json().unwrap()).unwrap();
+
+        assert_eq!(".level", json["children"][1]["path"]);
+        assert_eq!(3, json["children"][1]["start"]);
+        assert_eq!(10, json["children"][1]["end"]);
+    }
+
+    #[test]
+    fn it_annotates_items() {
+        let bytes = std::fs::read("small_stash.sss").unwrap();
+        let mut reader = BitVecReader::with_item_db(
+            crate::bitsy::MyBitVec::from_vec(bytes),
+            Rc::new(MapItemDb::from_data_dir("data/items")),
+        );
+        reader.set_property_db(Rc::new(MapPropertyDb::new()));
+        reader.set_context(&context::VERSION, 96);
+        let _: [u8; 14] = reader.read().unwrap();
+
+        // Skips to the first page with items.
+        let (list, root) = loop {
+            let _: [u8; 3] = reader.read().unwrap();
+            reader.record_annotations();
+            let list: ItemList = reader.read().unwrap();
+            let root = reader.take_annotations().unwrap();
+            if !list.items().is_empty() {
+                break (list, root);
+            }
+        };
+
+        assert_eq!(list.items().len(), root.children.len());
+        assert_eq!(32, root.find("[0].item_type").unwrap().bit_count());
+        let leaves = root.leaves();
+        assert!(leaves.iter().all(|leaf| leaf.complete));
+        assert!(leaves.windows(2).all(|pair| pair[0].end <= pair[1].start));
+    }
+}
@@ -0,0 +1,50 @@
+// Serializes bits as a string of 0s and 1s in reading order, for `#[serde(with = "...")]`.
+use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+use super::{bits_from_str, MyBitSlice, MyBitVec};
+
+pub fn to_string(bits: &MyBitSlice) -> String {
+    bits.iter()
+        .map(|bit| if *bit { '1' } else { '0' })
+        .collect()
+}
+
+pub fn serialize<S: Serializer>(bits: &MyBitSlice, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&to_string(bits))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MyBitVec, D::Error> {
+    let string = String::deserialize(deserializer)?;
+    bits_from_str(string).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::bitsy::testutils::bits;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper {
+        #[serde(with = "super")]
+        bits: MyBitVec,
+    }
+
+    #[test]
+    fn it_roundtrips() {
+        let wrapper = Wrapper {
+            bits: bits("0110 1"),
+        };
+
+        let json = serde_json::to_string(&wrapper).unwrap();
+
+        assert_eq!(r#"{"bits":"01101"}"#, json);
+        assert_eq!(
+            bits("01101"),
+            serde_json::from_str::<Wrapper>(&json).unwrap().bits
+        );
+        assert!(serde_json::from_str::<Wrapper>(r#"{"bits":"012"}"#).is_err());
+    }
+}
@@ -1,79 +1,124 @@
 use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
     collections::HashMap,
-    fmt::Debug,
+    fmt::{self, Debug},
+    marker::PhantomData,
     rc::Rc,
-    sync::{Mutex, MutexGuard},
 };
 
-use serde::{de::DeserializeOwned, Serialize};
-
 use crate::{item::info::ItemInfo, quality::QualityId};
 
-pub trait ContextValue: Serialize + DeserializeOwned + Debug + 'static {}
-impl<T: Serialize + DeserializeOwned + Debug + 'static> ContextValue for T {}
+use super::lenient::Diagnostics;
+
+pub trait ContextValue: Clone + Debug + 'static {}
+impl<T: Clone + Debug + 'static> ContextValue for T {}
 
-#[derive(Debug, Clone)]
+// `fn() -> T` keeps keys `Sync` so they can be statics, whatever `T` is.
 pub struct ContextKey<T: ContextValue> {
-    key: String,
-    _phantom: std::marker::PhantomData<T>,
+    key: &'static str,
+    _phantom: PhantomData<fn() -> T>,
 }
 
 impl<T: ContextValue> ContextKey<T> {
-    pub fn new(key: &str) -> Self {
+    pub const fn new(key: &'static str) -> Self {
         Self {
-            key: key.to_string(),
-            _phantom: std::marker::PhantomData,
+            key,
+            _phantom: PhantomData,
         }
     }
 
-    pub fn as_str(&self) -> &str {
-        &self.key
+    pub fn as_str(&self) -> &'static str {
+        self.key
+    }
+
+    fn slot(&self) -> Slot {
+        (self.key, TypeId::of::<T>())
     }
 }
 
-lazy_static::lazy_static! {
-    pub static ref VERSION: ContextKey<u32> = ContextKey::new("version");
-    pub static ref HAS_SOCKETS: ContextKey<bool> = ContextKey::new("has_sockets");
-    pub static ref HAS_RUNEWORD: ContextKey<bool> = ContextKey::new("has_runeword");
-    pub static ref ITEM_INFO: ContextKey<ItemInfo> = ContextKey::new("item_info");
-    pub static ref QUALITY_ID: ContextKey<QualityId> = ContextKey::new("quality_id");
+impl<T: ContextValue> Debug for ContextKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ContextKey").field(&self.key).finish()
+    }
 }
 
+pub static VERSION: ContextKey<u32> = ContextKey::new("version");
+pub static HAS_SOCKETS: ContextKey<bool> = ContextKey::new("has_sockets");
+pub static HAS_RUNEWORD: ContextKey<bool> = ContextKey::new("has_runeword");
+pub static IS_PERSONALIZED: ContextKey<bool> = ContextKey::new("is_personalized");
+// Shared so that reading it back for every item doesn't copy the item's names and codes.
+pub static ITEM_INFO: ContextKey<Rc<ItemInfo>> = ContextKey::new("item_info");
+pub static QUALITY_ID: ContextKey<QualityId> = ContextKey::new("quality_id");
+// Set for lenient reads, which report items they can't parse here instead of failing.
+pub static DIAGNOSTICS: ContextKey<Diagnostics> = ContextKey::new("diagnostics");
+
+// Values are keyed by both name and type, so a key can never be read back as another type.
+type Slot = (&'static str, TypeId);
+type Slots = HashMap<Slot, Rc<dyn Any>>;
+
+// The context values at some point in time. Taking a snapshot only clones an `Rc`; the slots are
+// copied the first time the context changes after that.
+#[derive(Clone)]
+pub struct ContextSnapshot(Rc<Slots>);
+
+impl Debug for ContextSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries(self.0.keys().map(|(key, _)| key))
+            .finish()
+    }
+}
+
+// Cloning a `ContextMap` gives an independent copy of the current values.
 #[derive(Debug)]
 pub struct ContextMap {
-    map: Rc<Mutex<HashMap<String, serde_json::Value>>>,
+    current: Rc<RefCell<ContextSnapshot>>,
 }
 
 impl Clone for ContextMap {
     fn clone(&self) -> Self {
         Self {
-            map: Rc::new(Mutex::new(self.lock().clone())),
+            current: Rc::new(RefCell::new(self.snapshot())),
         }
     }
 }
 
+impl Default for ContextMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ContextMap {
     pub fn new() -> Self {
         Self {
-            map: Rc::new(Mutex::new(HashMap::new())),
+            current: Rc::new(RefCell::new(ContextSnapshot(Rc::new(HashMap::new())))),
         }
     }
 
-    fn lock(&self) -> MutexGuard<HashMap<String, serde_json::Value>> {
-        self.map.lock().unwrap_or_else(|err| err.into_inner())
+    pub fn snapshot(&self) -> ContextSnapshot {
+        self.current.borrow().clone()
+    }
+
+    pub fn restore(&self, snapshot: ContextSnapshot) {
+        *self.current.borrow_mut() = snapshot;
     }
 
     pub fn context_reset(&self) -> ContextResetGuard {
         ContextResetGuard {
-            map: self.map.clone(),
-            initial: self.lock().clone(),
+            current: self.current.clone(),
+            initial: self.snapshot(),
         }
     }
 
     pub fn get_context<T: ContextValue>(&self, key: &ContextKey<T>) -> Option<T> {
-        self.lock()
-            .get(key.as_str())
-            .map(|x| serde_json::from_value::<T>(x.clone()).unwrap())
+        self.current
+            .borrow()
+            .0
+            .get(&key.slot())
+            .and_then(|value| value.downcast_ref::<T>())
+            .cloned()
     }
 
     pub fn set_context<T: ContextValue, V: Into<Option<T>>>(
@@ -81,29 +126,68 @@ impl ContextMap {
         key: &ContextKey<T>,
         value: V,
     ) -> Option<T> {
-        let mut map = self.lock();
-        let previous = if let Some(value) = value.into() {
-            map.insert(
-                key.as_str().to_string(),
-                serde_json::to_value(value).unwrap(),
-            )
-        } else {
-            map.remove(key.as_str())
+        let mut current = self.current.borrow_mut();
+        let slots = Rc::make_mut(&mut current.0);
+        let previous = match value.into() {
+            Some(value) => slots.insert(key.slot(), Rc::new(value)),
+            None => slots.remove(&key.slot()),
         };
-        previous.map(|json| serde_json::from_value::<T>(json).unwrap())
+        previous.and_then(|value| value.downcast_ref::<T>().cloned())
     }
 }
 
+// Restores the context to what it was when the guard was created.
 #[must_use = "if unused the context will be reverted immediately"]
 #[derive(Debug)]
 pub struct ContextResetGuard {
-    map: Rc<Mutex<HashMap<String, serde_json::Value>>>,
-    initial: HashMap<String, serde_json::Value>,
+    current: Rc<RefCell<ContextSnapshot>>,
+    initial: ContextSnapshot,
 }
 
 impl Drop for ContextResetGuard {
     fn drop(&mut self) {
-        let mut map = self.map.lock().unwrap_or_else(|err| err.into_inner());
-        *map = self.initial.clone();
+        *self.current.borrow_mut() = self.initial.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_resets_the_context() {
+        let context = ContextMap::new();
+        context.set_context(&VERSION, 96);
+        {
+            let _reset = context.context_reset();
+            assert_eq!(Some(96), context.set_context(&VERSION, 97));
+            context.set_context(&HAS_SOCKETS, true);
+            assert_eq!(Some(97), context.get_context(&VERSION));
+        }
+        assert_eq!(Some(96), context.get_context(&VERSION));
+        assert_eq!(None, context.get_context(&HAS_SOCKETS));
+    }
+
+    #[test]
+    fn it_restores_snapshots_in_place() {
+        let context = ContextMap::new();
+        let _reset = context.context_reset();
+        let snapshot = context.snapshot();
+        context.set_context(&HAS_RUNEWORD, true);
+        let copy = context.clone();
+        context.restore(snapshot);
+
+        assert_eq!(None, context.get_context(&HAS_RUNEWORD));
+        assert_eq!(Some(true), copy.get_context(&HAS_RUNEWORD));
+    }
+
+    #[test]
+    fn it_keeps_types_apart() {
+        let context = ContextMap::new();
+        let other: ContextKey<bool> = ContextKey::new("version");
+        context.set_context(&VERSION, 96);
+
+        assert_eq!(None, context.get_context(&other));
+        assert_eq!(Some(96), context.get_context(&VERSION));
     }
 }
@@ -0,0 +1,191 @@
+use std::{fmt::Write, ops::Range};
+
+use super::{annotation::Annotation, reader::int_to_printable_char, MyBitSlice};
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const PALETTE: [&str; 6] = [
+    "\x1b[31m", "\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m",
+];
+
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    // Colours every bit after the field it belongs to, with ANSI escapes.
+    pub color: bool,
+    // The bytes to print. Defaults to the whole input.
+    pub bytes: Option<Range<usize>>,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self {
+            color: true,
+            bytes: None,
+        }
+    }
+}
+
+// Prints one line per byte with its byte and bit offsets, its bits in reading order, and its hex
+// and ASCII values. With annotations, every field that starts in the byte is listed next to it,
+// and bytes with bits that no field covers are dimmed (or marked with `?` without colours).
+pub fn render_dump(
+    bits: &MyBitSlice,
+    annotations: Option<&Annotation>,
+    options: &DumpOptions,
+) -> String {
+    let byte_count = bits.len().div_ceil(8);
+    let range = options
+        .bytes
+        .clone()
+        .map(|range| range.start.min(byte_count)..range.end.min(byte_count))
+        .unwrap_or(0..byte_count);
+
+    let leaves = annotations.map(|a| a.leaves()).unwrap_or_default();
+    let mut nodes = Vec::new();
+    if let Some(annotations) = annotations {
+        collect_nodes(annotations, &mut nodes);
+    }
+
+    let mut output = String::new();
+    output.push_str("  offset      bit  binary    hx  c  fields\n");
+    let mut leaf_index = 0;
+    let mut node_index = nodes.partition_point(|node| node.start < range.start * 8);
+    for byte in range {
+        let start = byte * 8;
+        let end = (start + 8).min(bits.len());
+        let _ = write!(output, "0x{:06X} {:>8}  ", byte, start);
+
+        let mut uncovered = false;
+        for bit in start..end {
+            while leaf_index < leaves.len() && leaves[leaf_index].end <= bit {
+                leaf_index += 1;
+            }
+            let leaf = leaves
+                .get(leaf_index)
+                .filter(|leaf| leaf.start <= bit)
+                .map(|_| leaf_index);
+            let value = if bits[bit] { '1' } else { '0' };
+            match (options.color, leaf) {
+                (true, Some(leaf)) => {
+                    let _ = write!(
+                        output,
+                        "{}{}{}",
+                        PALETTE[leaf % PALETTE.len()],
+                        value,
+                        RESET
+                    );
+                }
+                (true, None) if annotations.is_some() => {
+                    let _ = write!(output, "{}{}{}", DIM, value, RESET);
+                }
+                _ => output.push(value),
+            }
+            if leaf.is_none() {
+                uncovered = true;
+            }
+        }
+        output.push_str(&" ".repeat(8 - (end - start)));
+
+        let value = bits[start..end]
+            .iter()
+            .enumerate()
+            .fold(0u32, |value, (index, bit)| value | (*bit as u32) << index);
+        let _ = write!(output, "  {:02X}  {}", value, int_to_printable_char(value));
+
+        let mut labels = Vec::new();
+        if uncovered && annotations.is_some() && !options.color {
+            labels.push("?".to_string());
+        }
+        while node_index < nodes.len() && nodes[node_index].start < end {
+            let node = nodes[node_index];
+            labels.push(format!(
+                "{} ({} bits{})",
+                node.path,
+                node.bit_count(),
+                if node.complete { "" } else { ", failed" }
+            ));
+            node_index += 1;
+        }
+        if !labels.is_empty() {
+            let _ = write!(output, "  {}", labels.join(", "));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// Every annotation except the root and empty fields, ordered by start bit (parents before their
+// children).
+fn collect_nodes<'a>(annotation: &'a Annotation, nodes: &mut Vec<&'a Annotation>) {
+    for child in &annotation.children {
+        if child.bit_count() > 0 || !child.complete {
+            nodes.push(child);
+        }
+        collect_nodes(child, nodes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitsy::{structs::BitsyInt, testutils::bits, BitReader, BitVecReader, Bitsy};
+
+    use super::*;
+
+    #[derive(Debug, Bitsy)]
+    struct Header {
+        magic: BitsyInt<u8, 4>,
+        size: BitsyInt<u16, 10>,
+    }
+
+    #[test]
+    fn it_prints_bytes() {
+        let bits = bits("10000010 01001010");
+
+        let dump = render_dump(
+            &bits,
+            None,
+            &DumpOptions {
+                color: false,
+                bytes: None,
+            },
+        );
+
+        let lines: Vec<_> = dump.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!("0x000000        0  10000010  41  A", lines[1]);
+        assert_eq!("0x000001        8  01001010  52  R", lines[2]);
+    }
+
+    #[test]
+    fn it_labels_fields() {
+        let bits = bits("10000010 01001010 1");
+        let mut reader = BitVecReader::new(bits.clone());
+        reader.record_annotations();
+        let _: Header = reader.read().unwrap();
+        let annotations = reader.take_annotations().unwrap();
+
+        let dump = render_dump(
+            &bits,
+            Some(&annotations),
+            &DumpOptions {
+                color: false,
+                bytes: Some(1..10),
+            },
+        );
+
+        let lines: Vec<_> = dump.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!("0x000001        8  01001010  52  R  ?", lines[1]);
+        assert_eq!("0x000002       16  1         01  .  ?", lines[2]);
+
+        let dump = render_dump(
+            &bits,
+            Some(&annotations),
+            &DumpOptions {
+                color: false,
+                bytes: None,
+            },
+        );
+        assert!(dump.contains("  41  A  .magic (4 bits), .size (10 bits)"));
+    }
+}
@@ -3,7 +3,12 @@ use std::{
     fmt::{Debug, Display},
 };
 
-#[derive(Debug)]
+use serde::{Serialize, Serializer};
+
+use super::{show_bitslice_around, MyBitSlice};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
 pub enum PathSegment {
     Index(usize),
     Name(String),
@@ -18,25 +23,54 @@ impl Display for PathSegment {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail")]
 pub enum BitsyErrorKind {
     EndOfData,
     InvalidData(String),
     MissingVersion,
     MissingContext(String),
     InvalidAction(String),
+    Io(String),
 }
 
 impl BitsyErrorKind {
     pub fn at_bit(self, bit: usize) -> BitsyError {
         BitsyError::new(self, bit)
     }
+
+    // A stable identifier for tools that react to specific errors.
+    pub fn code(&self) -> &'static str {
+        match self {
+            BitsyErrorKind::EndOfData => "E001",
+            BitsyErrorKind::InvalidData(_) => "E002",
+            BitsyErrorKind::MissingVersion => "E003",
+            BitsyErrorKind::MissingContext(_) => "E004",
+            BitsyErrorKind::InvalidAction(_) => "E005",
+            BitsyErrorKind::Io(_) => "E006",
+        }
+    }
+}
+
+impl Display for BitsyErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitsyErrorKind::EndOfData => write!(f, "Unexpected end of data"),
+            BitsyErrorKind::InvalidData(message) => write!(f, "Invalid data: {}", message),
+            BitsyErrorKind::MissingVersion => write!(f, "Missing version"),
+            BitsyErrorKind::MissingContext(key) => write!(f, "Missing context value {}", key),
+            BitsyErrorKind::InvalidAction(message) => write!(f, "Invalid action: {}", message),
+            BitsyErrorKind::Io(message) => write!(f, "I/O error: {}", message),
+        }
+    }
 }
 
 pub struct BitsyError {
     kind: BitsyErrorKind,
     bit: usize,
+    // Innermost segment first, since segments are added as the error bubbles up.
     path: Vec<PathSegment>,
+    source: Option<String>,
 }
 
 impl BitsyError {
@@ -45,8 +79,73 @@ impl BitsyError {
             kind,
             bit,
             path: Vec::new(),
+            source: None,
         }
     }
+
+    pub fn kind(&self) -> &BitsyErrorKind {
+        &self.kind
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    pub fn bit(&self) -> usize {
+        self.bit
+    }
+
+    pub fn byte(&self) -> usize {
+        self.bit / 8
+    }
+
+    // The path of the field that failed, outermost segment first.
+    pub fn path(&self) -> impl Iterator<Item = &PathSegment> {
+        self.path.iter().rev()
+    }
+
+    pub fn path_string(&self) -> String {
+        self.path().map(|s| s.to_string()).collect()
+    }
+
+    // The bits around the failing bit, if the error was given its input with `with_source`.
+    pub fn source_context(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl Serialize for BitsyError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Fields<'a> {
+            code: &'static str,
+            #[serde(flatten)]
+            kind: &'a BitsyErrorKind,
+            message: String,
+            bit: usize,
+            byte: usize,
+            path: String,
+            segments: Vec<&'a PathSegment>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            source: Option<&'a str>,
+        }
+
+        Fields {
+            code: self.code(),
+            kind: &self.kind,
+            message: self.kind.to_string(),
+            bit: self.bit,
+            byte: self.byte(),
+            path: self.path_string(),
+            segments: self.path().collect(),
+            source: self.source_context(),
+        }
+        .serialize(serializer)
+    }
 }
 
 impl Debug for BitsyError {
@@ -56,14 +155,13 @@ impl Debug for BitsyError {
             "{:?} at bit {} (byte {}). Path: {}",
             self.kind,
             self.bit,
-            self.bit / 8,
-            self.path
-                .iter()
-                .rev()
-                .map(|s| s.to_string())
-                .collect::<Vec<_>>()
-                .join("")
-        )
+            self.byte(),
+            self.path_string()
+        )?;
+        if let Some(source) = &self.source {
+            write!(f, "\n  Bits: {}", source)?;
+        }
+        Ok(())
     }
 }
 
@@ -78,6 +176,8 @@ impl Error for BitsyError {}
 pub trait BitsyErrorExt {
     fn prepend_path(self, segment: impl Into<String>) -> Self;
     fn prepend_index(self, index: usize) -> Self;
+    // Keeps the bits around the failing bit, for messages.
+    fn with_source(self, bits: &MyBitSlice) -> Self;
 }
 
 impl BitsyErrorExt for BitsyError {
@@ -90,4 +190,41 @@ impl BitsyErrorExt for BitsyError {
         self.path.push(PathSegment::Index(index));
         self
     }
+
+    fn with_source(mut self, bits: &MyBitSlice) -> Self {
+        self.source = Some(show_bitslice_around(bits, self.bit));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitsy::{testutils::bits, BitReader, BitVecReader};
+
+    use super::*;
+
+    #[test]
+    fn it_serializes_errors() {
+        let input = bits("1010");
+        let mut reader = BitVecReader::new(input.clone());
+        let error = reader
+            .read_named("outer", |r| r.read_indexed(2, |r| r.read_int::<u8>(8)))
+            .unwrap_err()
+            .with_source(&input);
+
+        assert_eq!(&BitsyErrorKind::EndOfData, error.kind());
+        assert_eq!(".outer[2]", error.path_string());
+        let json: serde_json::Value = serde_json::from_str(&error.to_json().unwrap()).unwrap();
+        assert_eq!("E001", json["code"]);
+        assert_eq!("EndOfData", json["kind"]);
+        assert_eq!(".outer[2]", json["path"]);
+        assert_eq!(serde_json::json!(["outer", 2]), json["segments"]);
+        assert_eq!(error.source_context().unwrap(), json["source"]);
+
+        let error = BitsyErrorKind::InvalidData("Bad header".to_string()).at_bit(12);
+        let json: serde_json::Value = serde_json::from_str(&error.to_json().unwrap()).unwrap();
+        assert_eq!("Bad header", json["detail"]);
+        assert_eq!(1, json["byte"]);
+        assert!(json.get("source").is_none());
+    }
 }
@@ -1,5 +1,7 @@
 use std::fmt::Debug;
 
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::bitsy::error::{BitsyError, BitsyErrorKind};
 
 use super::{error::BitsyErrorExt, result::BitsyResult, Bitsy};
@@ -129,6 +131,36 @@ impl<const N: usize> Bitsy for HuffmanChars<N> {
     }
 }
 
+impl<const N: usize> Serialize for HuffmanChars<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_string())
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for HuffmanChars<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        let chars: Vec<char> = string.chars().collect();
+        if chars.len() != N {
+            return Err(D::Error::custom(format!(
+                "Expected {} characters, got '{}'",
+                N, string
+            )));
+        }
+        if let Some(c) = chars.iter().find(|c| !HUFFMAN_ENCODE_MAP.contains_key(c)) {
+            return Err(D::Error::custom(format!(
+                "Could not find huffman code for '{}'",
+                c
+            )));
+        }
+        let mut result = [HuffmanChar::default(); N];
+        for (char, c) in result.iter_mut().zip(chars) {
+            char.char = c;
+        }
+        Ok(Self { chars: result })
+    }
+}
+
 impl<const N: usize> Debug for HuffmanChars<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "HC<{}>", self.as_string())
@@ -79,6 +79,16 @@ impl<T: Bitsy> Bitsy for Option<T> {
     }
 }
 
+impl<T: Bitsy> Bitsy for Box<T> {
+    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+        reader.read().map(Box::new)
+    }
+
+    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        writer.write(self.as_ref())
+    }
+}
+
 impl BitSized for MyBitVec {
     fn bit_size(&self) -> usize {
         self.len()
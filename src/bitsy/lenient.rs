@@ -0,0 +1,56 @@
+use std::{
+    cell::RefCell,
+    fmt::{self, Display},
+    rc::Rc,
+};
+
+use serde::Serialize;
+
+use super::{context, error::BitsyError, result::BitsyResult, BitReader, Bitsy};
+
+// Something that couldn't be parsed in lenient mode, and the bits that were skipped because of it.
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub start: usize,
+    pub end: usize,
+    pub error: BitsyError,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Kept bits {}..{} (bytes {}..{}) unparsed: {}",
+            self.start,
+            self.end,
+            self.start / 8,
+            self.end.div_ceil(8),
+            self.error
+        )
+    }
+}
+
+// Shared between all the contexts a lenient read goes through, so that resetting the context
+// after an item doesn't drop what was reported inside it.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics(Rc<RefCell<Vec<Diagnostic>>>);
+
+impl Diagnostics {
+    pub fn report(&self, diagnostic: Diagnostic) {
+        self.0.borrow_mut().push(diagnostic);
+    }
+
+    pub fn take(&self) -> Vec<Diagnostic> {
+        self.0.take()
+    }
+}
+
+// Reads a `T`, turning items that fail to parse into opaque items instead of failing. The reader's
+// context is left as it was before. Errors outside of item lists still fail the read.
+pub fn read_lenient<T: Bitsy, R: BitReader>(reader: &mut R) -> BitsyResult<(T, Vec<Diagnostic>)> {
+    let diagnostics = Diagnostics::default();
+    let _reset = reader.queue_context_reset();
+    reader.set_context(&context::DIAGNOSTICS, diagnostics.clone());
+    let value = reader.read()?;
+    Ok((value, diagnostics.take()))
+}
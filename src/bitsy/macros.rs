@@ -1,6 +1,6 @@
 macro_rules! bitsy_read {
     ($reader:ident $(, $dest:ident $(: $type:ty)?)+ $(,)?) => {
-        $(let $dest $(: $type)? = $crate::bitsy::error::BitsyErrorExt::prepend_path($reader.read(), stringify!($dest))?;)+
+        $(let $dest $(: $type)? = $reader.read_field(stringify!($dest))?;)+
     };
 }
 
@@ -27,7 +27,7 @@ macro_rules! bitsy_cond_read {
     ($reader:ident, $cond:expr $(, $dest:ident $(: $type:ty)?)+ $(,)?) => {
         $(
         let $dest $(: $type)? = if $cond {
-            Some($crate::bitsy::error::BitsyErrorExt::prepend_path($reader.read(), stringify!($dest))?)
+            Some($reader.read_field(stringify!($dest))?)
         } else {
             None
         };
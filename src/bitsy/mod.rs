@@ -1,30 +1,39 @@
+pub mod annotation;
+pub mod bit_string;
 pub mod context;
+pub mod dump;
 pub mod error;
 mod huffman;
 pub mod impls;
+pub mod lenient;
 pub mod macros;
 mod old;
 mod reader;
 pub mod result;
+mod stream;
 pub mod structs;
 mod writer;
 
+use bitvec::field::BitField;
 use std::{
     cmp::min,
     convert::{TryFrom, TryInto},
-    fmt::Debug,
+    fmt::{Debug, Display},
     rc::Rc,
 };
 
+pub use bitsy_derive::Bitsy;
 use context::{ContextKey, ContextResetGuard, ContextValue};
 pub use huffman::{HuffmanChar, HuffmanChars};
 pub use old::*;
 pub use reader::BitVecReader;
+pub use stream::{StreamReader, DEFAULT_LOOKAHEAD};
 pub use writer::BitVecWriter;
 
+use error::BitsyErrorExt;
 use result::BitsyResult;
 
-use crate::item::{info::ItemDb, properties::PropertyDb};
+use crate::item::{info::ItemDb, properties::PropertyDb, runeword::RunewordDb};
 
 pub fn parse_int(bits: &MyBitSlice) -> Result<u32, String> {
     if bits.len() > 32 {
@@ -54,7 +63,8 @@ pub trait BitReader: Sized {
     fn set_context<T: ContextValue>(&mut self, key: &ContextKey<T>, value: T);
 
     fn item_db(&self) -> Rc<dyn ItemDb>;
-    fn property_db(&self) -> impl PropertyDb;
+    fn property_db(&self) -> Rc<dyn PropertyDb>;
+    fn runeword_db(&self) -> Rc<dyn RunewordDb>;
 
     fn read_int<T: TryFrom<u32>>(&mut self, bit_count: usize) -> BitsyResult<T>;
     fn read_bits(&mut self, bit_count: usize) -> BitsyResult<MyBitVec>;
@@ -66,7 +76,32 @@ pub trait BitReader: Sized {
     fn read<T: Bitsy>(&mut self) -> BitsyResult<T> {
         T::parse(self)
     }
+
+    // Runs `read` for the field `name`, prepending the name to the path of its errors. Readers
+    // that record annotations also record the bits it covered.
+    fn read_named<T>(
+        &mut self,
+        name: &str,
+        read: impl FnOnce(&mut Self) -> BitsyResult<T>,
+    ) -> BitsyResult<T> {
+        read(self).prepend_path(name)
+    }
+    fn read_indexed<T>(
+        &mut self,
+        index: usize,
+        read: impl FnOnce(&mut Self) -> BitsyResult<T>,
+    ) -> BitsyResult<T> {
+        read(self).prepend_index(index)
+    }
+    fn read_field<T: Bitsy>(&mut self, name: &str) -> BitsyResult<T> {
+        self.read_named(name, Self::read)
+    }
+    fn read_element<T: Bitsy>(&mut self, index: usize) -> BitsyResult<T> {
+        self.read_indexed(index, Self::read)
+    }
     fn peek<T: Bitsy>(&mut self) -> BitsyResult<T>;
+    // Like `read`, but leaves the reader (and its context) where it was if reading fails.
+    fn try_read<T: Bitsy>(&mut self) -> BitsyResult<T>;
     fn search(&self, needle: &MyBitVec, offset: usize) -> Option<usize>;
 
     fn report_next_bytes(&self, count: usize);
@@ -111,10 +146,59 @@ pub trait Bitsy: Sized + Debug {
     fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()>;
 }
 
+// Reads a constant header and fails at its first bit if it doesn't match.
+pub fn read_magic<R: BitReader, T: Bitsy + BitSized + PartialEq>(
+    reader: &mut R,
+    expected: &T,
+    name: &str,
+) -> BitsyResult<()> {
+    let header: T = reader.read()?;
+    if header != *expected {
+        return Err(error::BitsyErrorKind::InvalidData(format!(
+            "Invalid {} header {:?} (expected {:?})",
+            name, header, expected
+        ))
+        .at_bit(reader.index() - header.bit_size()));
+    }
+    Ok(())
+}
+
 pub trait BitSized {
     fn bit_size(&self) -> usize;
 }
 
+// Knuth-Morris-Pratt search for `needle` in `haystack`, returning the index of the first match.
+// The haystack is loaded 64 bits at a time, as going through it bit by bit is much slower.
+pub fn find_bits(haystack: &MyBitSlice, needle: &MyBitSlice) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    let needle: Vec<usize> = needle.iter().map(|bit| *bit as usize).collect();
+    // transitions[state][bit] is the number of needle bits matched after reading `bit` with
+    // `state` bits matched.
+    let mut transitions = vec![[0usize; 2]; needle.len()];
+    let mut fallback = 0;
+    for (state, bit) in needle.iter().enumerate() {
+        transitions[state] = transitions[fallback];
+        transitions[state][*bit] = state + 1;
+        if state > 0 {
+            fallback = transitions[fallback][*bit];
+        }
+    }
+
+    let mut state = 0;
+    for (chunk_index, chunk) in haystack.chunks(64).enumerate() {
+        let word: u64 = chunk.load_le();
+        for offset in 0..chunk.len() {
+            state = transitions[state][((word >> offset) & 1) as usize];
+            if state == needle.len() {
+                return Some(chunk_index * 64 + offset + 1 - needle.len());
+            }
+        }
+    }
+    None
+}
+
 pub fn bitsy_to_bits(bitsy: &impl Bitsy, version: u32) -> MyBitVec {
     let mut writer = BitVecWriter::new(version);
     bitsy.write_to(&mut writer).unwrap();
@@ -169,6 +253,12 @@ impl Debug for BitsliceCompareError {
     }
 }
 
+impl Display for BitsliceCompareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
 fn find_first_difference(expected: &MyBitSlice, actual: &MyBitSlice) -> Option<usize> {
     let mut index = 0;
     while index < expected.len() && index < actual.len() {
@@ -202,7 +292,7 @@ fn as_bits(bits: &MyBitSlice, start: usize, end: usize) -> String {
 
 const DIFF_WINDOW: usize = 16;
 
-fn show_bitslice_around(bits: &MyBitSlice, index: usize) -> String {
+pub(crate) fn show_bitslice_around(bits: &MyBitSlice, index: usize) -> String {
     let mut result = String::new();
     if index > DIFF_WINDOW {
         result.push_str(&format!(" ({} hidden bits) ... ", index - DIFF_WINDOW));
@@ -256,3 +346,97 @@ pub mod testutils {
         bits_from_str(bits).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{structs::BitsyInt, testutils::bits, *};
+
+    const MAGIC: [u8; 1] = [0xAB];
+
+    #[derive(Debug, Bitsy)]
+    #[bitsy(magic = "MAGIC", padding)]
+    struct Derived {
+        version: BitsyInt<u8, 4>,
+        #[bitsy(context = "context::HAS_SOCKETS")]
+        flag: bool,
+        #[bitsy(if = "flag")]
+        value: Option<BitsyInt<u8, 3>>,
+        #[bitsy(if = "reader.get_context(&context::HAS_SOCKETS)? && version.value() > 3")]
+        extra: Option<bool>,
+    }
+
+    #[test]
+    fn find_bits_finds_the_first_match() {
+        let haystack = bits("0010 0101 1001 0110");
+
+        assert_eq!(Some(0), find_bits(&haystack, &bits("")));
+        assert_eq!(Some(2), find_bits(&haystack, &bits("1")));
+        assert_eq!(Some(4), find_bits(&haystack, &bits("0101 1")));
+        assert_eq!(Some(3), find_bits(&haystack, &bits("0010 11")));
+        assert_eq!(None, find_bits(&haystack, &bits("111")));
+        assert_eq!(None, find_bits(&haystack[..4], &haystack));
+    }
+
+    #[test]
+    fn find_bits_matches_a_naive_search() {
+        for _ in 0..100 {
+            let haystack = testutils::random_bits(200);
+            let needle = testutils::random_bits(1 + rand::random::<usize>() % 6);
+            let expected = (0..haystack.len()).find(|start| haystack[*start..].starts_with(&needle));
+
+            assert_eq!(expected, find_bits(&haystack, &needle));
+        }
+    }
+
+    #[test]
+    fn derive_reads_fields_in_order() {
+        let mut reader = BitVecReader::new(bits("11010101 0010 1 110 1 0 000000"));
+
+        let derived: Derived = reader.read().unwrap();
+
+        assert_eq!(4, derived.version.value());
+        assert_eq!(Some(3), derived.value.map(|v| v.value()));
+        assert_eq!(Some(true), derived.extra);
+        assert!(reader.get_context(&context::HAS_SOCKETS).unwrap());
+        assert_eq!(24, reader.index());
+    }
+
+    #[test]
+    fn derive_skips_conditional_fields() {
+        let mut reader = BitVecReader::new(bits("11010101 1000 0 0000"));
+
+        let derived: Derived = reader.read().unwrap();
+
+        assert!(derived.value.is_none());
+        assert!(derived.extra.is_none());
+        assert_eq!(16, reader.index());
+    }
+
+    #[test]
+    fn derive_roundtrips() {
+        let original = bits("11010101 0010 1 110 1 0 000000");
+        let mut reader = BitVecReader::new(original.clone());
+        let derived: Derived = reader.read().unwrap();
+
+        compare_bitslices(&original, &bitsy_to_bits(&derived, 0)).unwrap();
+    }
+
+    #[test]
+    fn derive_checks_magic() {
+        let mut reader = BitVecReader::new(bits("11010100 0010 0 0000"));
+
+        let error = format!("{:?}", reader.read::<Derived>().unwrap_err());
+
+        assert!(error.contains("Invalid Derived header"), "{}", error);
+        assert!(error.contains("at bit 0"), "{}", error);
+    }
+
+    #[test]
+    fn derive_prepends_field_paths() {
+        let mut reader = BitVecReader::new(bits("11010101 0010 1 1"));
+
+        let error = format!("{:?}", reader.read::<Derived>().unwrap_err());
+
+        assert!(error.ends_with("Path: .value"), "{}", error);
+    }
+}
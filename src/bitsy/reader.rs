@@ -5,13 +5,16 @@ use crate::{
     item::{
         info::{ItemDb, MapItemDb},
         properties::{MapPropertyDb, PropertyDb},
+        runeword::{MapRunewordDb, RunewordDb},
     },
 };
 
 use super::{
+    annotation::{Annotation, Annotator},
     bits_from_str,
     context::{ContextKey, ContextMap, ContextValue},
-    error::{BitsyError, BitsyErrorKind},
+    error::{BitsyError, BitsyErrorExt, BitsyErrorKind, PathSegment},
+    find_bits,
     result::BitsyResult,
     BitReader, Bitsy, MyBitSlice, MyBitVec,
 };
@@ -21,9 +24,12 @@ pub struct BitVecReader {
     index: usize,
     context: ContextMap,
     item_db: Rc<dyn ItemDb>,
+    property_db: Rc<dyn PropertyDb>,
+    runeword_db: Rc<dyn RunewordDb>,
+    annotator: Option<Annotator>,
 }
 
-fn int_to_printable_char(int: u32) -> char {
+pub(super) fn int_to_printable_char(int: u32) -> char {
     match int {
         32..=126 | 161..=255 => char::from_u32(int).unwrap(),
         _ => '.',
@@ -31,27 +37,84 @@ fn int_to_printable_char(int: u32) -> char {
 }
 
 impl BitVecReader {
-    pub fn new(bits: MyBitVec) -> Self {
+    pub fn new(mut bits: MyBitVec) -> Self {
+        bits.force_align();
         Self {
             bits,
             index: 0,
             context: ContextMap::new(),
             item_db: Rc::new(MapItemDb::new()),
+            property_db: Rc::new(MapPropertyDb::empty()),
+            runeword_db: Rc::new(MapRunewordDb::new()),
+            annotator: None,
         }
     }
 
-    pub fn with_item_db(bits: MyBitVec, item_db: Rc<dyn ItemDb>) -> Self {
+    pub fn with_item_db(mut bits: MyBitVec, item_db: Rc<dyn ItemDb>) -> Self {
+        bits.force_align();
         Self {
             bits,
             index: 0,
             context: ContextMap::new(),
             item_db,
+            property_db: Rc::new(MapPropertyDb::empty()),
+            runeword_db: Rc::new(MapRunewordDb::new()),
+            annotator: None,
         }
     }
 
+    // Properties are only decoded when their definition is known. Otherwise they are kept as raw
+    // bits, as the save sizes change between versions and mods.
+    pub fn set_property_db(&mut self, property_db: Rc<dyn PropertyDb>) {
+        self.property_db = property_db;
+    }
+
+    pub fn set_runeword_db(&mut self, runeword_db: Rc<dyn RunewordDb>) {
+        self.runeword_db = runeword_db;
+    }
+
+    // Starts recording the path and bit range of every field read from here on.
+    pub fn record_annotations(&mut self) {
+        self.annotator = Some(Annotator::new(self.index));
+    }
+
+    pub fn take_annotations(&mut self) -> Option<Annotation> {
+        self.annotator
+            .take()
+            .map(|annotator| annotator.finish(self.index))
+    }
+
+    fn annotated<T>(
+        &mut self,
+        segment: impl FnOnce() -> PathSegment,
+        read: impl FnOnce(&mut Self) -> BitsyResult<T>,
+    ) -> BitsyResult<T> {
+        if let Some(annotator) = &mut self.annotator {
+            annotator.begin(segment(), self.index);
+        }
+        let result = read(self);
+        if let Some(annotator) = &mut self.annotator {
+            annotator.end(self.index, result.is_ok());
+        }
+        result
+    }
+
     fn error(&self, kind: BitsyErrorKind) -> BitsyError {
         BitsyError::new(kind, self.index)
     }
+
+    // Loads the (at most 5) bytes holding the next `bit_count` bits in one go instead of going
+    // bit by bit. The bits are aligned to the start of the buffer by the constructors.
+    fn load_int(&self, bit_count: usize) -> u32 {
+        if bit_count == 0 {
+            return 0;
+        }
+        let bytes = &self.bits.as_raw_slice()[self.index / 8..=(self.index + bit_count - 1) / 8];
+        let word = bytes.iter().enumerate().fold(0u64, |word, (index, byte)| {
+            word | (*byte as u64) << (index * 8)
+        });
+        ((word >> (self.index % 8)) & ((1u64 << bit_count) - 1)) as u32
+    }
 }
 
 impl BitReader for BitVecReader {
@@ -77,8 +140,12 @@ impl BitReader for BitVecReader {
         self.item_db.clone()
     }
 
-    fn property_db(&self) -> impl PropertyDb {
-        MapPropertyDb::new()
+    fn property_db(&self) -> Rc<dyn PropertyDb> {
+        self.property_db.clone()
+    }
+
+    fn runeword_db(&self) -> Rc<dyn RunewordDb> {
+        self.runeword_db.clone()
     }
 
     fn read_int<T: TryFrom<u32>>(&mut self, bit_count: usize) -> BitsyResult<T> {
@@ -91,15 +158,7 @@ impl BitReader for BitVecReader {
             return Err(self.error(BitsyErrorKind::EndOfData));
         }
 
-        let mut res: u32 = 0;
-        let mut multiplier = 1;
-
-        for index in 0..bit_count {
-            res += self.bits[self.index + index] as u32 * multiplier;
-            if index < 31 {
-                multiplier *= 2;
-            }
-        }
+        let res = self.load_int(bit_count);
 
         let result = T::try_from(res).map_err(|_| {
             self.error(BitsyErrorKind::InvalidData(format!(
@@ -113,43 +172,61 @@ impl BitReader for BitVecReader {
     }
 
     fn read_bits(&mut self, bit_count: usize) -> BitsyResult<MyBitVec> {
-        let mut bitvec = MyBitVec::new();
-        for bit in self.bits[self.index..].iter().take(bit_count) {
-            bitvec.push(*bit);
-        }
-        if bitvec.len() != bit_count {
+        if self.index + bit_count > self.bits.len() {
             return Err(self.error(BitsyErrorKind::EndOfData));
         }
-        self.index += bit_count;
+        // Copying the slice with to_bitvec goes bit by bit, so the bits are copied a byte at a
+        // time instead.
+        let mut bytes = Vec::with_capacity(bit_count.div_ceil(8));
+        let mut remaining = bit_count;
+        while remaining > 0 {
+            let byte_size = min(8, remaining);
+            bytes.push(self.load_int(byte_size) as u8);
+            self.index += byte_size;
+            remaining -= byte_size;
+        }
+        let mut bitvec = MyBitVec::from_vec(bytes);
+        bitvec.truncate(bit_count);
         Ok(bitvec)
     }
 
     fn read_padding(&mut self) -> BitsyResult<()> {
         if self.index % 8 != 0 {
-            let padding = 8 - (self.index % 8);
-            if self.bits[self.index..self.index + padding].any() {
+            // read_padding does not fail if there is not enough data
+            let end = min(self.index + 8 - (self.index % 8), self.bits.len());
+            if self.bits[self.index..end].any() {
                 return Err(self.error(BitsyErrorKind::InvalidData("Padding not zero".to_string())));
             }
-            // read_padding does not fail if there is not enough data
-            self.index = min(self.index + padding, self.bits.len());
+            self.index = end;
         }
         Ok(())
     }
 
     fn read_tail(&mut self) -> BitsyResult<MyBitVec> {
-        let mut tail = MyBitVec::new();
-        tail.extend_from_bitslice(&self.bits[self.index..]);
-        self.index = self.bits.len();
-        Ok(tail)
+        self.read_bits(self.bits.len() - self.index)
     }
 
     fn read_until(&mut self, bits: &MyBitVec) -> BitsyResult<MyBitVec> {
-        let result = self
-            .search(bits, 0)
-            .map(|offset| self.bits[self.index..self.index + offset].to_owned())
-            .unwrap_or_else(|| self.bits[self.index..].to_owned());
-        self.index += result.len();
-        Ok(result)
+        let length = self.search(bits, 0).unwrap_or(self.bits.len() - self.index);
+        self.read_bits(length)
+    }
+
+    fn read_named<T>(
+        &mut self,
+        name: &str,
+        read: impl FnOnce(&mut Self) -> BitsyResult<T>,
+    ) -> BitsyResult<T> {
+        self.annotated(|| PathSegment::Name(name.to_string()), read)
+            .prepend_path(name)
+    }
+
+    fn read_indexed<T>(
+        &mut self,
+        index: usize,
+        read: impl FnOnce(&mut Self) -> BitsyResult<T>,
+    ) -> BitsyResult<T> {
+        self.annotated(|| PathSegment::Index(index), read)
+            .prepend_index(index)
     }
 
     fn read_property_tail(&mut self) -> BitsyResult<MyBitVec> {
@@ -164,8 +241,8 @@ impl BitReader for BitVecReader {
             match_index += 1;
         }
 
-        let tail = self.bits[self.index..match_index].to_owned();
-        self.index = match_index + 9;
+        let tail = self.read_bits(match_index - self.index)?;
+        self.index += 9;
         Ok(tail)
     }
 
@@ -200,21 +277,31 @@ impl BitReader for BitVecReader {
 
     fn peek<T: Bitsy>(&mut self) -> BitsyResult<T> {
         let index = self.index;
-        let context = self.context.clone();
+        let context = self.context.snapshot();
+        let annotator = self.annotator.take();
         let value = T::parse(self);
         self.index = index;
-        self.context = context;
+        self.context.restore(context);
+        self.annotator = annotator;
+        value
+    }
+
+    fn try_read<T: Bitsy>(&mut self) -> BitsyResult<T> {
+        let index = self.index;
+        let context = self.context.snapshot();
+        let value = T::parse(self);
+        if value.is_err() {
+            self.index = index;
+            self.context.restore(context);
+        }
         value
     }
 
     fn search(&self, needle: &MyBitVec, offset: usize) -> Option<usize> {
-        let mut start = self.index + offset;
-        while start < self.bits.len() {
-            if self.bits[start..].starts_with(needle) {
-                return Some(start - self.index);
-            }
-            start += 1;
+        let start = self.index + offset;
+        if start >= self.bits.len() {
+            return None;
         }
-        None
+        find_bits(&self.bits[start..], needle).map(|found| found + offset)
     }
 }
@@ -1,4 +1,7 @@
-use super::error::{BitsyError, BitsyErrorExt};
+use super::{
+    error::{BitsyError, BitsyErrorExt},
+    MyBitSlice,
+};
 
 pub type BitsyResult<T> = Result<T, BitsyError>;
 
@@ -10,4 +13,8 @@ impl<T> BitsyErrorExt for BitsyResult<T> {
     fn prepend_index(self, index: usize) -> Self {
         self.map_err(|e| e.prepend_index(index))
     }
+
+    fn with_source(self, bits: &MyBitSlice) -> Self {
+        self.map_err(|e| e.with_source(bits))
+    }
 }
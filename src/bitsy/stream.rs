@@ -0,0 +1,453 @@
+use std::{any::type_name, cmp::min, convert::TryFrom, io::Read, rc::Rc};
+
+use crate::item::{
+    info::{ItemDb, MapItemDb},
+    properties::{MapPropertyDb, PropertyDb},
+    runeword::{MapRunewordDb, RunewordDb},
+};
+
+use super::{
+    bits_from_str,
+    context::{ContextKey, ContextMap, ContextResetGuard, ContextValue},
+    dump::{render_dump, DumpOptions},
+    error::{BitsyError, BitsyErrorKind},
+    find_bits, parse_int,
+    result::BitsyResult,
+    BitReader, Bitsy, MyBitVec,
+};
+
+const CHUNK_SIZE: usize = 4096;
+// Consumed bits are only dropped from the buffer once there are this many of them, so the
+// buffer is not shifted on every read.
+const COMPACT_THRESHOLD: usize = CHUNK_SIZE * 8 * 4;
+pub const DEFAULT_LOOKAHEAD: usize = 64 * 1024 * 8;
+
+// A BitReader that pulls bytes from an io::Read as they are needed, keeping only the unread bits
+// (plus whatever an ongoing peek may rewind to) in memory. Searches and peeks can't look further
+// than `lookahead` bits ahead.
+pub struct StreamReader<S: Read> {
+    source: S,
+    bits: MyBitVec,
+    // Absolute index of the first buffered bit.
+    start: usize,
+    index: usize,
+    eof: bool,
+    lookahead: usize,
+    // Index that an ongoing peek (or failing try_read) will rewind to.
+    peek_start: Option<usize>,
+    context: ContextMap,
+    item_db: Rc<dyn ItemDb>,
+    property_db: Rc<dyn PropertyDb>,
+    runeword_db: Rc<dyn RunewordDb>,
+}
+
+impl<S: Read> StreamReader<S> {
+    pub fn new(source: S) -> Self {
+        Self::with_item_db(source, Rc::new(MapItemDb::new()))
+    }
+
+    pub fn with_item_db(source: S, item_db: Rc<dyn ItemDb>) -> Self {
+        Self {
+            source,
+            bits: MyBitVec::new(),
+            start: 0,
+            index: 0,
+            eof: false,
+            lookahead: DEFAULT_LOOKAHEAD,
+            peek_start: None,
+            context: ContextMap::new(),
+            item_db,
+            property_db: Rc::new(MapPropertyDb::empty()),
+            runeword_db: Rc::new(MapRunewordDb::new()),
+        }
+    }
+
+    pub fn set_property_db(&mut self, property_db: Rc<dyn PropertyDb>) {
+        self.property_db = property_db;
+    }
+
+    pub fn set_runeword_db(&mut self, runeword_db: Rc<dyn RunewordDb>) {
+        self.runeword_db = runeword_db;
+    }
+
+    pub fn set_lookahead(&mut self, bit_count: usize) {
+        self.lookahead = bit_count;
+    }
+
+    // Whether the source is exhausted, which is how concatenated dumps know when to stop.
+    pub fn is_at_end(&mut self) -> BitsyResult<bool> {
+        Ok(!self.fill(1)?)
+    }
+
+    fn error(&self, kind: BitsyErrorKind) -> BitsyError {
+        BitsyError::new(kind, self.index)
+    }
+
+    fn offset(&self) -> usize {
+        self.index - self.start
+    }
+
+    fn available(&self) -> usize {
+        self.bits.len() - self.offset()
+    }
+
+    fn compact(&mut self) {
+        let keep_from = self
+            .peek_start
+            .map_or(self.index, |peek| min(peek, self.index));
+        let drop_count = keep_from - self.start;
+        if drop_count >= COMPACT_THRESHOLD {
+            self.bits = self.bits[drop_count..].to_bitvec();
+            self.start = keep_from;
+        }
+    }
+
+    // Buffers `bit_count` bits past the current index. Returns false if the source ends before.
+    fn fill(&mut self, bit_count: usize) -> BitsyResult<bool> {
+        if let Some(peek_start) = self.peek_start {
+            if self.index + bit_count - peek_start > self.lookahead {
+                return Err(self.error(BitsyErrorKind::InvalidAction(format!(
+                    "Peek went past the lookahead of {} bits",
+                    self.lookahead
+                ))));
+            }
+        }
+        self.compact();
+        let mut chunk = [0u8; CHUNK_SIZE];
+        while self.available() < bit_count && !self.eof {
+            match self.source.read(&mut chunk) {
+                Ok(0) => self.eof = true,
+                Ok(count) => self
+                    .bits
+                    .extend_from_bitslice(&MyBitVec::from_vec(chunk[..count].to_vec())),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(self.error(BitsyErrorKind::Io(err.to_string()))),
+            }
+        }
+        Ok(self.available() >= bit_count)
+    }
+
+    // Buffers as much as the lookahead allows, for searches.
+    fn fill_lookahead(&mut self) -> BitsyResult<()> {
+        let bit_count = match self.peek_start {
+            Some(peek_start) => self.lookahead - (self.index - peek_start),
+            None => self.lookahead,
+        };
+        self.fill(bit_count)?;
+        Ok(())
+    }
+
+    fn not_found(&self, what: &str) -> BitsyError {
+        self.error(BitsyErrorKind::InvalidData(format!(
+            "Could not find {} within the next {} bits",
+            what, self.lookahead
+        )))
+    }
+}
+
+impl<S: Read> BitReader for StreamReader<S> {
+    fn index(&self) -> usize {
+        self.index
+    }
+
+    fn queue_context_reset(&self) -> ContextResetGuard {
+        self.context.context_reset()
+    }
+
+    fn set_context<T: ContextValue>(&mut self, key: &ContextKey<T>, value: T) {
+        self.context.set_context(key, value);
+    }
+
+    fn get_context<T: ContextValue>(&self, key: &ContextKey<T>) -> BitsyResult<T> {
+        self.context
+            .get_context(key)
+            .ok_or_else(|| self.error(BitsyErrorKind::MissingContext(key.as_str().to_string())))
+    }
+
+    fn item_db(&self) -> Rc<dyn ItemDb> {
+        self.item_db.clone()
+    }
+
+    fn property_db(&self) -> Rc<dyn PropertyDb> {
+        self.property_db.clone()
+    }
+
+    fn runeword_db(&self) -> Rc<dyn RunewordDb> {
+        self.runeword_db.clone()
+    }
+
+    fn read_int<T: TryFrom<u32>>(&mut self, bit_count: usize) -> BitsyResult<T> {
+        if bit_count > 32 {
+            return Err(self.error(BitsyErrorKind::InvalidData(
+                "Ints > 32 bits not supported".to_string(),
+            )));
+        }
+        if !self.fill(bit_count)? {
+            return Err(self.error(BitsyErrorKind::EndOfData));
+        }
+        let offset = self.offset();
+        let value = parse_int(&self.bits[offset..offset + bit_count])
+            .map_err(|message| self.error(BitsyErrorKind::InvalidData(message)))?;
+        let result = T::try_from(value).map_err(|_| {
+            self.error(BitsyErrorKind::InvalidData(format!(
+                "Could not fit int of {} bits in {}",
+                bit_count,
+                type_name::<T>()
+            )))
+        })?;
+        self.index += bit_count;
+        Ok(result)
+    }
+
+    fn read_bits(&mut self, bit_count: usize) -> BitsyResult<MyBitVec> {
+        if !self.fill(bit_count)? {
+            return Err(self.error(BitsyErrorKind::EndOfData));
+        }
+        let offset = self.offset();
+        let bits = self.bits[offset..offset + bit_count].to_bitvec();
+        self.index += bit_count;
+        Ok(bits)
+    }
+
+    fn read_padding(&mut self) -> BitsyResult<()> {
+        if !self.index.is_multiple_of(8) {
+            let padding = 8 - (self.index % 8);
+            self.fill(padding)?;
+            // read_padding does not fail if there is not enough data
+            let padding = min(padding, self.available());
+            let offset = self.offset();
+            if self.bits[offset..offset + padding].any() {
+                return Err(self.error(BitsyErrorKind::InvalidData("Padding not zero".to_string())));
+            }
+            self.index += padding;
+        }
+        Ok(())
+    }
+
+    fn read_tail(&mut self) -> BitsyResult<MyBitVec> {
+        self.fill(usize::MAX - self.index)?;
+        let tail = self.bits[self.offset()..].to_bitvec();
+        self.index += tail.len();
+        Ok(tail)
+    }
+
+    fn read_until(&mut self, bits: &MyBitVec) -> BitsyResult<MyBitVec> {
+        self.fill_lookahead()?;
+        let length = match self.search(bits, 0) {
+            Some(length) => length,
+            None if self.eof => self.available(),
+            None => return Err(self.not_found(&format!("{}", bits))),
+        };
+        self.read_bits(length)
+    }
+
+    fn read_property_tail(&mut self) -> BitsyResult<MyBitVec> {
+        self.fill_lookahead()?;
+        let terminator = bits_from_str("111 111 111").unwrap();
+        let mut length = self
+            .search(&terminator, 0)
+            .ok_or_else(|| self.not_found("property tail"))?;
+        let offset = self.offset();
+        while offset + length + 9 < self.bits.len() && self.bits[offset + length + 9] {
+            length += 1;
+        }
+
+        let tail = self.read_bits(length)?;
+        self.index += 9;
+        Ok(tail)
+    }
+
+    fn peek<T: Bitsy>(&mut self) -> BitsyResult<T> {
+        let index = self.index;
+        let context = self.context.snapshot();
+        // Nested peeks keep the start of the outermost one, which is the furthest back that
+        // anything may still rewind to.
+        let peek_start = self
+            .peek_start
+            .replace(self.peek_start.map_or(index, |start| start.min(index)));
+        let value = T::parse(self);
+        self.index = index;
+        self.context.restore(context);
+        self.peek_start = peek_start;
+        value
+    }
+
+    fn try_read<T: Bitsy>(&mut self) -> BitsyResult<T> {
+        let index = self.index;
+        let context = self.context.snapshot();
+        let peek_start = self.peek_start.replace(self.peek_start.unwrap_or(index));
+        let value = T::parse(self);
+        if value.is_err() {
+            self.index = index;
+            self.context.restore(context);
+        }
+        self.peek_start = peek_start;
+        value
+    }
+
+    // Only searches the bits that are already buffered.
+    fn search(&self, needle: &MyBitVec, offset: usize) -> Option<usize> {
+        let start = self.offset() + offset;
+        if start >= self.bits.len() {
+            return None;
+        }
+        find_bits(&self.bits[start..], needle).map(|found| found + offset)
+    }
+
+    // Dumps the next `count` buffered bytes, with offsets counted from the current index.
+    fn report_next_bytes(&self, count: usize) {
+        println!(
+            "BitReader status: in bit {} (byte {}), {} bits buffered",
+            self.index,
+            self.index / 8,
+            self.available()
+        );
+        let options = DumpOptions {
+            color: false,
+            bytes: Some(0..count),
+        };
+        print!(
+            "{}",
+            render_dump(&self.bits[self.offset()..], None, &options)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bitsy::{compare_bitslices, context, read_magic, BitVecWriter, BitWriter},
+        item::{info::MapItemDb, ItemList},
+    };
+
+    use super::*;
+
+    // Hands out one byte per read, so every read crosses a chunk boundary.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((first, rest)) if !buf.is_empty() => {
+                    buf[0] = *first;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn it_reads_across_chunks() {
+        let mut reader = StreamReader::new(Trickle(&[0xB1, 0xF2, 0x18]));
+
+        assert_eq!(0x1, reader.read_int::<u8>(4).unwrap());
+        assert_eq!(0xF2B, reader.read_int::<u16>(12).unwrap());
+        assert_eq!(0x18, reader.peek::<u8>().unwrap());
+        assert!(!reader.is_at_end().unwrap());
+        assert_eq!(0x18, reader.read::<u8>().unwrap());
+        assert!(reader.is_at_end().unwrap());
+        assert!(reader.read::<bool>().is_err());
+    }
+
+    #[test]
+    fn peeks_are_bounded() {
+        let bytes = [0u8; 16];
+        let mut reader = StreamReader::new(&bytes[..]);
+        reader.set_lookahead(64);
+
+        assert!(reader.peek::<[u8; 8]>().is_ok());
+        assert!(reader.peek::<[u8; 9]>().is_err());
+        assert!(reader.read::<[u8; 9]>().is_ok());
+        assert_eq!(72, reader.index());
+    }
+
+    #[test]
+    fn read_until_stops_at_the_needle() {
+        let bytes = [0x00, 0x4A, 0x4D, 0x01];
+        let mut reader = StreamReader::new(Trickle(&bytes));
+
+        let head = reader
+            .read_until(&MyBitVec::from_vec(b"JM".to_vec()))
+            .unwrap();
+
+        assert_eq!(8, head.len());
+        assert_eq!(*b"JM", reader.read::<[u8; 2]>().unwrap());
+        assert_eq!(
+            8,
+            reader
+                .read_until(&MyBitVec::from_vec(b"JM".to_vec()))
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn parses_a_stash_incrementally() {
+        let bytes = std::fs::read("small_stash.sss").unwrap();
+        let mut reader = StreamReader::with_item_db(
+            Trickle(&bytes),
+            Rc::new(MapItemDb::from_data_dir("data/items")),
+        );
+        reader.set_property_db(Rc::new(MapPropertyDb::new()));
+        reader.set_context(&context::VERSION, 96);
+
+        let header: [u8; 14] = reader.read().unwrap();
+        let mut writer = BitVecWriter::new(96);
+        writer.write(&header).unwrap();
+        while !reader.is_at_end().unwrap() {
+            let page_header: [u8; 3] = reader.read().unwrap();
+            assert_eq!(b"ST\0", &page_header);
+            let items: ItemList = reader.read().unwrap();
+            writer.write(&page_header).unwrap();
+            writer.write(&items).unwrap();
+        }
+
+        compare_bitslices(&MyBitVec::from_vec(bytes), &writer.into_bits()).unwrap();
+    }
+
+    #[test]
+    fn it_drops_consumed_bits() {
+        let bytes = vec![0u8; CHUNK_SIZE * 16];
+        let mut reader = StreamReader::new(&bytes[..]);
+
+        while !reader.is_at_end().unwrap() {
+            reader.read::<u32>().unwrap();
+        }
+
+        assert_eq!(bytes.len() * 8, reader.index());
+        assert!(reader.bits.len() <= COMPACT_THRESHOLD + CHUNK_SIZE * 8);
+    }
+
+    // Reads a byte, peeks at the next one and then fails on it.
+    #[derive(Debug)]
+    struct PeekThenFail;
+
+    impl Bitsy for PeekThenFail {
+        fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+            reader.read::<u8>()?;
+            reader.peek::<u8>()?;
+            read_magic(reader, &0xFFu8, "marker")?;
+            Ok(PeekThenFail)
+        }
+
+        fn write_to<W: BitWriter>(&self, _writer: &mut W) -> BitsyResult<()> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn nested_peeks_keep_the_failed_read() {
+        let bytes = vec![0u8; CHUNK_SIZE * 8];
+        let mut reader = StreamReader::new(&bytes[..]);
+        reader.set_lookahead(64);
+        reader.read_bits(COMPACT_THRESHOLD - 8).unwrap();
+
+        // The peek starts right at the compaction threshold, which must not drop the byte that
+        // the failed read rewinds to.
+        assert!(reader.try_read::<PeekThenFail>().is_err());
+        assert_eq!(COMPACT_THRESHOLD - 8, reader.index());
+        assert_eq!(0, reader.read::<u16>().unwrap());
+    }
+}
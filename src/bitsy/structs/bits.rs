@@ -1,8 +1,10 @@
 use std::fmt::Debug;
 
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::bitsy::{
-    error::BitsyErrorKind, result::BitsyResult, BitReader, BitSized, BitWriter, Bitsy, MyBitSlice,
-    MyBitVec,
+    bit_string, error::BitsyErrorKind, result::BitsyResult, BitReader, BitSized, BitWriter, Bitsy,
+    MyBitSlice, MyBitVec,
 };
 
 pub struct Bits<const N: usize> {
@@ -36,6 +38,18 @@ impl<const N: usize> Debug for Bits<N> {
     }
 }
 
+impl<const N: usize> Serialize for Bits<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        bit_string::serialize(&self.bits, serializer)
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Bits<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::new(bit_string::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
 impl<const N: usize> Bitsy for Bits<N> {
     fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
         Ok(Self {
@@ -95,6 +109,21 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn it_serializes_as_a_bit_string() {
+        let bits = Bits::<3>::new(bits_from_str("011").unwrap()).unwrap();
+
+        assert_eq!(r#""011""#, serde_json::to_string(&bits).unwrap());
+        assert!(serde_json::from_str::<Bits<3>>(r#""0110""#).is_err());
+        compare_bitslices(
+            bits.as_bitslice(),
+            serde_json::from_str::<Bits<3>>(r#""011""#)
+                .unwrap()
+                .as_bitslice(),
+        )
+        .unwrap();
+    }
+
     #[test]
     fn roundtrip_random_bits() {
         for _index in 0..1000 {
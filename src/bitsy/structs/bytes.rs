@@ -3,6 +3,8 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::bitsy::{result::BitsyResult, BitReader, BitWriter, Bitsy};
 
 pub struct BitsyBytes<const N: usize> {
@@ -13,6 +15,13 @@ impl<const N: usize> BitsyBytes<N> {
     pub fn new(bytes: [u8; N]) -> Self {
         Self { bytes }
     }
+
+    pub fn to_hex(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
 }
 
 impl<const N: usize> Deref for BitsyBytes<N> {
@@ -39,6 +48,32 @@ impl<const N: usize> Bitsy for BitsyBytes<N> {
     }
 }
 
+// Serialized as a hex string, e.g. "4a4d".
+impl<const N: usize> Serialize for BitsyBytes<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for BitsyBytes<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() != N * 2 || !hex.is_ascii() {
+            return Err(D::Error::custom(format!(
+                "Expected {} hex digits, got '{}'",
+                N * 2,
+                hex
+            )));
+        }
+        let mut bytes = [0u8; N];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16)
+                .map_err(|err| D::Error::custom(format!("Invalid hex '{}': {}", hex, err)))?;
+        }
+        Ok(Self { bytes })
+    }
+}
+
 const MAX_DEBUG_BYTES: usize = 12;
 
 fn format_bytes(bytes: &[u8]) -> String {
@@ -102,6 +137,19 @@ mod tests {
         assert_eq!(reader.index(), 16 * 8 + 3);
     }
 
+    #[test]
+    fn it_serializes_as_hex() {
+        let bytes = BitsyBytes::<2>::new([0x4A, 0x0D]);
+
+        assert_eq!(r#""4a0d""#, serde_json::to_string(&bytes).unwrap());
+        assert_eq!(
+            [0x4A, 0x0D],
+            *serde_json::from_str::<BitsyBytes<2>>(r#""4A0d""#).unwrap()
+        );
+        assert!(serde_json::from_str::<BitsyBytes<2>>(r#""4a0""#).is_err());
+        assert!(serde_json::from_str::<BitsyBytes<2>>(r#""4a0g""#).is_err());
+    }
+
     #[test]
     fn it_writes() {
         let first = BitsyBytes::<2>::new([0b00101101, 0b10110100]);
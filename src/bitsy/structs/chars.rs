@@ -1,5 +1,7 @@
 use std::convert::TryInto;
 
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::bitsy::{
     error::BitsyErrorKind, result::BitsyResult, BitReader, BitSized, BitWriter, Bitsy,
 };
@@ -34,6 +36,10 @@ impl<const N: usize> BitsyChars<N> {
         }
         Ok(Self { bytes })
     }
+
+    pub fn as_string(&self) -> String {
+        self.bytes.iter().map(|byte| *byte as char).collect()
+    }
 }
 
 impl<const N: usize> Bitsy for BitsyChars<N> {
@@ -46,15 +52,21 @@ impl<const N: usize> Bitsy for BitsyChars<N> {
     }
 }
 
+impl<const N: usize> Serialize for BitsyChars<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.as_string())
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for BitsyChars<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::new(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
 impl<const N: usize> std::fmt::Debug for BitsyChars<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let string = self
-            .bytes
-            .iter()
-            .map(|byte| *byte as char)
-            .collect::<String>();
-
-        write!(f, "BC<{:?}>", string)
+        write!(f, "BC<{:?}>", self.as_string())
     }
 }
 
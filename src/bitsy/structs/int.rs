@@ -3,6 +3,8 @@ use std::{
     fmt::{Debug, Display},
 };
 
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::bitsy::{
     error::BitsyErrorKind, result::BitsyResult, BitReader, BitSized, BitWriter, Bitsy,
 };
@@ -29,7 +31,7 @@ impl<T: BitsyIntTarget, const N: usize> Display for BitsyInt<T, N> {
 
 impl<T: BitsyIntTarget, const N: usize> BitsyInt<T, N> {
     pub fn new(value: T) -> BitsyResult<Self> {
-        if value.into() >= (2u32 << N) {
+        if u64::from(value.into()) >> N != 0 {
             return Err(BitsyErrorKind::InvalidData(format!(
                 "Value {value:?} is too large for {N} bits",
             ))
@@ -44,6 +46,21 @@ impl<T: BitsyIntTarget, const N: usize> BitsyInt<T, N> {
     }
 }
 
+impl<T: BitsyIntTarget, const N: usize> Serialize for BitsyInt<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.value.into())
+    }
+}
+
+impl<'de, T: BitsyIntTarget, const N: usize> Deserialize<'de> for BitsyInt<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = u32::deserialize(deserializer)?;
+        let value = T::try_from(value)
+            .map_err(|_| D::Error::custom(format!("Value {} is out of range", value)))?;
+        Self::new(value).map_err(D::Error::custom)
+    }
+}
+
 impl<T: BitsyIntTarget, const N: usize> Bitsy for BitsyInt<T, N> {
     fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
         Ok(Self {
@@ -91,6 +108,21 @@ mod tests {
         compare_bitslices(&bits_from_str("001011").unwrap(), &writer.into_bits()).unwrap();
     }
 
+    #[test]
+    fn it_checks_the_size() {
+        assert!(BitsyInt::<u8, 6>::new(63).is_ok());
+        assert!(BitsyInt::<u8, 6>::new(64).is_err());
+        assert!(BitsyInt::<u32, 32>::new(u32::MAX).is_ok());
+        assert_eq!(
+            52,
+            serde_json::from_str::<BitsyInt<u8, 6>>("52")
+                .unwrap()
+                .value()
+        );
+        assert!(serde_json::from_str::<BitsyInt<u8, 6>>("64").is_err());
+        assert!(serde_json::from_str::<BitsyInt<u8, 8>>("256").is_err());
+    }
+
     #[test]
     fn random_rountrips() {
         for _ in 0..100 {
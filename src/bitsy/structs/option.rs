@@ -3,6 +3,8 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::bitsy::{result::BitsyResult, BitReader, BitWriter, Bitsy};
 
 #[derive(PartialEq, Eq)]
@@ -44,6 +46,18 @@ impl<T: Bitsy> BitsyOption<T> {
     }
 }
 
+impl<T: Bitsy + Serialize> Serialize for BitsyOption<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T: Bitsy + Deserialize<'de>> Deserialize<'de> for BitsyOption<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::deserialize(deserializer).map(|value| Self { value })
+    }
+}
+
 impl<T: Bitsy> Bitsy for BitsyOption<T> {
     fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
         let is_present: bool = reader.read()?;
@@ -0,0 +1,571 @@
+// What changed between two saves, or two versions of one: items that were added, removed, moved or
+// changed, matched by their GUID, and for characters the attributes, gold, quests and waypoints.
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    export::ItemRecord,
+    item::{Container, NewItem},
+    save::SaveFile,
+};
+
+pub const DIFFICULTIES: [&str; 3] = ["Normal", "Nightmare", "Hell"];
+
+const QUESTS_HEADER_SIZE: usize = 10;
+const QUESTS_SIZE: usize = 96;
+// The words of each difficulty that hold the state of a quest, by act. The words in between are
+// the intro and travel flags of the acts.
+const QUEST_WORDS: [(u8, std::ops::Range<usize>); 5] =
+    [(1, 1..7), (2, 9..15), (3, 17..23), (4, 25..28), (5, 35..41)];
+
+const WAYPOINTS_HEADER_SIZE: usize = 8;
+const WAYPOINTS_SIZE: usize = 24;
+// The bit field starts after 2 bytes of each difficulty.
+const WAYPOINT_BITS_OFFSET: usize = 2;
+const WAYPOINTS: [&str; 39] = [
+    "Rogue Encampment",
+    "Cold Plains",
+    "Stony Field",
+    "Dark Wood",
+    "Black Marsh",
+    "Outer Cloister",
+    "Jail Level 1",
+    "Inner Cloister",
+    "Catacombs Level 2",
+    "Lut Gholein",
+    "Sewers Level 2",
+    "Dry Hills",
+    "Halls of the Dead Level 2",
+    "Far Oasis",
+    "Lost City",
+    "Palace Cellar Level 1",
+    "Arcane Sanctuary",
+    "Canyon of the Magi",
+    "Kurast Docks",
+    "Spider Forest",
+    "Great Marsh",
+    "Flayer Jungle",
+    "Lower Kurast",
+    "Kurast Bazaar",
+    "Upper Kurast",
+    "Travincal",
+    "Durance of Hate Level 2",
+    "Pandemonium Fortress",
+    "City of the Damned",
+    "River of Flame",
+    "Harrogath",
+    "Frigid Highlands",
+    "Arreat Plateau",
+    "Crystalline Passage",
+    "Halls of Pain",
+    "Glacial Trail",
+    "Frozen Tundra",
+    "The Ancients' Way",
+    "Worldstone Keep Level 2",
+];
+
+// Fields of an item record that say where it is rather than what it is.
+const PLACE_FIELDS: [&str; 7] = [
+    "list",
+    "list_index",
+    "item_index",
+    "container",
+    "x",
+    "y",
+    "equipped_slot",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Place {
+    // The list, as in `SaveFile::item_lists`, like "character" or "page 2 (runes)".
+    pub list: String,
+    pub container: Container,
+    pub x: u8,
+    pub y: u8,
+    pub equipped_slot: Option<u8>,
+}
+
+impl Place {
+    fn new(list: &str, item: &NewItem) -> Self {
+        let (x, y) = item.position();
+        Self {
+            list: list.to_string(),
+            container: item.container(),
+            x,
+            y,
+            equipped_slot: Some(item.equipped_slot())
+                .filter(|_| item.container() == Container::Equipped),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.container, self.equipped_slot) {
+            (Container::Equipped, Some(slot)) => format!("{} equipped (slot {})", self.list, slot),
+            (Container::Belt, _) => format!("{} belt (slot {})", self.list, self.x),
+            (container, _) => format!(
+                "{} {} ({}, {})",
+                self.list,
+                container.name(),
+                self.x,
+                self.y
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffItem {
+    pub guid: Option<String>,
+    pub code: Option<String>,
+    pub name: String,
+    pub place: Place,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MovedItem {
+    pub item: DiffItem,
+    pub to: Place,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangedItem {
+    // Where the item is in the second file.
+    pub item: DiffItem,
+    // Fields of the item's export record, see docs/export-format.md.
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValueChange {
+    pub name: String,
+    pub old: Option<u32>,
+    pub new: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuestChange {
+    pub difficulty: &'static str,
+    pub act: u8,
+    // Counted from 1 in the act.
+    pub quest: u8,
+    pub old: u16,
+    pub new: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WaypointChange {
+    pub difficulty: &'static str,
+    pub waypoint: &'static str,
+    pub active: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SaveDiff {
+    pub added: Vec<DiffItem>,
+    pub removed: Vec<DiffItem>,
+    pub moved: Vec<MovedItem>,
+    pub changed: Vec<ChangedItem>,
+    pub attributes: Vec<ValueChange>,
+    // Gold of characters, in the inventory and stash, and of shared stashes.
+    pub gold: Vec<ValueChange>,
+    pub quests: Vec<QuestChange>,
+    pub waypoints: Vec<WaypointChange>,
+}
+
+struct Entry {
+    summary: DiffItem,
+    // The export record without where the item is, to compare items with.
+    record: BTreeMap<String, Value>,
+}
+
+impl Entry {
+    // What items are matched by.
+    fn key(&self) -> String {
+        match &self.summary.guid {
+            Some(guid) => format!("guid {}", guid),
+            None => format!("record {:?}", self.record),
+        }
+    }
+}
+
+// Removes where an item and what is socketed into it are.
+fn remove_place(record: &mut serde_json::Map<String, Value>) {
+    for field in PLACE_FIELDS {
+        record.remove(field);
+    }
+    if let Some(Value::Array(socketed_items)) = record.get_mut("socketed_items") {
+        for socketed in socketed_items {
+            if let Value::Object(socketed) = socketed {
+                remove_place(socketed);
+            }
+        }
+    }
+}
+
+fn entries(save: &SaveFile) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for (list_index, (label, list)) in save.item_lists().into_iter().enumerate() {
+        for (item_index, item) in list.items().iter().enumerate() {
+            let record =
+                match serde_json::to_value(ItemRecord::new(&label, list_index, item_index, item)) {
+                    Ok(Value::Object(mut record)) => {
+                        remove_place(&mut record);
+                        record.into_iter().collect()
+                    }
+                    _ => BTreeMap::new(),
+                };
+            entries.push(Entry {
+                summary: DiffItem {
+                    guid: item.guid(),
+                    code: item.code().map(|code| code.trim_end().to_string()),
+                    name: item.description(),
+                    place: Place::new(&label, item),
+                },
+                record,
+            });
+        }
+    }
+    entries
+}
+
+fn changed_fields(old: &Entry, new: &Entry) -> Vec<String> {
+    let mut fields: Vec<String> = old
+        .record
+        .iter()
+        .filter(|(field, value)| new.record.get(*field) != Some(value))
+        .map(|(field, _)| field.clone())
+        .collect();
+    fields.extend(
+        new.record
+            .keys()
+            .filter(|field| !old.record.contains_key(*field))
+            .cloned(),
+    );
+    fields
+}
+
+impl SaveDiff {
+    pub fn new(old: &SaveFile, new: &SaveFile) -> Self {
+        let mut diff = SaveDiff::default();
+        diff.compare_items(entries(old), entries(new));
+        match (old, new) {
+            (SaveFile::Player(old_player), SaveFile::Player(new_player)) => {
+                let old_values: Vec<_> = old_player.attributes().values();
+                let new_values: Vec<_> = new_player.attributes().values();
+                let mut names: Vec<&str> = old_values.iter().map(|(name, _)| *name).collect();
+                names.extend(
+                    new_values
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .filter(|name| !names.contains(name))
+                        .collect::<Vec<_>>(),
+                );
+                for name in names {
+                    let (old_value, new_value) = (
+                        old_player.attributes().get(name),
+                        new_player.attributes().get(name),
+                    );
+                    if old_value != new_value {
+                        let change = ValueChange {
+                            name: name.to_string(),
+                            old: old_value,
+                            new: new_value,
+                        };
+                        if name.contains("Gold") {
+                            diff.gold.push(change);
+                        } else {
+                            diff.attributes.push(change);
+                        }
+                    }
+                }
+                diff.compare_quests(old_player.quests(), new_player.quests());
+                diff.compare_waypoints(old_player.waypoints(), new_player.waypoints());
+            }
+            (SaveFile::Stash(old_stash), SaveFile::Stash(new_stash))
+                if old_stash.gold() != new_stash.gold() =>
+            {
+                diff.gold.push(ValueChange {
+                    name: "Gold".to_string(),
+                    old: old_stash.gold(),
+                    new: new_stash.gold(),
+                });
+            }
+            _ => {}
+        }
+        diff
+    }
+
+    // Items are matched by GUID. Those without one, like runes and potions, match an item with
+    // the same content. Items in the same place are matched first.
+    fn compare_items(&mut self, old: Vec<Entry>, new: Vec<Entry>) {
+        let mut candidates: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, entry) in new.iter().enumerate() {
+            candidates.entry(entry.key()).or_default().push(index);
+        }
+        let mut unmatched_new: Vec<Option<Entry>> = new.into_iter().map(Some).collect();
+        let mut unmatched_old: Vec<Entry> = old;
+        let mut pairs = Vec::new();
+        for same_place in [true, false] {
+            for old_entry in std::mem::take(&mut unmatched_old) {
+                let index = candidates.get(&old_entry.key()).and_then(|indexes| {
+                    indexes.iter().copied().find(|index| {
+                        unmatched_new[*index].as_ref().is_some_and(|entry| {
+                            !same_place || entry.summary.place == old_entry.summary.place
+                        })
+                    })
+                });
+                match index.and_then(|index| unmatched_new[index].take()) {
+                    Some(new_entry) => pairs.push((old_entry, new_entry)),
+                    None => unmatched_old.push(old_entry),
+                }
+            }
+        }
+
+        self.removed = unmatched_old
+            .into_iter()
+            .map(|entry| entry.summary)
+            .collect();
+        self.added = unmatched_new
+            .into_iter()
+            .flatten()
+            .map(|entry| entry.summary)
+            .collect();
+        for (old_entry, new_entry) in pairs {
+            if old_entry.summary.place != new_entry.summary.place {
+                self.moved.push(MovedItem {
+                    item: old_entry.summary.clone(),
+                    to: new_entry.summary.place.clone(),
+                });
+            }
+            let fields = changed_fields(&old_entry, &new_entry);
+            if !fields.is_empty() {
+                self.changed.push(ChangedItem {
+                    item: new_entry.summary,
+                    fields,
+                });
+            }
+        }
+    }
+
+    fn compare_quests(&mut self, old: &[u8], new: &[u8]) {
+        let word = |bytes: &[u8], offset: usize| {
+            bytes
+                .get(offset..offset + 2)
+                .map_or(0, |word| u16::from_le_bytes([word[0], word[1]]))
+        };
+        for (difficulty_index, difficulty) in DIFFICULTIES.iter().enumerate() {
+            for (act, words) in QUEST_WORDS.iter().cloned() {
+                for (quest, word_index) in words.enumerate() {
+                    let offset =
+                        QUESTS_HEADER_SIZE + difficulty_index * QUESTS_SIZE + word_index * 2;
+                    let (old_word, new_word) = (word(old, offset), word(new, offset));
+                    if old_word != new_word {
+                        self.quests.push(QuestChange {
+                            difficulty,
+                            act,
+                            quest: quest as u8 + 1,
+                            old: old_word,
+                            new: new_word,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn compare_waypoints(&mut self, old: &[u8], new: &[u8]) {
+        let is_active = |bytes: &[u8], offset: usize, index: usize| {
+            bytes
+                .get(offset + index / 8)
+                .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+        };
+        for (difficulty_index, difficulty) in DIFFICULTIES.iter().enumerate() {
+            let offset =
+                WAYPOINTS_HEADER_SIZE + difficulty_index * WAYPOINTS_SIZE + WAYPOINT_BITS_OFFSET;
+            for (index, waypoint) in WAYPOINTS.iter().enumerate() {
+                let active = is_active(new, offset, index);
+                if is_active(old, offset, index) != active {
+                    self.waypoints.push(WaypointChange {
+                        difficulty,
+                        waypoint,
+                        active,
+                    });
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &SaveDiff::default()
+    }
+
+    // One line per difference.
+    pub fn to_lines(&self) -> Vec<String> {
+        let describe = |item: &DiffItem| {
+            let mut description = item.name.clone();
+            if let Some(code) = &item.code {
+                description.push_str(&format!(" [{}]", code));
+            }
+            if let Some(guid) = &item.guid {
+                description.push_str(&format!(" ({})", guid));
+            }
+            description
+        };
+        let value =
+            |value: Option<u32>| value.map_or("none".to_string(), |value| value.to_string());
+        let mut lines = Vec::new();
+        for item in &self.added {
+            lines.push(format!(
+                "added {} in {}",
+                describe(item),
+                item.place.describe()
+            ));
+        }
+        for item in &self.removed {
+            lines.push(format!(
+                "removed {} from {}",
+                describe(item),
+                item.place.describe()
+            ));
+        }
+        for moved in &self.moved {
+            lines.push(format!(
+                "moved {} from {} to {}",
+                describe(&moved.item),
+                moved.item.place.describe(),
+                moved.to.describe()
+            ));
+        }
+        for changed in &self.changed {
+            lines.push(format!(
+                "changed {}: {}",
+                describe(&changed.item),
+                changed.fields.join(", ")
+            ));
+        }
+        for change in self.attributes.iter().chain(&self.gold) {
+            lines.push(format!(
+                "{}: {} -> {}",
+                change.name,
+                value(change.old),
+                value(change.new)
+            ));
+        }
+        for quest in &self.quests {
+            lines.push(format!(
+                "{} act {} quest {}: {:#06x} -> {:#06x}",
+                quest.difficulty, quest.act, quest.quest, quest.old, quest.new
+            ));
+        }
+        for waypoint in &self.waypoints {
+            lines.push(format!(
+                "{} waypoint {}: {}",
+                waypoint.difficulty,
+                waypoint.waypoint,
+                if waypoint.active {
+                    "activated"
+                } else {
+                    "deactivated"
+                }
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, rc::Rc};
+
+    use crate::{
+        item::info::MapItemDb,
+        transfer::{move_item, Destination, PanelSizes},
+    };
+
+    use super::*;
+
+    fn read(path: &str) -> SaveFile {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        SaveFile::read(Path::new(path), item_db).unwrap().1
+    }
+
+    #[test]
+    fn it_finds_no_differences_in_the_same_file() {
+        for path in ["examples/LaCope2.d2s", "stash_example.sss"] {
+            assert!(
+                SaveDiff::new(&read(path), &read(path)).is_empty(),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn it_lists_moved_added_and_removed_items() {
+        let old = read("examples/LaCope2.d2s");
+        let mut new = read("examples/LaCope2.d2s");
+        let sizes = PanelSizes::for_version(new.version());
+        let charm = new.item_lists()[0]
+            .1
+            .items()
+            .iter()
+            .position(|item| item.code().as_deref() == Some("cm1 "))
+            .unwrap();
+        let guid = new.item_lists()[0].1.items()[charm].guid();
+        move_item(&mut new, 0, charm, None, Destination::Stash, &sizes)
+            .unwrap()
+            .unwrap();
+
+        let diff = SaveDiff::new(&old, &new);
+
+        assert_eq!(1, diff.moved.len());
+        assert_eq!(guid, diff.moved[0].item.guid);
+        assert_eq!(Container::Inventory, diff.moved[0].item.place.container);
+        assert_eq!(Container::Stash, diff.moved[0].to.container);
+        assert_eq!(
+            vec![format!(
+                "moved Small Charm [cm1] ({}) from character inventory (0, 4) to character stash \
+                 (2, 0)",
+                guid.unwrap()
+            )],
+            diff.to_lines()
+        );
+
+        let reverse = SaveDiff::new(&read("examples/LaCope2.d2s"), &read("small_stash.sss"));
+        assert_eq!(
+            old.item_lists()
+                .iter()
+                .map(|(_, list)| list.items().len())
+                .sum::<usize>(),
+            reverse.removed.len()
+        );
+        assert!(!reverse.added.is_empty());
+        assert!(reverse.attributes.is_empty());
+    }
+
+    #[test]
+    fn it_lists_progress() {
+        let old = read("examples/StartingD2R.d2s");
+        let new = read("examples/LaCope2.d2s");
+
+        let diff = SaveDiff::new(&old, &new);
+
+        assert!(diff
+            .attributes
+            .iter()
+            .any(|change| change.name == "Level" && change.new == Some(72)));
+        assert!(diff
+            .quests
+            .iter()
+            .any(|quest| quest.difficulty == "Nightmare" && quest.act == 5 && quest.new != 0));
+        assert!(diff.waypoints.contains(&WaypointChange {
+            difficulty: "Normal",
+            waypoint: "Harrogath",
+            active: true,
+        }));
+        let json = serde_json::to_value(&diff).unwrap();
+        assert_eq!("Normal", json["waypoints"][0]["difficulty"]);
+    }
+}
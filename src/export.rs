@@ -0,0 +1,384 @@
+// The JSON written by `d2items export` and read back by `d2items import`, documented in
+// docs/export-format.md. Any change to what is written must bump FORMAT_VERSION.
+//
+// Also the flat CSV of `d2items csv`, one row per item.
+use std::{collections::BTreeMap, io::Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitsy::{bit_string, error::BitsyErrorKind, result::BitsyResult},
+    item::{Container, NewItem},
+    quality::QualityId,
+    save::{hash, SaveFile, SaveKind},
+};
+
+pub const FORMAT: &str = "d2items";
+pub const FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub format: String,
+    pub format_version: u32,
+    pub kind: SaveKind,
+    pub source: Source,
+    // Overviews for readers of the JSON. They are derived from `save` and ignored on import.
+    pub player: Option<PlayerSummary>,
+    pub items: Vec<ItemRecord>,
+    // Everything in the file, which is what import writes back.
+    pub save: SaveFile,
+}
+
+// The exported file, so that an import can tell whether it gives back the same bytes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Source {
+    pub size: usize,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlayerSummary {
+    pub name: String,
+    pub class: String,
+    pub level: u8,
+    pub version: u32,
+    pub attributes: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemRecord {
+    pub list: String,
+    pub list_index: usize,
+    pub item_index: usize,
+    pub container: Container,
+    pub x: u8,
+    pub y: u8,
+    pub equipped_slot: Option<u8>,
+    pub code: Option<String>,
+    pub name: String,
+    pub quality: Option<QualityId>,
+    pub set_or_unique_id: Option<u16>,
+    pub ethereal: bool,
+    pub identified: bool,
+    pub sockets: Option<u8>,
+    pub guid: Option<String>,
+    pub drop_level: Option<u8>,
+    pub defense: Option<u16>,
+    pub durability: Option<u16>,
+    pub max_durability: Option<u16>,
+    pub quantity: Option<u16>,
+    pub properties: Vec<String>,
+    // Bits of properties that could not be decoded, starting with the unknown property id.
+    pub unknown_property_bits: Vec<String>,
+    pub socketed_items: Vec<ItemRecord>,
+}
+
+impl ItemRecord {
+    pub fn new(list: &str, list_index: usize, item_index: usize, item: &NewItem) -> Self {
+        let (x, y) = item.position();
+        Self {
+            list: list.to_string(),
+            list_index,
+            item_index,
+            container: item.container(),
+            x,
+            y,
+            equipped_slot: Some(item.equipped_slot())
+                .filter(|_| item.container() == Container::Equipped),
+            code: item.code().map(|code| code.trim_end().to_string()),
+            name: item.description(),
+            quality: item.quality(),
+            set_or_unique_id: item.set_or_unique_id(),
+            ethereal: item.is_ethereal(),
+            identified: item.is_identified(),
+            sockets: item.socket_count(),
+            guid: item.guid(),
+            drop_level: item.drop_level(),
+            defense: item.defense(),
+            durability: item.durability().map(|(current, _)| current),
+            max_durability: item.durability().map(|(_, max)| max),
+            quantity: item.quantity(),
+            properties: item
+                .properties()
+                .iter()
+                .map(|property| property.description())
+                .collect(),
+            unknown_property_bits: item
+                .unknown_property_bits()
+                .into_iter()
+                .map(bit_string::to_string)
+                .collect(),
+            socketed_items: item
+                .socketed_items()
+                .iter()
+                .map(|socketed| ItemRecord::new(list, list_index, item_index, socketed))
+                .collect(),
+        }
+    }
+
+    // Records for every item in a file, in file order.
+    pub fn all(save: &SaveFile) -> Vec<Self> {
+        save.item_lists()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(list_index, (label, list))| {
+                list.items()
+                    .iter()
+                    .enumerate()
+                    .map(|(item_index, item)| ItemRecord::new(&label, list_index, item_index, item))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl Export {
+    pub fn new(kind: SaveKind, bytes: &[u8], save: SaveFile) -> Self {
+        let player = match &save {
+            SaveFile::Player(player) => Some(PlayerSummary {
+                name: player.name(),
+                class: player.class_name().to_string(),
+                level: player.level(),
+                version: player.version,
+                attributes: player
+                    .attributes()
+                    .values()
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect(),
+            }),
+            SaveFile::Stash(_) => None,
+        };
+        Self {
+            format: FORMAT.to_string(),
+            format_version: FORMAT_VERSION,
+            kind,
+            source: Source {
+                size: bytes.len(),
+                sha256: hash(bytes),
+            },
+            player,
+            items: ItemRecord::all(&save),
+            save,
+        }
+    }
+
+    pub fn from_json(json: &str) -> BitsyResult<Self> {
+        let export: Self = serde_json::from_str(json).map_err(|error| {
+            BitsyErrorKind::InvalidData(format!("Invalid export: {}", error)).at_bit(0)
+        })?;
+        if export.format != FORMAT || export.format_version != FORMAT_VERSION {
+            return Err(BitsyErrorKind::InvalidData(format!(
+                "Unsupported format {} version {}, expected {} version {}",
+                export.format, export.format_version, FORMAT, FORMAT_VERSION
+            ))
+            .at_bit(0));
+        }
+        let is_player = matches!(export.save, SaveFile::Player(_));
+        if is_player != (export.kind == SaveKind::Player) {
+            return Err(BitsyErrorKind::InvalidData(format!(
+                "The save doesn't match the kind {:?}",
+                export.kind
+            ))
+            .at_bit(0));
+        }
+        Ok(export)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    // The bytes of the file, and whether they are those that were exported.
+    pub fn to_bytes(&self) -> BitsyResult<(Vec<u8>, bool)> {
+        let bytes = self.save.to_bytes()?;
+        let unchanged = bytes.len() == self.source.size && hash(&bytes) == self.source.sha256;
+        Ok((bytes, unchanged))
+    }
+}
+
+// A row of the CSV export. Socketed items get rows of their own, at the position of the item
+// they are in.
+#[derive(Debug, Serialize)]
+pub struct CsvRow {
+    pub file: String,
+    // "character", "corpse", "mercenary" or "stash".
+    pub list: String,
+    // Stash pages are counted from 1.
+    pub page: Option<usize>,
+    pub container: &'static str,
+    pub x: u8,
+    pub y: u8,
+    pub code: Option<String>,
+    pub name: Option<String>,
+    pub quality: Option<String>,
+    pub set_or_unique_id: Option<u16>,
+    pub ethereal: bool,
+    pub sockets: Option<u8>,
+    pub guid: Option<String>,
+    pub properties: String,
+}
+
+impl CsvRow {
+    fn new(file: &str, list: &str, page: Option<usize>, item: &NewItem) -> Self {
+        let (x, y) = item.position();
+        Self {
+            file: file.to_string(),
+            list: list.to_string(),
+            page,
+            container: item.container().name(),
+            x,
+            y,
+            code: item.code().map(|code| code.trim_end().to_string()),
+            name: item.item_info().map(|info| info.name.clone()),
+            quality: item
+                .quality()
+                .map(|quality| format!("{:?}", quality).to_lowercase()),
+            set_or_unique_id: item.set_or_unique_id(),
+            ethereal: item.is_ethereal(),
+            sockets: item.socket_count(),
+            guid: item.guid(),
+            properties: item
+                .properties()
+                .iter()
+                .map(|property| property.description())
+                .collect::<Vec<_>>()
+                .join("; "),
+        }
+    }
+
+    // The rows for every item of a file, in file order.
+    pub fn all(file: &str, save: &SaveFile) -> Vec<Self> {
+        let mut rows = Vec::new();
+        for (index, (label, list)) in save.item_lists().into_iter().enumerate() {
+            let (list_name, page) = match save {
+                SaveFile::Player(_) => (label.as_str(), None),
+                SaveFile::Stash(_) => ("stash", Some(index + 1)),
+            };
+            for item in list.items() {
+                rows.push(CsvRow::new(file, list_name, page, item));
+                for socketed in item.socketed_items() {
+                    let mut row = CsvRow::new(file, list_name, page, socketed);
+                    (row.x, row.y) = item.position();
+                    rows.push(row);
+                }
+            }
+        }
+        rows
+    }
+}
+
+pub fn write_csv<W: Write>(writer: W, rows: &[CsvRow]) -> BitsyResult<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for row in rows {
+        writer.serialize(row).map_err(|error| {
+            BitsyErrorKind::Io(format!("Could not write a CSV row: {}", error)).at_bit(0)
+        })?;
+    }
+    writer
+        .flush()
+        .map_err(|error| BitsyErrorKind::Io(format!("Could not write CSV: {}", error)).at_bit(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, rc::Rc};
+
+    use crate::item::info::MapItemDb;
+
+    use super::*;
+
+    fn export(path: &str) -> Export {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        let bytes = std::fs::read(path).unwrap();
+        let (kind, save) = SaveFile::read(Path::new(path), item_db).unwrap();
+        Export::new(kind, &bytes, save)
+    }
+
+    #[test]
+    fn it_imports_exports_unchanged() {
+        for path in [
+            "examples/LaCope2.d2s",
+            "examples/PlasticSurgeon.d2s",
+            "examples/StartingD2R.d2s",
+            "small_stash.sss",
+            "stash_example.sss",
+            "Aleeria.d2x",
+        ] {
+            let json = export(path).to_json().unwrap();
+
+            let (bytes, unchanged) = Export::from_json(&json).unwrap().to_bytes().unwrap();
+
+            assert_eq!(std::fs::read(path).unwrap(), bytes, "{}", path);
+            assert!(unchanged);
+        }
+    }
+
+    #[test]
+    fn it_summarizes_players_and_items() {
+        let export = export("examples/LaCope2.d2s");
+
+        let player = export.player.as_ref().unwrap();
+        assert_eq!("LaCopperfield", player.name);
+        assert_eq!(72, player.level);
+        assert_eq!(Some(&35), player.attributes.get("Strength"));
+        let ring = export
+            .items
+            .iter()
+            .find(|item| item.code.as_deref() == Some("rin"))
+            .unwrap();
+        assert_eq!(Container::Equipped, ring.container);
+        assert_eq!(Some(QualityId::Crafted), ring.quality);
+        assert!(ring.guid.is_some());
+    }
+
+    #[test]
+    fn it_writes_csv_rows() {
+        let player = export("examples/LaCope2.d2s");
+        let rows = CsvRow::all("LaCope2.d2s", &player.save);
+        let mut csv = Vec::new();
+
+        write_csv(&mut csv, &rows).unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            Some(
+                "file,list,page,container,x,y,code,name,quality,set_or_unique_id,ethereal,\
+                 sockets,guid,properties"
+            ),
+            lines.next()
+        );
+        assert_eq!(rows.len(), lines.count());
+        assert!(rows.len() > player.items.len());
+        assert!(rows
+            .iter()
+            .any(|row| row.list == "mercenary" && row.code.as_deref() == Some("rin")));
+        let stash = CsvRow::all("small_stash.sss", &export("small_stash.sss").save);
+        assert!(stash
+            .iter()
+            .all(|row| row.list == "stash" && row.page.is_some()));
+        assert!(stash
+            .iter()
+            .any(|row| row.properties.contains("Fire Resist +30%")));
+    }
+
+    #[test]
+    fn it_notices_changes_and_other_formats() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(&export("small_stash.sss").to_json().unwrap()).unwrap();
+        json["save"]["Stash"]["pages"][3]["items"]["items"][0]["x"] = 0.into();
+
+        let (_, unchanged) = Export::from_json(&json.to_string())
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+        assert!(!unchanged);
+
+        json["format_version"] = (FORMAT_VERSION + 1).into();
+        assert!(Export::from_json(&json.to_string()).is_err());
+        json["format_version"] = FORMAT_VERSION.into();
+        json["kind"] = "Player".into();
+        assert!(Export::from_json(&json.to_string()).is_err());
+    }
+}
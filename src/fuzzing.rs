@@ -0,0 +1,168 @@
+// Entry points for fuzzers, shared by the targets in `fuzz/` and the tests below. Whatever the
+// input, parsing must either fail or give a value that writes back to exactly the bits it was
+// read from. Anything else panics.
+//
+// The targets run with `cargo +nightly fuzz run <player|item_list|item>` from the repository root.
+use std::rc::Rc;
+
+use crate::{
+    bitsy::{
+        compare_bitslices, context, lenient::read_lenient, result::BitsyResult, BitReader,
+        BitVecReader, BitVecWriter, Bitsy, MyBitVec, StreamReader,
+    },
+    item::{
+        info::{ItemDb, MapItemDb},
+        properties::{MapPropertyDb, PropertyDb},
+        ItemList, NewItem, D2R_VERSION,
+    },
+    player::Player,
+};
+
+const VERSIONS: [u32; 2] = [96, 99];
+
+thread_local! {
+    static ITEM_DB: Rc<dyn ItemDb> = Rc::new(MapItemDb::from_data_dir(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/data/items"
+    )));
+    static PROPERTY_DBS: [Rc<dyn PropertyDb>; 2] =
+        [Rc::new(MapPropertyDb::new()), Rc::new(MapPropertyDb::d2r())];
+}
+
+fn reader(data: &[u8], version: Option<u32>) -> BitVecReader {
+    let mut reader =
+        BitVecReader::with_item_db(MyBitVec::from_vec(data.to_vec()), ITEM_DB.with(Rc::clone));
+    if let Some(version) = version {
+        let d2r = usize::from(version >= D2R_VERSION);
+        reader.set_property_db(PROPERTY_DBS.with(|dbs| dbs[d2r].clone()));
+        reader.set_context(&context::VERSION, version);
+    }
+    reader
+}
+
+fn stream_reader(data: &[u8], version: u32) -> StreamReader<&[u8]> {
+    let mut reader = StreamReader::with_item_db(data, ITEM_DB.with(Rc::clone));
+    let d2r = usize::from(version >= D2R_VERSION);
+    reader.set_property_db(PROPERTY_DBS.with(|dbs| dbs[d2r].clone()));
+    reader.set_context(&context::VERSION, version);
+    reader
+}
+
+// Checks that a successful read roundtrips to the first `end` bits of the data.
+fn check<T: Bitsy>(data: &[u8], end: usize, result: BitsyResult<T>, version: u32) {
+    let value = match result {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    let mut writer = BitVecWriter::new(version);
+    if let Err(error) = value.write_to(&mut writer) {
+        panic!(
+            "Parsed {} but could not write it: {}",
+            type_name::<T>(),
+            error
+        );
+    }
+    let bits = MyBitVec::from_vec(data.to_vec());
+    if let Err(error) = compare_bitslices(&bits[..end], &writer.into_bits()) {
+        panic!("{} did not roundtrip: {:?}", type_name::<T>(), error);
+    }
+}
+
+fn type_name<T>() -> &'static str {
+    std::any::type_name::<T>()
+}
+
+pub fn parse_player(data: &[u8]) {
+    let mut strict = reader(data, None);
+    let result = strict.read::<Player>();
+    let version = result.as_ref().map_or(0, |player| player.version);
+    check(data, strict.index(), result, version);
+
+    let mut lenient = reader(data, None);
+    let result = read_lenient::<Player, _>(&mut lenient).map(|(player, _)| player);
+    let version = result.as_ref().map_or(0, |player| player.version);
+    check(data, lenient.index(), result, version);
+}
+
+pub fn parse_item_list(data: &[u8]) {
+    for version in VERSIONS {
+        let mut strict = reader(data, Some(version));
+        let result = strict.read::<ItemList>();
+        check(data, strict.index(), result, version);
+
+        let mut lenient = reader(data, Some(version));
+        let result = read_lenient::<ItemList, _>(&mut lenient).map(|(list, _)| list);
+        check(data, lenient.index(), result, version);
+
+        let mut stream = stream_reader(data, version);
+        let result = read_lenient::<ItemList, _>(&mut stream).map(|(list, _)| list);
+        check(data, stream.index(), result, version);
+    }
+}
+
+pub fn parse_item(data: &[u8]) {
+    for version in VERSIONS {
+        let mut reader = reader(data, Some(version));
+        let result = reader.read::<NewItem>();
+        check(data, reader.index(), result, version);
+    }
+}
+
+pub fn parse_all(data: &[u8]) {
+    parse_player(data);
+    parse_item_list(data);
+    parse_item(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    const SAMPLES: [&str; 6] = [
+        "examples/LaCope2.d2s",
+        "examples/PlasticSurgeon.d2s",
+        "examples/StartingD2R.d2s",
+        "examples/ItemD2R.bin",
+        "examples/CraftedAar.bin",
+        "examples/HoradricCube.bin",
+    ];
+
+    #[test]
+    fn it_survives_mutated_samples() {
+        let mut rng = StdRng::seed_from_u64(0x1d2);
+        for path in SAMPLES {
+            let sample = std::fs::read(path).unwrap();
+            parse_all(&sample);
+            for _ in 0..40 {
+                let mut data = sample.clone();
+                for _ in 0..rng.gen_range(1..4) {
+                    let bit = rng.gen_range(0..data.len() * 8);
+                    data[bit / 8] ^= 1 << (bit % 8);
+                }
+                if rng.gen_bool(0.3) {
+                    data.truncate(rng.gen_range(0..data.len()));
+                }
+                parse_all(&data);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn it_survives_arbitrary_bytes(data in proptest::collection::vec(any::<u8>(), 0..512)) {
+            parse_all(&data);
+        }
+
+        #[test]
+        fn it_survives_arbitrary_items(mut data in proptest::collection::vec(any::<u8>(), 0..128)) {
+            // Gets past the header, so that the item itself is fuzzed.
+            data.splice(0..0, [0x4A, 0x4D]);
+            parse_item(&data);
+            data.splice(0..0, [0x4A, 0x4D, 0x01, 0x00]);
+            parse_item_list(&data);
+        }
+    }
+}
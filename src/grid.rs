@@ -0,0 +1,96 @@
+// Which cells of an inventory grid are taken.
+pub struct Grid {
+    width: u8,
+    height: u8,
+    cells: Vec<bool>,
+}
+
+impl Grid {
+    pub fn new(width: u8, height: u8) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![false; usize::from(width) * usize::from(height)],
+        }
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    fn contains(&self, x: u8, y: u8, width: u8, height: u8) -> bool {
+        u16::from(x) + u16::from(width) <= u16::from(self.width)
+            && u16::from(y) + u16::from(height) <= u16::from(self.height)
+    }
+
+    fn cells(&self, x: u8, y: u8, width: u8, height: u8) -> impl Iterator<Item = usize> {
+        let grid_width = usize::from(self.width);
+        (y..y + height)
+            .flat_map(move |y| (x..x + width).map(move |x| (x, y)))
+            .map(move |(x, y)| usize::from(y) * grid_width + usize::from(x))
+    }
+
+    pub fn is_free(&self, x: u8, y: u8, width: u8, height: u8) -> bool {
+        self.contains(x, y, width, height)
+            && !self.cells(x, y, width, height).any(|cell| self.cells[cell])
+    }
+
+    // Marks the cells as taken. Returns false, taking the cells that are in the grid anyway, if
+    // the item is out of bounds or overlaps another one.
+    pub fn occupy(&mut self, x: u8, y: u8, width: u8, height: u8) -> bool {
+        let fits = self.is_free(x, y, width, height);
+        let (width, height) = (
+            width.min(self.width.saturating_sub(x)),
+            height.min(self.height.saturating_sub(y)),
+        );
+        for cell in self.cells(x, y, width, height) {
+            self.cells[cell] = true;
+        }
+        fits
+    }
+
+    // Takes the first free spot for an item, going row by row or column by column.
+    pub fn place(&mut self, width: u8, height: u8, by_columns: bool) -> Option<(u8, u8)> {
+        let (max_x, max_y) = (
+            self.width.checked_sub(width)?,
+            self.height.checked_sub(height)?,
+        );
+        let spots: Vec<(u8, u8)> = if by_columns {
+            (0..=max_x)
+                .flat_map(|x| (0..=max_y).map(move |y| (x, y)))
+                .collect()
+        } else {
+            (0..=max_y)
+                .flat_map(|y| (0..=max_x).map(move |x| (x, y)))
+                .collect()
+        };
+        let (x, y) = spots
+            .into_iter()
+            .find(|&(x, y)| self.is_free(x, y, width, height))?;
+        self.occupy(x, y, width, height);
+        Some((x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_finds_free_spots() {
+        let mut grid = Grid::new(4, 3);
+        assert!(grid.occupy(0, 0, 2, 3));
+        assert!(!grid.occupy(1, 1, 1, 1));
+        assert!(!grid.occupy(3, 2, 2, 1));
+
+        assert_eq!(Some((2, 0)), grid.place(2, 2, false));
+        assert_eq!(None, grid.place(2, 1, true));
+        assert_eq!(Some((2, 2)), grid.place(1, 1, false));
+        assert!(grid.is_free(3, 0, 0, 0));
+        assert!(!grid.is_free(3, 3, 1, 1));
+    }
+}
@@ -0,0 +1,83 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bitsy::{
+    context, error::BitsyErrorExt, result::BitsyResult, structs::BitsyChars, BitReader, BitWriter,
+    Bitsy, HuffmanChars,
+};
+
+use super::D2R_VERSION;
+
+// The four character item type code, e.g. "rin ". D2R Huffman-encodes it while older versions
+// store plain bytes.
+#[derive(Serialize, Deserialize)]
+pub enum ItemCode {
+    Plain(BitsyChars<4>),
+    Huffman(HuffmanChars<4>),
+}
+
+impl ItemCode {
+    pub fn as_string(&self) -> String {
+        match self {
+            ItemCode::Plain(chars) => chars.as_string(),
+            ItemCode::Huffman(chars) => chars.as_string(),
+        }
+    }
+}
+
+impl Bitsy for ItemCode {
+    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+        if reader.get_context(&context::VERSION)? < D2R_VERSION {
+            reader.read().map(ItemCode::Plain).prepend_path("plain")
+        } else {
+            reader.read().map(ItemCode::Huffman).prepend_path("huffman")
+        }
+    }
+
+    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        match self {
+            ItemCode::Plain(chars) => writer.write(chars),
+            ItemCode::Huffman(chars) => writer.write(chars),
+        }
+    }
+}
+
+impl Debug for ItemCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemCode::Plain(chars) => chars.fmt(f),
+            ItemCode::Huffman(chars) => chars.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitsy::{bitsy_to_bits, BitVecReader};
+
+    use super::*;
+
+    #[test]
+    fn it_depends_on_the_version() {
+        for version in [96, 99] {
+            let code = if version < D2R_VERSION {
+                ItemCode::Plain(BitsyChars::new("rin ").unwrap())
+            } else {
+                ItemCode::Huffman(HuffmanChars::new(['r', 'i', 'n', ' ']))
+            };
+            let bits = bitsy_to_bits(&code, version);
+            let mut reader = BitVecReader::new(bits.clone());
+            reader.set_context(&context::VERSION, version);
+
+            let parsed: ItemCode = reader.read().unwrap();
+
+            assert_eq!("rin ", parsed.as_string());
+            assert_eq!(bits, bitsy_to_bits(&parsed, version));
+        }
+        assert_eq!(
+            32,
+            bitsy_to_bits(&ItemCode::Plain(BitsyChars::new("rin ").unwrap()), 96).len()
+        );
+    }
+}
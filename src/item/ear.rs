@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bitsy::{result::BitsyResult, structs::BitsyInt, Bitsy};
+
+use super::name::PlayerName;
+
+const CLASS_NAMES: [&str; 7] = [
+    "Amazon",
+    "Sorceress",
+    "Necromancer",
+    "Paladin",
+    "Barbarian",
+    "Druid",
+    "Assassin",
+];
+
+// Player ears replace the whole item body (type code, extended info and properties) with the
+// class, level and name of the character they were taken from.
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
+pub struct Ear {
+    class: BitsyInt<u8, 3>,
+    level: BitsyInt<u8, 7>,
+    name: PlayerName,
+}
+
+pub fn class_name(class: u8) -> &'static str {
+    CLASS_NAMES
+        .get(usize::from(class))
+        .copied()
+        .unwrap_or("Unknown")
+}
+
+impl Ear {
+    pub fn new(class: u8, level: u8, name: PlayerName) -> BitsyResult<Self> {
+        Ok(Self {
+            class: BitsyInt::new(class)?,
+            level: BitsyInt::new(level)?,
+            name,
+        })
+    }
+
+    pub fn class(&self) -> u8 {
+        self.class.value()
+    }
+
+    pub fn class_name(&self) -> &'static str {
+        class_name(self.class.value())
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level.value()
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitsy::{bitsy_to_bits, context, testutils::bits, BitReader, BitVecReader};
+
+    use super::*;
+
+    #[test]
+    fn it_reads() {
+        let mut reader = BitVecReader::new(bits("100 1100100 01000010 00010110 00000000"));
+        reader.set_context(&context::VERSION, 99);
+
+        let ear: Ear = reader.read().unwrap();
+
+        assert_eq!(1, ear.class());
+        assert_eq!("Sorceress", ear.class_name());
+        assert_eq!(19, ear.level());
+        assert_eq!("Bh", ear.name());
+        assert!(reader.read_tail().unwrap().is_empty());
+    }
+
+    #[test]
+    fn roundtrips() {
+        let ear = Ear::new(4, 99, PlayerName::new("Ugh").unwrap()).unwrap();
+        let mut reader = BitVecReader::new(bitsy_to_bits(&ear, 99));
+        reader.set_context(&context::VERSION, 99);
+
+        let parsed: Ear = reader.read().unwrap();
+
+        assert_eq!(4, parsed.class());
+        assert_eq!(99, parsed.level());
+        assert_eq!("Ugh", parsed.name());
+        assert!(reader.read_tail().unwrap().is_empty());
+    }
+}
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 use std::path::Path;
+use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
+// The name of item types that aren't in the item data, like those of mods.
+const UNKNOWN_NAME: &str = "?????????";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ItemInfo {
     pub id: String,
@@ -18,7 +22,7 @@ impl ItemInfo {
     fn default(id: &str) -> Self {
         return ItemInfo {
             id: id.to_string(),
-            name: "?????????".to_string(),
+            name: UNKNOWN_NAME.to_string(),
             height: None,
             width: None,
             has_durability: false,
@@ -26,6 +30,12 @@ impl ItemInfo {
             has_quantity: false,
         };
     }
+
+    // Whether the item data has the type. The fields of unknown types that depend on it, like
+    // durability, are guessed.
+    pub fn is_known(&self) -> bool {
+        self.name != UNKNOWN_NAME
+    }
 }
 
 impl Default for ItemInfo {
@@ -34,12 +44,13 @@ impl Default for ItemInfo {
     }
 }
 
+// Infos are shared with every item of their type, as they are looked up for each item parsed.
 pub trait ItemDb {
-    fn get_info(&self, id: &str) -> ItemInfo;
+    fn get_info(&self, id: &str) -> Rc<ItemInfo>;
 }
 
 pub struct MapItemDb {
-    item_infos: HashMap<String, ItemInfo>,
+    item_infos: HashMap<String, Rc<ItemInfo>>,
 }
 
 impl MapItemDb {
@@ -75,14 +86,13 @@ impl MapItemDb {
         has_defense: bool,
         has_quantity: bool,
     ) {
-        let string_path = path.as_ref().to_string_lossy().to_string();
         let mut reader = csv::Reader::from_path(path).unwrap();
         for result in reader.records() {
             let row = result.unwrap();
             let id = row.get(0).unwrap().to_string();
             self.item_infos.insert(
                 id.to_string(),
-                ItemInfo {
+                Rc::new(ItemInfo {
                     id,
                     name: row.get(1).unwrap().to_string(),
                     width: row.get(2).unwrap().parse::<u8>().ok(),
@@ -90,32 +100,17 @@ impl MapItemDb {
                     has_durability,
                     has_defense,
                     has_quantity,
-                },
+                }),
             );
         }
-        println!(
-            "Finished loading data from {}. Final size: {}",
-            string_path,
-            self.item_infos.len()
-        )
     }
 }
 
 impl ItemDb for MapItemDb {
-    fn get_info(&self, id: &str) -> ItemInfo {
+    fn get_info(&self, id: &str) -> Rc<ItemInfo> {
         self.item_infos
             .get(id)
             .map(|x| x.clone())
-            .unwrap_or_else(|| {
-                return ItemInfo {
-                    id: id.to_string(),
-                    name: "?????????".to_string(),
-                    height: None,
-                    width: None,
-                    has_durability: false,
-                    has_defense: false,
-                    has_quantity: false,
-                };
-            })
+            .unwrap_or_else(|| Rc::new(ItemInfo::default(id)))
     }
 }
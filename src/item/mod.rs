@@ -1,49 +1,75 @@
-use std::fmt::{Debug, Display, Formatter};
+use std::{
+    fmt::{Debug, Display, Formatter},
+    rc::Rc,
+};
 
 use bitvec::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     bitsy::{
         bits_from_str, bitsy_to_bits, context,
         error::{BitsyError, BitsyErrorExt, BitsyErrorKind},
+        lenient::Diagnostic,
         macros::{bitsy_cond_read, bitsy_read, bitsy_write},
         parse_int,
         result::BitsyResult,
         structs::{Bits, BitsyBytes, BitsyInt, BitsyOption},
-        BitReader, BitSized, BitWriter, Bitsy, HuffmanChar, HuffmanChars, MyBitVec, OldBitReader,
+        BitReader, BitSized, BitWriter, Bitsy, HuffmanChar, MyBitSlice, MyBitVec, OldBitReader,
         OldBitWriter,
     },
     constants,
 };
 
+use crate::item::code::ItemCode;
+use crate::item::ear::Ear;
 use crate::item::info::ItemInfo;
-use crate::item::properties::PropertyList;
+use crate::item::name::PlayerName;
+use crate::item::opaque::{read_opaque, OpaqueItem};
+use crate::item::properties::{Property, PropertyList, PROPERTY_ID_SIZE, TERMINATOR_ID};
 use crate::item::reader::ItemReader;
+use crate::item::runeword::{RunewordId, RunewordInfo};
 use crate::quality::*;
 
+pub mod code;
+pub mod ear;
 pub mod info;
+pub mod name;
+pub mod opaque;
 pub mod properties;
 pub mod reader;
+pub mod runeword;
 
 pub struct Item {
     header: [u8; 2],
+    is_new: bool,
     _unk1: MyBitVec,
     identified: bool,
     _unk2: MyBitVec,
-    num_sockets: Option<u8>,
+    broken: bool,
     _unk3: MyBitVec,
+    num_sockets: Option<u8>,
+    _unk4: MyBitVec,
+    in_store: bool,
+    _unk5: MyBitVec,
+    is_ear: bool,
+    starter: bool,
+    _unk6: MyBitVec,
     simple: bool,
     ethereal: bool,
-    _unk4: MyBitVec,
+    _unk7: MyBitVec,
     inscribed: Option<Vec<u8>>,
-    _unk5: MyBitVec,
+    _unk8: MyBitVec,
     runeword: Option<u16>,
-    _unk6: MyBitVec,
+    _unk9: MyBitVec,
+    item_version: u16,
+    mode: u8,
+    equipped_slot: u8,
     x: u8,
     y: u8,
-    _unk7: MyBitVec,
+    location: u8,
     item_type: [u8; 4],
-    item_info: ItemInfo,
+    item_info: Rc<ItemInfo>,
     extended_info: Option<ExtendedInfo>,
     random_pad: Option<[u8; 12]>,
     specific_info: Option<SpecificInfo>,
@@ -59,23 +85,34 @@ impl Item {
         // println!("Item initial  bits:{}", bits.peek_bits(512));
         let mut item = Item {
             header: [0; 2],
-            _unk1: BitVec::repeat(false, 4),
+            is_new: false,
+            _unk1: BitVec::repeat(false, 3),
             identified: false,
-            _unk2: BitVec::repeat(false, 6),
+            _unk2: BitVec::repeat(false, 3),
+            broken: false,
+            _unk3: BitVec::repeat(false, 2),
             num_sockets: None,
-            _unk3: BitVec::repeat(false, 9),
+            _unk4: BitVec::repeat(false, 1),
+            in_store: false,
+            _unk5: BitVec::repeat(false, 2),
+            is_ear: false,
+            starter: false,
+            _unk6: BitVec::repeat(false, 3),
             simple: false,
             ethereal: false,
-            _unk4: BitVec::repeat(false, 1),
+            _unk7: BitVec::repeat(false, 1),
             inscribed: None,
-            _unk5: BitVec::repeat(false, 1),
+            _unk8: BitVec::repeat(false, 1),
             runeword: None,
-            _unk6: BitVec::repeat(false, 22),
+            _unk9: BitVec::repeat(false, 5),
+            item_version: 0,
+            mode: 0,
+            equipped_slot: 0,
             x: 0,
             y: 0,
-            _unk7: BitVec::repeat(false, 3),
+            location: 0,
             item_type: [0; 4],
-            item_info: ItemInfo::default(),
+            item_info: Rc::new(ItemInfo::default()),
             extended_info: None,
             random_pad: None,
             specific_info: None,
@@ -85,24 +122,35 @@ impl Item {
         };
 
         item.header = bits.read_byte_arr(); // 16
-        item._unk1 = bits.read_bits(4); // 20
+        item.is_new = bits.read_bool(); // 17
+        item._unk1 = bits.read_bits(3); // 20
         item.identified = bits.read_bool(); // 21
-        item._unk2 = bits.read_bits(6); // 27
+        item._unk2 = bits.read_bits(3); // 24
+        item.broken = bits.read_bool(); // 25
+        item._unk3 = bits.read_bits(2); // 27
         let socketed = bits.read_bool(); // 28
         if socketed {
             item.num_sockets = Some(0);
         }
-        item._unk3 = bits.read_bits(9); // 37
+        item._unk4 = bits.read_bits(1); // 29
+        item.in_store = bits.read_bool(); // 30
+        item._unk5 = bits.read_bits(2); // 32
+        item.is_ear = bits.read_bool(); // 33
+        item.starter = bits.read_bool(); // 34
+        item._unk6 = bits.read_bits(3); // 37
         item.simple = bits.read_bool(); // 38
         item.ethereal = bits.read_bool(); // 39
-        item._unk4 = bits.read_bits(1); // 40
+        item._unk7 = bits.read_bits(1); // 40
         let inscribed = bits.read_bool(); // 41
-        item._unk5 = bits.read_bits(1); // 42
+        item._unk8 = bits.read_bits(1); // 42
         let has_runeword = bits.read_bool(); // 43
-        item._unk6 = bits.read_bits(22); // 65
+        item._unk9 = bits.read_bits(5); // 48
+        item.item_version = bits.read_int(10); // 58
+        item.mode = bits.read_int(3); // 61
+        item.equipped_slot = bits.read_int(4); // 65
         item.x = bits.read_int(4); // 69
         item.y = bits.read_int(4); // 73
-        item._unk7 = bits.read_bits(3); // 76
+        item.location = bits.read_int(3); // 76
         item.item_type = bits.read_byte_arr(); // 108
         item.item_info = bits
             .item_db()
@@ -194,21 +242,32 @@ impl Item {
 
     pub fn append_to(&self, bitvec: &mut MyBitVec) {
         bitvec.extend_from_raw_slice(&self.header);
+        bitvec.append_bool(self.is_new);
         bitvec.append_bits(&self._unk1);
         bitvec.append_bool(self.identified);
         bitvec.append_bits(&self._unk2);
-        bitvec.append_bool(self.num_sockets.is_some());
+        bitvec.append_bool(self.broken);
         bitvec.append_bits(&self._unk3);
+        bitvec.append_bool(self.num_sockets.is_some());
+        bitvec.append_bits(&self._unk4);
+        bitvec.append_bool(self.in_store);
+        bitvec.append_bits(&self._unk5);
+        bitvec.append_bool(self.is_ear);
+        bitvec.append_bool(self.starter);
+        bitvec.append_bits(&self._unk6);
         bitvec.append_bool(self.simple);
         bitvec.append_bool(self.ethereal);
-        bitvec.append_bits(&self._unk4);
+        bitvec.append_bits(&self._unk7);
         bitvec.append_bool(self.inscribed.is_some());
-        bitvec.append_bits(&self._unk5);
+        bitvec.append_bits(&self._unk8);
         bitvec.append_bool(self.runeword.is_some());
-        bitvec.append_bits(&self._unk6);
+        bitvec.append_bits(&self._unk9);
+        bitvec.append_int(self.item_version, 10);
+        bitvec.append_int(self.mode, 3);
+        bitvec.append_int(self.equipped_slot, 4);
         bitvec.append_int(self.x as u32, 4);
         bitvec.append_int(self.y as u32, 4);
-        bitvec.append_bits(&self._unk7);
+        bitvec.append_int(self.location, 3);
         bitvec.extend_from_raw_slice(&self.item_type);
         if let Some(info) = &self.extended_info {
             info.append_to(bitvec, &self);
@@ -228,17 +287,26 @@ impl Display for Item {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Item: {}({}){}{}{}{}{}{}, pos:({},{}) extended:{} specific:{} gems:{} tail is {} bits ({})",
+            "Item: {}({}){}{}{}{}{}{}{}{}{}{}{}, v:{} mode:{} slot:{} pos:({},{}) loc:{} extended:{} specific:{} gems:{} tail is {} bits ({})",
             self.item_info.name,
             arr_to_chr(&self.item_type),
             conditional_display(!self.identified, "u"),
+            conditional_display(self.is_new, "(new)"),
+            conditional_display(self.broken, "(broken)"),
+            conditional_display(self.in_store, "(store)"),
+            conditional_display(self.is_ear, "(ear)"),
+            conditional_display(self.starter, "(starter)"),
             self.num_sockets.map(|ns| format!("({}os)", ns)).unwrap_or("".to_string()),
             conditional_display(self.simple, "(s)"),
             conditional_display(self.ethereal, "(eth)"),
             conditional_display(self.inscribed.is_some(), "(ins)"),
             self.runeword.map(|rw| format!("(rw:{})", rw)).unwrap_or("".to_string()),
+            self.item_version,
+            self.mode,
+            self.equipped_slot,
             self.x,
             self.y,
+            self.location,
             self.extended_info.as_ref().map(|info| format!("[{}]", info)).unwrap_or("None".to_string()),
             self.specific_info.as_ref().map(|info| format!("[{}]", info)).unwrap_or("None".to_string()),
             self.gems.len(),
@@ -296,7 +364,15 @@ impl ExtendedInfo {
             item.runeword = Some(bits.read_int(16));
         }
         if inscribed {
-            panic!("We do not support inscribed items yet");
+            let mut name = Vec::new();
+            loop {
+                let c: u8 = bits.read_int(7);
+                if c == 0 {
+                    break;
+                }
+                name.push(c);
+            }
+            item.inscribed = Some(name);
         }
 
         return (info, gem_count);
@@ -313,6 +389,12 @@ impl ExtendedInfo {
         if let Some(runeword) = item.runeword {
             bitvec.append_int(runeword, 16);
         }
+        if let Some(name) = &item.inscribed {
+            for c in name {
+                bitvec.append_int(*c, 7);
+            }
+            bitvec.append_int(0u8, 7);
+        }
     }
 
     fn parse_quality(quality_id: u8, bits: &mut OldBitReader) -> Box<dyn Quality> {
@@ -476,33 +558,441 @@ impl ItemProperties {
 
 const ITEM_HEADER: [u8; 2] = [0x4A, 0x4D];
 
-#[derive(Debug)]
+// First save version of D2R. Older versions prefix every item with "JM", use 10 bits for the item
+// version and store the item code as plain bytes.
+pub const D2R_VERSION: u32 = 97;
+
+fn item_version_size(version: u32) -> usize {
+    if version < D2R_VERSION {
+        10
+    } else {
+        3
+    }
+}
+
+// Simple items store how many items are socketed into them right after their code, in 1 bit in
+// D2R and 3 bits before. Extended items have it in their extended info instead.
+#[derive(Debug, Serialize, Deserialize)]
+struct SimpleGemCount(u8);
+
+fn simple_gem_count_size(version: u32) -> usize {
+    if version < D2R_VERSION {
+        3
+    } else {
+        1
+    }
+}
+
+impl Bitsy for SimpleGemCount {
+    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+        let version = reader.get_context(&context::VERSION)?;
+        reader
+            .read_int(simple_gem_count_size(version))
+            .map(SimpleGemCount)
+    }
+
+    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        let version = writer
+            .version()
+            .ok_or_else(|| BitsyError::new(BitsyErrorKind::MissingVersion, 0))?;
+        writer.write_int(self.0, simple_gem_count_size(version))
+    }
+}
+
+// Items traded on a realm have a presence bit followed by 96 bits of realm data. D2R still has
+// the bit but never stores the data.
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
+struct RealmData {
+    present: bool,
+    #[bitsy(if = "present && reader.get_context(&context::VERSION)? < D2R_VERSION")]
+    data: Option<Bits<96>>,
+}
+
+pub const MODE_STORED: u8 = 0;
+pub const MODE_EQUIPPED: u8 = 1;
+pub const MODE_BELT: u8 = 2;
+pub const MODE_SOCKETED: u8 = 6;
+
+pub const LOCATION_INVENTORY: u8 = 1;
+pub const LOCATION_CUBE: u8 = 4;
+pub const LOCATION_STASH: u8 = 5;
+
+// Where an item is, from its mode and location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Container {
+    Inventory,
+    Cube,
+    Stash,
+    Equipped,
+    Belt,
+    Socket,
+    Other,
+}
+
+impl Container {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Container::Inventory => "inventory",
+            Container::Cube => "cube",
+            Container::Stash => "stash",
+            Container::Equipped => "equipped",
+            Container::Belt => "belt",
+            Container::Socket => "socket",
+            Container::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NewItem {
-    unknown1: Bits<4>,
+    // The first 32 bits are the item flags (D2Common's `IFLAG_*`). Bits without a known meaning
+    // are kept as-is so the item roundtrips.
+    is_new: bool,
+    unknown1: Bits<3>,
     identified: bool,
-    unknown2: Bits<6>,
+    unknown2: Bits<3>,
+    broken: bool,
+    unknown3: Bits<2>,
     socketed: bool,
-    unknown3: Bits<9>,
+    unknown4: Bits<1>,
+    in_store: bool,
+    unknown5: Bits<2>,
+    is_ear: bool,
+    starter: bool,
+    unknown6: Bits<3>,
     simple: bool,
     ethereal: bool,
-    unknown4: Bits<1>,
+    unknown7: Bits<1>,
     inscribed: bool,
-    unknown5: Bits<1>,
+    unknown8: Bits<1>,
     has_runeword: bool,
-    unknown6: Bits<15>,
+    unknown9: Bits<5>,
+    item_version: u16,
+    mode: BitsyInt<u8, 3>,
+    equipped_slot: BitsyInt<u8, 4>,
     x: BitsyInt<u8, 4>,
     y: BitsyInt<u8, 4>,
     location: BitsyInt<u8, 3>,
-    item_type: HuffmanChars<4>,
-    item_info: ItemInfo,
-    extended_info: Option<NewExtendedInfo>,
-    item_properties: Option<NewPropertyList>,
-    runeword_properties: Option<NewPropertyList>,
-    has_extra_padding: bool,
+    body: ItemBody,
     socketed_items: Vec<NewItem>,
     //tail: MyBitVec,
 }
 
+// Regular items are by far the most common, so boxing them is not worth it.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Serialize, Deserialize)]
+enum ItemBody {
+    Ear(Ear),
+    Regular {
+        item_type: ItemCode,
+        item_info: Rc<ItemInfo>,
+        runeword: Option<RunewordInfo>,
+        extended_info: Option<Box<NewExtendedInfo>>,
+        simple_gem_count: Option<SimpleGemCount>,
+        item_properties: Option<NewPropertyList>,
+        set_properties: Vec<NewPropertyList>,
+        runeword_properties: Option<NewPropertyList>,
+    },
+}
+
+impl NewItem {
+    pub fn is_new(&self) -> bool {
+        self.is_new
+    }
+
+    pub fn set_new(&mut self, is_new: bool) {
+        self.is_new = is_new;
+    }
+
+    pub fn is_identified(&self) -> bool {
+        self.identified
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    pub fn is_socketed(&self) -> bool {
+        self.socketed
+    }
+
+    pub fn is_in_store(&self) -> bool {
+        self.in_store
+    }
+
+    pub fn is_ear(&self) -> bool {
+        self.is_ear
+    }
+
+    pub fn is_starter(&self) -> bool {
+        self.starter
+    }
+
+    pub fn is_simple(&self) -> bool {
+        self.simple
+    }
+
+    pub fn is_ethereal(&self) -> bool {
+        self.ethereal
+    }
+
+    pub fn is_personalized(&self) -> bool {
+        self.inscribed
+    }
+
+    pub fn has_runeword(&self) -> bool {
+        self.has_runeword
+    }
+
+    pub fn ear(&self) -> Option<&Ear> {
+        match &self.body {
+            ItemBody::Ear(ear) => Some(ear),
+            ItemBody::Regular { .. } => None,
+        }
+    }
+
+    pub fn personalized_name(&self) -> Option<&str> {
+        self.extended_info()
+            .and_then(|info| info.personalized_name.as_ref())
+            .map(|name| name.as_str())
+    }
+
+    pub fn set_personalized_name(&mut self, name: Option<&str>) -> BitsyResult<()> {
+        let name = name.map(PlayerName::new).transpose()?;
+        let info = self.extended_info_mut().ok_or_else(|| {
+            BitsyErrorKind::InvalidAction("Only extended items can be personalized".to_string())
+                .at_bit(0)
+        })?;
+        let is_personalized = name.is_some();
+        info.personalized_name = name;
+        self.inscribed = is_personalized;
+        Ok(())
+    }
+
+    pub fn code(&self) -> Option<String> {
+        match &self.body {
+            ItemBody::Regular { item_type, .. } => Some(item_type.as_string()),
+            ItemBody::Ear(_) => None,
+        }
+    }
+
+    pub fn quality(&self) -> Option<QualityId> {
+        self.extended_info()
+            .map(|info| info.quality.get_quality_id())
+    }
+
+    // Whether the item is stored, equipped, in the belt... See `MODE_*`.
+    pub fn mode(&self) -> u8 {
+        self.mode.value()
+    }
+
+    // The panel of stored items. See `LOCATION_*`.
+    pub fn location(&self) -> u8 {
+        self.location.value()
+    }
+
+    pub fn container(&self) -> Container {
+        match (self.mode(), self.location()) {
+            (MODE_STORED, LOCATION_INVENTORY) => Container::Inventory,
+            (MODE_STORED, LOCATION_CUBE) => Container::Cube,
+            (MODE_STORED, LOCATION_STASH) => Container::Stash,
+            (MODE_EQUIPPED, _) => Container::Equipped,
+            (MODE_BELT, _) => Container::Belt,
+            (MODE_SOCKETED, _) => Container::Socket,
+            _ => Container::Other,
+        }
+    }
+
+    pub fn equipped_slot(&self) -> u8 {
+        self.equipped_slot.value()
+    }
+
+    pub fn position(&self) -> (u8, u8) {
+        (self.x.value(), self.y.value())
+    }
+
+    pub fn set_position(&mut self, x: u8, y: u8) -> BitsyResult<()> {
+        self.x = BitsyInt::new(x)?;
+        self.y = BitsyInt::new(y)?;
+        Ok(())
+    }
+
+    // Puts the item at a position of a panel of stored items, see `LOCATION_*`.
+    pub fn store(&mut self, location: u8, x: u8, y: u8) -> BitsyResult<()> {
+        self.mode = BitsyInt::new(MODE_STORED)?;
+        self.equipped_slot = BitsyInt::new(0)?;
+        self.location = BitsyInt::new(location)?;
+        self.set_position(x, y)
+    }
+
+    // The width and height of the item in inventory cells, if it is known.
+    pub fn size(&self) -> Option<(u8, u8)> {
+        match &self.body {
+            ItemBody::Regular { item_info, .. } => item_info.width.zip(item_info.height),
+            ItemBody::Ear(_) => Some((1, 1)),
+        }
+    }
+
+    pub fn item_info(&self) -> Option<&ItemInfo> {
+        match &self.body {
+            ItemBody::Regular { item_info, .. } => Some(item_info),
+            ItemBody::Ear(_) => None,
+        }
+    }
+
+    pub fn runeword_id(&self) -> Option<u16> {
+        self.extended_info()
+            .and_then(|info| info.runeword.as_ref())
+            .map(|id| id.value())
+    }
+
+    pub fn runeword(&self) -> Option<&RunewordInfo> {
+        match &self.body {
+            ItemBody::Regular { runeword, .. } => runeword.as_ref(),
+            ItemBody::Ear(_) => None,
+        }
+    }
+
+    pub fn guid(&self) -> Option<String> {
+        self.extended_info().map(|info| info.guid.to_hex())
+    }
+
+    pub fn set_or_unique_id(&self) -> Option<u16> {
+        self.extended_info()
+            .and_then(|info| info.quality.set_or_unique_id())
+    }
+
+    pub fn socket_count(&self) -> Option<u8> {
+        self.extended_info()
+            .and_then(|info| info.socket_count.as_ref())
+            .map(|count| count.value())
+    }
+
+    // The number of items in the sockets, as stored in the item.
+    pub fn gem_count(&self) -> u8 {
+        match &self.body {
+            ItemBody::Regular {
+                simple_gem_count: Some(count),
+                ..
+            } => count.0,
+            _ => self
+                .extended_info()
+                .map_or(0, |info| info.gem_count.value()),
+        }
+    }
+
+    pub fn socketed_items(&self) -> &[NewItem] {
+        &self.socketed_items
+    }
+
+    pub fn defense(&self) -> Option<u16> {
+        self.extended_info()
+            .and_then(|info| info.defense.as_ref())
+            .map(|defense| defense.value())
+    }
+
+    // Current and maximum durability.
+    pub fn durability(&self) -> Option<(u16, u16)> {
+        let info = self.extended_info()?;
+        info.current_durability.zip(info.max_durability)
+    }
+
+    pub fn quantity(&self) -> Option<u16> {
+        self.extended_info()
+            .and_then(|info| info.quantity.as_ref())
+            .map(|quantity| quantity.value())
+    }
+
+    pub fn drop_level(&self) -> Option<u8> {
+        self.extended_info().map(|info| info.drop_level.value())
+    }
+
+    // All decoded properties: the item's own, then those of its set bonuses and its runeword.
+    pub fn properties(&self) -> Vec<&Property> {
+        match &self.body {
+            ItemBody::Regular {
+                item_properties,
+                set_properties,
+                runeword_properties,
+                ..
+            } => item_properties
+                .iter()
+                .chain(set_properties)
+                .chain(runeword_properties)
+                .flat_map(|list| &list.properties)
+                .collect(),
+            ItemBody::Ear(_) => Vec::new(),
+        }
+    }
+
+    // Property bits that could not be decoded, from the first unknown property id on.
+    pub fn unknown_property_bits(&self) -> Vec<&MyBitSlice> {
+        match &self.body {
+            ItemBody::Regular {
+                item_properties,
+                set_properties,
+                runeword_properties,
+                ..
+            } => item_properties
+                .iter()
+                .chain(set_properties)
+                .chain(runeword_properties)
+                .filter(|list| !list.tail.is_empty())
+                .map(|list| list.tail.as_bitslice())
+                .collect(),
+            ItemBody::Ear(_) => Vec::new(),
+        }
+    }
+
+    pub fn runeword_properties(&self) -> &[Property] {
+        match &self.body {
+            ItemBody::Regular {
+                runeword_properties: Some(properties),
+                ..
+            } => &properties.properties,
+            _ => &[],
+        }
+    }
+
+    // A short human readable name, e.g. "Enigma (Jah Ith Ber) in Mage Plate".
+    pub fn description(&self) -> String {
+        if let Some(ear) = self.ear() {
+            return format!(
+                "{}'s Ear ({} level {})",
+                ear.name(),
+                ear.class_name(),
+                ear.level()
+            );
+        }
+        let base = self
+            .item_info()
+            .map(|info| info.name.clone())
+            .unwrap_or_default();
+        match (self.runeword(), self.runeword_id()) {
+            (Some(runeword), _) => {
+                format!("{} ({}) in {}", runeword.name, runeword.rune_string(), base)
+            }
+            (None, Some(id)) => format!("Runeword<{}> in {}", id, base),
+            (None, None) => base,
+        }
+    }
+
+    fn extended_info(&self) -> Option<&NewExtendedInfo> {
+        match &self.body {
+            ItemBody::Regular { extended_info, .. } => extended_info.as_deref(),
+            ItemBody::Ear(_) => None,
+        }
+    }
+
+    fn extended_info_mut(&mut self) -> Option<&mut NewExtendedInfo> {
+        match &mut self.body {
+            ItemBody::Regular { extended_info, .. } => extended_info.as_deref_mut(),
+            ItemBody::Ear(_) => None,
+        }
+    }
+}
+
 fn search_huffman<R: BitReader>(reader: &mut R, string: &str) {
     let mut bits = MyBitVec::new();
     string
@@ -519,7 +1009,7 @@ impl Bitsy for NewItem {
         let start_bit = reader.index();
         let _reset = reader.queue_context_reset();
         let version = reader.get_context(&context::VERSION)?;
-        if version < 97 {
+        if version < D2R_VERSION {
             let header: [u8; 2] = reader.read()?;
             if header != ITEM_HEADER {
                 return Err(BitsyError::new(
@@ -533,87 +1023,122 @@ impl Bitsy for NewItem {
         }
         bitsy_read!(
             reader,
+            is_new,
             unknown1,
             identified,
             unknown2,
-            socketed,
+            broken,
             unknown3,
+            socketed,
+            unknown4,
+            in_store,
+            unknown5,
+            is_ear,
+            starter,
+            unknown6,
             simple,
             ethereal,
-            unknown4,
+            unknown7,
             inscribed,
-            unknown5,
+            unknown8,
             has_runeword,
-            unknown6,
-            x,
-            y,
-            location,
-            item_type,
+            unknown9,
         );
-        reader.set_context(&context::HAS_RUNEWORD, has_runeword);
-        let x: BitsyInt<u8, 4> = x;
-        let y: BitsyInt<u8, 4> = y;
-        let location: BitsyInt<u8, 3> = location;
-        let simple: bool = simple;
-        let item_type: HuffmanChars<4> = item_type;
-        let item_info = reader.item_db().get_info(&item_type.as_string());
-        reader.set_context(&context::HAS_SOCKETS, socketed);
-        reader.set_context(&context::ITEM_INFO, item_info.clone());
-        //reader.report_next_bytes(512);
-        bitsy_cond_read!(reader, !simple, extended_info, item_properties,);
-
-        let gem_count = extended_info
-            .as_ref()
-            .map(|info: &NewExtendedInfo| info.gem_count.value())
-            .filter(|_| socketed)
-            .unwrap_or(0);
-
-        bitsy_cond_read!(reader, has_runeword, runeword_properties);
-
-        reader.read_padding()?;
-
-        let has_extra_padding = if reader.peek::<u8>()? == 0 {
-            let _: u8 = reader.read()?;
-            true
+        let item_version: u16 =
+            reader.read_named("item_version", |r| r.read_int(item_version_size(version)))?;
+        bitsy_read!(reader, mode, equipped_slot, x, y, location);
+        let is_ear: bool = is_ear;
+        let (body, gem_count) = if is_ear {
+            bitsy_read!(reader, ear);
+            (ItemBody::Ear(ear), 0)
         } else {
-            false
+            bitsy_read!(reader, item_type);
+            reader.set_context(&context::HAS_RUNEWORD, has_runeword);
+            reader.set_context(&context::IS_PERSONALIZED, inscribed);
+            let simple: bool = simple;
+            let item_type: ItemCode = item_type;
+            let item_info = reader.item_db().get_info(&item_type.as_string());
+            reader.set_context(&context::HAS_SOCKETS, socketed);
+            reader.set_context(&context::ITEM_INFO, item_info.clone());
+            //reader.report_next_bytes(512);
+            bitsy_cond_read!(reader, !simple, extended_info);
+            bitsy_cond_read!(reader, simple, simple_gem_count);
+            bitsy_cond_read!(reader, !simple, item_properties);
+
+            let set_list_count = extended_info
+                .as_deref()
+                .and_then(|info: &NewExtendedInfo| info.set_item_mods.as_ref())
+                .map(|mods| mods.as_bitslice().count_ones())
+                .unwrap_or(0);
+            let set_properties = reader.read_named("set_properties", |reader| {
+                (0..set_list_count)
+                    .map(|index| reader.read_element(index))
+                    .collect::<BitsyResult<Vec<_>>>()
+            })?;
+
+            let gem_count = extended_info
+                .as_deref()
+                .map(|info: &NewExtendedInfo| info.gem_count.value())
+                .or_else(|| simple_gem_count.as_ref().map(|count: &SimpleGemCount| count.0))
+                .filter(|_| socketed)
+                .unwrap_or(0);
+
+            bitsy_cond_read!(reader, has_runeword, runeword_properties);
+
+            let runeword = extended_info
+                .as_deref()
+                .and_then(|info: &NewExtendedInfo| info.runeword.as_ref())
+                .and_then(|id| reader.runeword_db().get_runeword(id.value()));
+
+            let body = ItemBody::Regular {
+                item_type,
+                item_info,
+                runeword,
+                extended_info,
+                simple_gem_count,
+                item_properties,
+                set_properties,
+                runeword_properties,
+            };
+            (body, gem_count)
         };
 
-        if item_type.as_string() == "rvl " || item_type.as_string() == "box " {
-            reader.report_next_bytes(30);
-        }
+        reader.read_padding()?;
 
-        let mut socketed_items = Vec::new();
-        for index in 0..gem_count as usize {
-            let item = reader
-                .read()
-                .prepend_index(index)
-                .prepend_path("socketed_items")?;
-            socketed_items.push(item);
-        }
+        let socketed_items = reader.read_named("socketed_items", |reader| {
+            (0..gem_count as usize)
+                .map(|index| reader.read_element(index))
+                .collect::<BitsyResult<Vec<_>>>()
+        })?;
 
         Ok(NewItem {
+            is_new,
             unknown1,
             identified,
             unknown2,
-            socketed,
+            broken,
             unknown3,
+            socketed,
+            unknown4,
+            in_store,
+            unknown5,
+            is_ear,
+            starter,
+            unknown6,
             simple,
             ethereal,
-            unknown4,
+            unknown7,
             inscribed,
-            unknown5,
+            unknown8,
             has_runeword,
-            unknown6,
+            unknown9,
+            item_version,
+            mode,
+            equipped_slot,
             x,
             y,
             location,
-            item_type,
-            item_info,
-            extended_info,
-            item_properties,
-            runeword_properties,
-            has_extra_padding,
+            body,
             socketed_items,
         })
     }
@@ -622,44 +1147,108 @@ impl Bitsy for NewItem {
         let version = writer
             .version()
             .ok_or_else(|| BitsyError::new(BitsyErrorKind::MissingVersion, 0))?;
-        if version < 97 {
+        if version < D2R_VERSION {
             writer.write(&ITEM_HEADER)?;
         }
         bitsy_write!(
             writer,
+            &self.is_new,
             &self.unknown1,
             &self.identified,
             &self.unknown2,
-            &self.socketed,
+            &self.broken,
             &self.unknown3,
+            &self.socketed,
+            &self.unknown4,
+            &self.in_store,
+            &self.unknown5,
+            &self.is_ear,
+            &self.starter,
+            &self.unknown6,
             &self.simple,
             &self.ethereal,
-            &self.unknown4,
+            &self.unknown7,
             &self.inscribed,
-            &self.unknown5,
+            &self.unknown8,
             &self.has_runeword,
-            &self.unknown6,
+            &self.unknown9,
+        );
+        writer
+            .write_int(self.item_version, item_version_size(version))
+            .prepend_path("item_version")?;
+        bitsy_write!(
+            writer,
+            &self.mode,
+            &self.equipped_slot,
             &self.x,
             &self.y,
             &self.location,
-            &self.item_type,
-            &self.extended_info,
-            &self.item_properties,
-            &self.runeword_properties,
         );
+        match &self.body {
+            ItemBody::Ear(ear) => {
+                bitsy_write!(writer, ear);
+            }
+            ItemBody::Regular {
+                item_type,
+                item_info: _,
+                runeword: _,
+                extended_info,
+                simple_gem_count,
+                item_properties,
+                set_properties,
+                runeword_properties,
+            } => {
+                bitsy_write!(
+                    writer,
+                    item_type,
+                    extended_info,
+                    simple_gem_count,
+                    item_properties,
+                    set_properties,
+                    runeword_properties
+                );
+            }
+        }
 
         writer.write_padding()?;
-        if self.has_extra_padding {
-            writer.write(&0u8)?;
-        }
         bitsy_write!(writer, &self.socketed_items);
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ItemList {
     items: Vec<NewItem>,
+    // Items that a lenient read couldn't parse.
+    opaque_items: Vec<OpaqueItem>,
+}
+
+impl ItemList {
+    pub fn items(&self) -> &[NewItem] {
+        &self.items
+    }
+
+    pub fn items_mut(&mut self) -> &mut [NewItem] {
+        &mut self.items
+    }
+
+    // Takes an item, with the items socketed into it, out of the list.
+    pub fn remove(&mut self, index: usize) -> NewItem {
+        for opaque_item in &mut self.opaque_items {
+            if opaque_item.index > index {
+                opaque_item.index -= 1;
+            }
+        }
+        self.items.remove(index)
+    }
+
+    pub fn push(&mut self, item: NewItem) {
+        self.items.push(item);
+    }
+
+    pub fn opaque_items(&self) -> &[OpaqueItem] {
+        &self.opaque_items
+    }
 }
 
 impl Bitsy for ItemList {
@@ -676,26 +1265,76 @@ impl Bitsy for ItemList {
         }
 
         let count: u16 = reader.read()?;
+        let diagnostics = reader.get_context(&context::DIAGNOSTICS).ok();
         let mut items = Vec::new();
-        for index in 0..count {
-            let item = reader.read().prepend_index(index.into())?;
-            items.push(item);
+        let mut opaque_items = Vec::new();
+        let mut index = 0;
+        while index < count {
+            let diagnostics = match &diagnostics {
+                Some(diagnostics) => diagnostics,
+                None => {
+                    items.push(reader.read_element(index.into())?);
+                    index += 1;
+                    continue;
+                }
+            };
+            let error = match reader.read_indexed(index.into(), |r| r.try_read()) {
+                Ok(item) => {
+                    items.push(item);
+                    index += 1;
+                    continue;
+                }
+                Err(error) => error,
+            };
+            let start = reader.index();
+            let opaque = reader.read_indexed(index.into(), |r| {
+                read_opaque(r, items.len(), count - index)
+            })?;
+            let opaque = match opaque {
+                Some(opaque) => opaque,
+                None => return Err(error),
+            };
+            diagnostics.report(Diagnostic {
+                start,
+                end: reader.index(),
+                error,
+            });
+            index += opaque.entry_count;
+            opaque_items.push(opaque);
         }
 
-        Ok(ItemList { items })
+        Ok(ItemList {
+            items,
+            opaque_items,
+        })
     }
 
     fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
         writer.write(&ITEM_HEADER)?;
-        writer.write(&(self.items.len() as u16))?;
-        for item in &self.items {
+        let opaque_count: usize = self
+            .opaque_items
+            .iter()
+            .map(|opaque| opaque.entry_count as usize)
+            .sum();
+        writer.write(&((self.items.len() + opaque_count) as u16))?;
+        let mut opaque_items = self.opaque_items.iter().peekable();
+        for (index, item) in self.items.iter().enumerate() {
+            while let Some(opaque) = opaque_items.next_if(|opaque| opaque.index <= index) {
+                writer.write_bits(&opaque.bits().to_bitvec())?;
+            }
             writer.write(item)?;
         }
+        for opaque in opaque_items {
+            writer.write_bits(&opaque.bits().to_bitvec())?;
+        }
         Ok(())
     }
 }
 
-#[derive(Debug)]
+// Tomes store 5 extra bits after the personalized name.
+const TOME_CODES: [&str; 2] = ["tbk ", "ibk "];
+
+#[derive(Debug, Serialize, Deserialize)]
 struct NewExtendedInfo {
     gem_count: BitsyInt<u8, 3>,
     guid: BitsyBytes<4>,
@@ -703,42 +1342,81 @@ struct NewExtendedInfo {
     gfx: BitsyOption<BitsyInt<u8, 3>>,
     class_info: BitsyOption<Bits<11>>,
     quality: ItemQuality,
-    runeword: Option<Bits<16>>,
-    //realm_data_present: bool,
-    //realm_data_present: BitsyOption<Bits<128>>,
+    runeword: Option<RunewordId>,
+    personalized_name: Option<PlayerName>,
+    tome_data: Option<Bits<5>>,
+    realm_data: RealmData,
     defense: Option<BitsyInt<u16, 11>>,
-    max_durability: Option<BitsyInt<u16, 9>>,
-    current_durability: Option<BitsyInt<u16, 9>>,
+    max_durability: Option<u16>,
+    current_durability: Option<u16>,
     quantity: Option<BitsyInt<u16, 9>>,
-    set_item_mods: Option<Bits<5>>,
     socket_count: Option<BitsyInt<u8, 4>>,
+    set_item_mods: Option<Bits<5>>,
+}
+
+// D2R reads 8 bits of maximum durability, while the 1.10 files we have store 9.
+fn max_durability_size(version: u32) -> usize {
+    if version < D2R_VERSION {
+        9
+    } else {
+        8
+    }
 }
 
+const CURRENT_DURABILITY_SIZE: usize = 9;
+
 impl Bitsy for NewExtendedInfo {
     fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+        let version = reader.get_context(&context::VERSION)?;
         bitsy_read!(reader, gem_count, guid, drop_level);
-        let quality_id: QualityId = reader.read()?;
+        let quality_id: QualityId = reader.read_field("quality_id")?;
         reader.set_context(&context::QUALITY_ID, quality_id);
         bitsy_read!(reader, gfx, class_info, quality);
-        let runeword = reader
-            .get_context(&context::HAS_RUNEWORD)
-            .ok()
-            .filter(|v| *v)
-            .map(|_| reader.read().prepend_path("runeword"))
-            .transpose()?;
-
-        //bitsy_read!(reader, realm_data_present);
+        bitsy_cond_read!(
+            reader,
+            reader.get_context(&context::HAS_RUNEWORD)?,
+            runeword
+        );
+        bitsy_cond_read!(
+            reader,
+            reader.get_context(&context::IS_PERSONALIZED)?,
+            personalized_name
+        );
         let item_info = reader.get_context(&context::ITEM_INFO)?;
+        bitsy_cond_read!(
+            reader,
+            TOME_CODES.contains(&item_info.id.as_str()),
+            tome_data
+        );
+        bitsy_read!(reader, realm_data);
 
         bitsy_cond_read!(reader, item_info.has_defense, defense);
-        bitsy_cond_read!(reader, item_info.has_durability, max_durability);
-
-        let current_durability: Option<BitsyInt<u16, 9>> = max_durability
-            .filter(|d: &BitsyInt<u16, 9>| d.value() > 0)
-            .map(|_| reader.read().prepend_path("current_durability"))
-            .transpose()?;
+        let max_durability: Option<u16> = if item_info.has_durability {
+            Some(reader.read_named("max_durability", |r| {
+                r.read_int(max_durability_size(version))
+            })?)
+        } else {
+            None
+        };
+        let current_durability: Option<u16> = if max_durability.filter(|d| *d > 0).is_some() {
+            Some(reader.read_named("current_durability", |r| {
+                r.read_int(CURRENT_DURABILITY_SIZE)
+            })?)
+        } else {
+            None
+        };
 
-        bitsy_cond_read!(reader, item_info.has_quantity, quantity);
+        let has_sockets = reader.get_context(&context::HAS_SOCKETS)?;
+        // 1.10 stores the socket count before the quantity, D2R after it.
+        let (quantity, socket_count) = if version < D2R_VERSION {
+            bitsy_cond_read!(reader, has_sockets, socket_count);
+            bitsy_cond_read!(reader, item_info.has_quantity, quantity);
+            (quantity, socket_count)
+        } else {
+            bitsy_cond_read!(reader, item_info.has_quantity, quantity);
+            bitsy_cond_read!(reader, has_sockets, socket_count);
+            (quantity, socket_count)
+        };
 
         bitsy_cond_read!(
             reader,
@@ -746,13 +1424,6 @@ impl Bitsy for NewExtendedInfo {
             set_item_mods
         );
 
-        let socket_count: Option<BitsyInt<u8, 4>> = reader
-            .get_context(&context::HAS_SOCKETS)
-            .ok()
-            .filter(|v| *v)
-            .map(|_| reader.read().prepend_path("socket_count"))
-            .transpose()?;
-
         Ok(NewExtendedInfo {
             gem_count,
             guid,
@@ -761,17 +1432,22 @@ impl Bitsy for NewExtendedInfo {
             class_info,
             quality,
             runeword,
-            //realm_data_present,
+            personalized_name,
+            tome_data,
+            realm_data,
             defense,
             max_durability,
             current_durability,
             quantity,
-            set_item_mods,
             socket_count,
+            set_item_mods,
         })
     }
 
     fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        let version = writer
+            .version()
+            .ok_or_else(|| BitsyError::new(BitsyErrorKind::MissingVersion, writer.index()))?;
         bitsy_write!(writer, &self.gem_count, &self.guid, &self.drop_level);
         writer.write(&self.quality.get_quality_id())?;
         bitsy_write!(
@@ -780,22 +1456,41 @@ impl Bitsy for NewExtendedInfo {
             &self.class_info,
             &self.quality,
             &self.runeword,
+            &self.personalized_name,
+            &self.tome_data,
+            &self.realm_data,
             &self.defense,
-            &self.max_durability,
-            &self.current_durability,
-            &self.quantity,
-            &self.set_item_mods,
-            &self.socket_count
         );
+        if let Some(max_durability) = self.max_durability {
+            writer
+                .write_int(max_durability, max_durability_size(version))
+                .prepend_path("max_durability")?;
+        }
+        if let Some(current_durability) = self.current_durability {
+            writer
+                .write_int(current_durability, CURRENT_DURABILITY_SIZE)
+                .prepend_path("current_durability")?;
+        }
+        if version < D2R_VERSION {
+            bitsy_write!(writer, &self.socket_count, &self.quantity);
+        } else {
+            bitsy_write!(writer, &self.quantity, &self.socket_count);
+        }
+        bitsy_write!(writer, &self.set_item_mods);
         Ok(())
     }
 }
 
+type PropertyId = BitsyInt<u16, PROPERTY_ID_SIZE>;
+
 lazy_static::lazy_static! {
     pub static ref PROPERTY_TERMINATOR: MyBitVec = bits_from_str("111111111").unwrap();
 }
 
+#[derive(Serialize, Deserialize)]
 struct NewPropertyList {
+    properties: Vec<Property>,
+    #[serde(with = "crate::bitsy::bit_string")]
     tail: MyBitVec,
 }
 
@@ -814,6 +1509,14 @@ impl NewPropertyList {
 impl Debug for NewPropertyList {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("NewPropertyList")
+            .field(
+                "properties",
+                &self
+                    .properties
+                    .iter()
+                    .map(|property| property.to_string())
+                    .collect::<Vec<_>>(),
+            )
             .field("tail", &self.tail.to_string())
             .field("first_unk_id", &self.first_unknown_id())
             .finish()
@@ -822,12 +1525,58 @@ impl Debug for NewPropertyList {
 
 impl Bitsy for NewPropertyList {
     fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
-        let tail = reader.read_property_tail().prepend_path("tail")?;
+        let property_db = reader.property_db();
+        // The fields before the properties of item types missing from the item data are guessed,
+        // so their properties are kept as raw bits.
+        if !reader
+            .get_context(&context::ITEM_INFO)
+            .map_or(true, |item_info| item_info.is_known())
+        {
+            let tail = reader.read_named("tail", |r| r.read_property_tail())?;
+            return Ok(NewPropertyList {
+                properties: Vec::new(),
+                tail,
+            });
+        }
+        let mut properties = Vec::new();
+        let tail = loop {
+            let id: PropertyId = reader.peek()?;
+            if id.value() == TERMINATOR_ID {
+                let _: PropertyId = reader.read()?;
+                break MyBitVec::new();
+            }
+            match property_db.get_definition(id.value()) {
+                Some(definition) => {
+                    let index = properties.len();
+                    let values = reader.read_named("properties", |reader| {
+                        reader.read_indexed(index, |reader| {
+                            let _: PropertyId = reader.read()?;
+                            definition.read_values(reader)
+                        })
+                    })?;
+                    properties.push(Property::new(definition, values));
+                }
+                // Without a definition the size of the values is unknown, so everything up to the
+                // terminator is kept as raw bits.
+                None => break reader.read_named("tail", |r| r.read_property_tail())?,
+            }
+        };
 
-        Ok(NewPropertyList { tail })
+        Ok(NewPropertyList { properties, tail })
     }
 
     fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        for (index, property) in self.properties.iter().enumerate() {
+            writer
+                .write_int(property.definition().id(), PROPERTY_ID_SIZE)
+                .prepend_index(index)
+                .prepend_path("properties")?;
+            property
+                .definition()
+                .write_values(property.values(), writer)
+                .prepend_index(index)
+                .prepend_path("properties")?;
+        }
         writer.write_bits(&self.tail)?;
         writer.write_bits(&PROPERTY_TERMINATOR)?;
         Ok(())
@@ -861,4 +1610,386 @@ mod tests {
 
         compare_bitslices(&bits, &writer.into_bits()).unwrap();
     }
+
+    #[test]
+    fn parse_item_flags() {
+        let bytes = std::fs::read("examples/LaCope2.d2s").unwrap();
+        // The first item of the player's item list, right after `JM` and the item count.
+        let mut reader = BitVecReader::new(MyBitVec::from_vec(bytes[848..].to_vec()));
+        reader.set_context(&context::VERSION, 99);
+        let mut item: NewItem = reader.read().unwrap();
+
+        assert!(item.is_identified());
+        assert!(item.is_starter());
+        assert!(!item.is_new());
+        assert!(!item.is_ear());
+        assert!(!item.is_socketed());
+        assert!(!item.is_ethereal());
+
+        item.set_new(true);
+        assert!(bitsy_to_bits(&item, 99)[0]);
+    }
+
+    #[test]
+    fn parse_ear() {
+        let flags: u32 = 1 << 4 | 1 << 16 | 1 << 21 | 1 << 23;
+        let ear = Ear::new(2, 87, PlayerName::new("LaCope").unwrap()).unwrap();
+        let mut writer = BitVecWriter::new(99);
+        writer.write(&flags).unwrap();
+        writer.write_int(5u8, 3).unwrap(); // item version
+        writer.write_int(0u8, 3).unwrap(); // mode
+        writer.write_int(0u8, 4).unwrap(); // equipped slot
+        writer.write_int(3u8, 4).unwrap(); // x
+        writer.write_int(1u8, 4).unwrap(); // y
+        writer.write_int(1u8, 3).unwrap(); // location
+        writer.write(&ear).unwrap();
+        writer.write_padding().unwrap();
+        let bits = writer.into_bits();
+
+        let mut with_next_item = bits.clone();
+        with_next_item.extend_from_raw_slice(&ITEM_HEADER);
+        let mut reader = BitVecReader::new(with_next_item);
+        reader.set_context(&context::VERSION, 99);
+        let item: NewItem = reader.read().unwrap();
+
+        assert!(item.is_ear());
+        assert!(item.is_simple());
+        let parsed_ear = item.ear().unwrap();
+        assert_eq!("Necromancer", parsed_ear.class_name());
+        assert_eq!(87, parsed_ear.level());
+        assert_eq!("LaCope", parsed_ear.name());
+        assert_eq!(reader.index(), bits.len());
+
+        compare_bitslices(&bits, &bitsy_to_bits(&item, 99)).unwrap();
+    }
+
+    #[test]
+    fn personalize_item() {
+        let bytes = std::fs::read("examples/LaCope2.d2s").unwrap();
+        let mut reader = BitVecReader::new(MyBitVec::from_vec(bytes[844..].to_vec()));
+        reader.set_context(&context::VERSION, 99);
+        let mut list: ItemList = reader.read().unwrap();
+        let item = list
+            .items
+            .iter_mut()
+            .find(|item| item.extended_info().is_some() && item.socketed_items.is_empty())
+            .unwrap();
+        let original = bitsy_to_bits(item, 99);
+        assert_eq!(None, item.personalized_name());
+
+        item.set_personalized_name(Some("Aleeria")).unwrap();
+        let mut personalized = bitsy_to_bits(item, 99);
+        personalized.extend_from_raw_slice(&ITEM_HEADER);
+        let mut reader = BitVecReader::new(personalized);
+        reader.set_context(&context::VERSION, 99);
+        let mut reparsed: NewItem = reader.read().unwrap();
+        assert!(reparsed.is_personalized());
+        assert_eq!(Some("Aleeria"), reparsed.personalized_name());
+
+        reparsed.set_personalized_name(None).unwrap();
+        assert!(!reparsed.is_personalized());
+        compare_bitslices(&original, &bitsy_to_bits(&reparsed, 99)).unwrap();
+    }
+
+    #[test]
+    fn decode_property_list() {
+        use crate::bitsy::testutils::bits;
+        use crate::item::properties::MapPropertyDb;
+        use std::rc::Rc;
+
+        // +7 to Strength, an unknown property (id 500) and the terminator.
+        let original = bits("000000000 11100100 001011111 1010 111111111");
+
+        let mut reader = BitVecReader::new(original.clone());
+        reader.set_property_db(Rc::new(MapPropertyDb::d2r()));
+        let list: NewPropertyList = reader.read().unwrap();
+        assert_eq!(1, list.properties.len());
+        assert_eq!(0, list.properties[0].definition().id());
+        assert_eq!(7, list.properties[0].values()[0]);
+        assert_eq!(Some("500".to_string()), list.first_unknown_id());
+        compare_bitslices(&original, &bitsy_to_bits(&list, 99)).unwrap();
+
+        let mut reader = BitVecReader::new(original.clone());
+        let list: NewPropertyList = reader.read().unwrap();
+        assert!(list.properties.is_empty());
+        compare_bitslices(&original, &bitsy_to_bits(&list, 99)).unwrap();
+
+        // The properties of item types missing from the item data are not decoded.
+        let mut reader = BitVecReader::new(original.clone());
+        reader.set_property_db(Rc::new(MapPropertyDb::d2r()));
+        reader.set_context(&context::ITEM_INFO, Rc::new(ItemInfo::default()));
+        let list: NewPropertyList = reader.read().unwrap();
+        assert!(list.properties.is_empty());
+        assert_eq!(original.len() - 9, list.tail.len());
+        compare_bitslices(&original, &bitsy_to_bits(&list, 99)).unwrap();
+    }
+
+    #[test]
+    fn parse_crafted_item() {
+        let bits = MyBitVec::from_vec(std::fs::read("examples/CraftedAar.bin").unwrap());
+        let mut reader = BitVecReader::new(bits.clone());
+        reader.set_context(&context::VERSION, 99);
+
+        let item: NewItem = reader.read().unwrap();
+
+        assert!(matches!(
+            item.extended_info().unwrap().quality,
+            ItemQuality::Crafted { .. }
+        ));
+        assert!(reader.read_tail().unwrap().is_empty());
+        compare_bitslices(&bits, &bitsy_to_bits(&item, 99)).unwrap();
+    }
+
+    #[test]
+    fn parse_legacy_items() {
+        use crate::item::info::MapItemDb;
+        use crate::item::properties::MapPropertyDb;
+        use std::rc::Rc;
+
+        // A 1.10 shared stash: a 14 byte header and pages made of "ST\0" and an item list.
+        let bits = MyBitVec::from_vec(std::fs::read("small_stash.sss").unwrap());
+        let mut reader = BitVecReader::with_item_db(
+            bits.clone(),
+            Rc::new(MapItemDb::from_data_dir("data/items")),
+        );
+        reader.set_property_db(Rc::new(MapPropertyDb::new()));
+        reader.set_context(&context::VERSION, 96);
+
+        let header: [u8; 14] = reader.read().unwrap();
+        let mut writer = BitVecWriter::new(96);
+        writer.write(&header).unwrap();
+        while reader.index() < bits.len() {
+            let page_header: [u8; 3] = reader.read().unwrap();
+            assert_eq!(b"ST\0", &page_header);
+            let items: ItemList = reader.read().unwrap();
+            writer.write(&page_header).unwrap();
+            writer.write(&items).unwrap();
+        }
+
+        compare_bitslices(&bits, &writer.into_bits()).unwrap();
+    }
+
+    #[test]
+    fn realm_data_depends_on_version() {
+        use crate::bitsy::testutils::bits;
+
+        let original = bits(format!("1{}", "01".repeat(48)));
+        for (version, size) in [(96, 97), (99, 1)] {
+            let mut reader = BitVecReader::new(original.clone());
+            reader.set_context(&context::VERSION, version);
+
+            let realm_data: RealmData = reader.read().unwrap();
+
+            assert!(realm_data.present);
+            assert_eq!(size, reader.index());
+            compare_bitslices(&original[..size], &bitsy_to_bits(&realm_data, version)).unwrap();
+        }
+    }
+
+    #[test]
+    fn simple_gem_count_depends_on_version() {
+        use crate::bitsy::testutils::bits;
+
+        let original = bits("110 1");
+        for (version, size, count) in [(96, 3, 3), (99, 1, 1)] {
+            let mut reader = BitVecReader::new(original.clone());
+            reader.set_context(&context::VERSION, version);
+
+            let gem_count: SimpleGemCount = reader.read().unwrap();
+
+            assert_eq!(count, gem_count.0);
+            assert_eq!(size, reader.index());
+            compare_bitslices(&original[..size], &bitsy_to_bits(&gem_count, version)).unwrap();
+        }
+    }
+
+    mod generated {
+        use std::rc::Rc;
+
+        use proptest::prelude::*;
+        use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+        use serde_json::{json, Value};
+
+        use crate::{
+            bitsy::{context, BitReader, BitVecReader, BitVecWriter, Bitsy, MyBitVec},
+            item::{
+                info::{ItemDb, MapItemDb},
+                properties::{MapPropertyDb, PropertyDb},
+                ItemList, NewItem, D2R_VERSION, ITEM_HEADER,
+            },
+            player::Player,
+        };
+
+        struct Dbs {
+            items: Rc<dyn ItemDb>,
+            legacy_properties: Rc<dyn PropertyDb>,
+            d2r_properties: Rc<dyn PropertyDb>,
+        }
+
+        fn reader(dbs: &Dbs, bits: MyBitVec, version: u32) -> BitVecReader {
+            let mut reader = BitVecReader::with_item_db(bits, dbs.items.clone());
+            reader.set_property_db(if version < D2R_VERSION {
+                dbs.legacy_properties.clone()
+            } else {
+                dbs.d2r_properties.clone()
+            });
+            reader.set_context(&context::VERSION, version);
+            reader
+        }
+
+        // Every item of the sample files, as JSON, with the version to write it with.
+        fn templates(dbs: &Dbs) -> Vec<(u32, Value)> {
+            let mut templates = Vec::new();
+            for path in ["examples/LaCope2.d2s", "examples/PlasticSurgeon.d2s"] {
+                let bits = MyBitVec::from_vec(std::fs::read(path).unwrap());
+                let mut reader = reader(dbs, bits, 99);
+                let player: Player = reader.read().unwrap();
+                let player = serde_json::to_value(&player).unwrap();
+                for item in player["items"]["items"].as_array().unwrap() {
+                    templates.push((player["version"].as_u64().unwrap() as u32, item.clone()));
+                }
+            }
+
+            let bits = MyBitVec::from_vec(std::fs::read("small_stash.sss").unwrap());
+            let mut reader = reader(dbs, bits, 96);
+            let _header: [u8; 14] = reader.read().unwrap();
+            while reader.read::<[u8; 3]>().is_ok() {
+                let list: ItemList = reader.read().unwrap();
+                for item in list.items() {
+                    templates.push((96, serde_json::to_value(item).unwrap()));
+                }
+            }
+            templates
+        }
+
+        thread_local! {
+            static DBS: Dbs = Dbs {
+                items: Rc::new(MapItemDb::from_data_dir("data/items")),
+                legacy_properties: Rc::new(MapPropertyDb::new()),
+                // Not all D2R properties are known yet, so they are kept as bits.
+                d2r_properties: Rc::new(MapPropertyDb::empty()),
+            };
+            static TEMPLATES: Vec<(u32, Value)> = DBS.with(templates);
+        }
+
+        fn bits(rng: &mut StdRng, length: usize) -> Value {
+            json!((0..length)
+                .map(|_| if rng.gen() { '1' } else { '0' })
+                .collect::<String>())
+        }
+
+        fn set_if_present(value: &mut Value, key: &str, new_value: impl FnOnce() -> Value) {
+            if !value[key].is_null() {
+                value[key] = new_value();
+            }
+        }
+
+        const FLAGS: [&str; 6] = [
+            "is_new",
+            "identified",
+            "broken",
+            "in_store",
+            "starter",
+            "ethereal",
+        ];
+
+        // Changes everything in the item that doesn't decide which fields follow, keeping every
+        // value within the bits it's stored in.
+        fn mutate(item: &mut Value, version: u32, rng: &mut StdRng) {
+            for flag in FLAGS {
+                item[flag] = json!(rng.gen::<bool>());
+            }
+            for unknown in (1..=9).map(|index| format!("unknown{}", index)) {
+                let length = item[&unknown].as_str().unwrap().len();
+                item[&unknown] = bits(rng, length);
+            }
+            for (field, size) in [("mode", 3), ("equipped_slot", 4), ("x", 4), ("y", 4)] {
+                item[field] = json!(rng.gen_range(0..1 << size));
+            }
+
+            let mut inscribed = None;
+            if let Some(body) = item["body"].get_mut("Regular") {
+                if let Some(info) = body.get_mut("extended_info").filter(|info| !info.is_null()) {
+                    info["guid"] = json!(format!("{:08x}", rng.gen::<u32>()));
+                    info["drop_level"] = json!(rng.gen_range(0..128));
+                    set_if_present(info, "defense", || json!(rng.gen_range(0..1 << 11)));
+                    set_if_present(info, "quantity", || json!(rng.gen_range(0..1 << 9)));
+                    set_if_present(info, "socket_count", || json!(rng.gen_range(0..16)));
+                    // A current durability is only stored when there is a maximum.
+                    if info["max_durability"].as_u64().unwrap_or(0) > 0 {
+                        let max_size = if version < D2R_VERSION { 9 } else { 8 };
+                        info["max_durability"] = json!(rng.gen_range(1..1 << max_size));
+                        info["current_durability"] = json!(rng.gen_range(0..1 << 9));
+                    }
+                    let name: String = (0..rng.gen_range(2..16))
+                        .map(|_| *b"abcdefghijklmnopqrstuvwxyzAZ_-".choose(rng).unwrap() as char)
+                        .collect();
+                    let personalized = rng.gen::<bool>();
+                    info["personalized_name"] = json!(personalized.then_some(name));
+                    inscribed = Some(personalized);
+                }
+
+                for list in ["item_properties", "runeword_properties"] {
+                    let properties = body[list]
+                        .get_mut("properties")
+                        .and_then(Value::as_array_mut);
+                    for property in properties.into_iter().flatten() {
+                        let definitions = property["definition"]["values"].clone();
+                        for (value, definition) in property["values"]
+                            .as_array_mut()
+                            .unwrap()
+                            .iter_mut()
+                            .zip(definitions.as_array().unwrap())
+                        {
+                            let size = definition["size"].as_u64().unwrap();
+                            let offset = definition["offset"].as_i64().unwrap();
+                            if size > 0 {
+                                *value = json!(rng.gen_range(0..1i64 << size) - offset);
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(inscribed) = inscribed {
+                item["inscribed"] = json!(inscribed);
+            }
+
+            for socketed in item["socketed_items"].as_array_mut().unwrap() {
+                let is_new = socketed["is_new"].clone();
+                mutate(socketed, version, rng);
+                // Undecoded D2R properties end at the last one of the terminator's run of ones,
+                // which would take in the first flag of the item after them.
+                if version >= D2R_VERSION {
+                    socketed["is_new"] = is_new;
+                }
+            }
+        }
+
+        fn write(item: &NewItem, version: u32) -> MyBitVec {
+            let mut writer = BitVecWriter::new(version);
+            item.write_to(&mut writer).unwrap();
+            writer.into_bits()
+        }
+
+        proptest! {
+            #[test]
+            fn it_roundtrips_generated_items(template in any::<prop::sample::Index>(), seed: u64) {
+                let (version, mut expected) =
+                    TEMPLATES.with(|templates| templates[template.index(templates.len())].clone());
+                mutate(&mut expected, version, &mut StdRng::seed_from_u64(seed));
+                let item: NewItem = serde_json::from_value(expected.clone()).unwrap();
+
+                let written = write(&item, version);
+                let mut input = written.clone();
+                input.extend_from_raw_slice(&ITEM_HEADER);
+                let mut reader = DBS.with(|dbs| reader(dbs, input, version));
+                let reparsed: NewItem = reader.read().unwrap();
+
+                prop_assert_eq!(written.len(), reader.index());
+                prop_assert_eq!(&expected, &serde_json::to_value(&reparsed).unwrap());
+                prop_assert_eq!(written, write(&reparsed, version));
+            }
+        }
+    }
 }
@@ -0,0 +1,193 @@
+use std::fmt::Debug;
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::bitsy::{
+    context,
+    error::{BitsyError, BitsyErrorKind},
+    result::BitsyResult,
+    BitReader, BitWriter, Bitsy,
+};
+
+const MAX_NAME_LENGTH: usize = 15;
+
+// Names are stored as NUL-terminated strings of 7-bit characters, which became 8-bit characters
+// after version 97.
+fn char_size(version: u32) -> usize {
+    if version > 97 {
+        8
+    } else {
+        7
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct PlayerName {
+    name: String,
+}
+
+impl PlayerName {
+    pub fn new<S: AsRef<str>>(name: S) -> BitsyResult<Self> {
+        let name = name.as_ref();
+        if name.chars().count() > MAX_NAME_LENGTH {
+            return Err(BitsyErrorKind::InvalidData(format!(
+                "Name '{name}' is longer than {MAX_NAME_LENGTH} characters"
+            ))
+            .at_bit(0));
+        }
+        // Any character of a byte, as 8-bit versions have them. Those of 7-bit versions are checked
+        // when written, as names don't know their version.
+        if let Some(c) = name.chars().find(|c| *c == '\0' || *c > '\u{FF}') {
+            return Err(BitsyErrorKind::InvalidData(format!(
+                "Invalid character {c:?} in name '{name}'"
+            ))
+            .at_bit(0));
+        }
+        Ok(Self {
+            name: name.to_string(),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Serialize for PlayerName {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for PlayerName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::new(String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+impl Bitsy for PlayerName {
+    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+        let char_size = char_size(reader.get_context(&context::VERSION)?);
+        // Counted in characters, as those from 0x80 take two bytes in the string.
+        let mut name = String::new();
+        loop {
+            let value: u8 = reader.read_int(char_size)?;
+            if value == 0 {
+                break;
+            } else if name.chars().count() == MAX_NAME_LENGTH {
+                return Err(BitsyErrorKind::InvalidData(format!(
+                    "Name '{name}' is not terminated after {MAX_NAME_LENGTH} characters"
+                ))
+                .at_bit(reader.index() - char_size));
+            }
+            name.push(value as char);
+        }
+        Ok(Self { name })
+    }
+
+    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        let version = writer
+            .version()
+            .ok_or_else(|| BitsyError::new(BitsyErrorKind::MissingVersion, writer.index()))?;
+        let char_size = char_size(version);
+        for c in self.name.chars() {
+            if u32::from(c) >= 1 << char_size {
+                return Err(BitsyErrorKind::InvalidData(format!(
+                    "Character {c:?} of name '{}' doesn't fit in {char_size} bits",
+                    self.name
+                ))
+                .at_bit(writer.index()));
+            }
+            writer.write_int(c as u8, char_size)?;
+        }
+        writer.write_int(0u8, char_size)
+    }
+}
+
+impl Debug for PlayerName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PN<{:?}>", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitsy::{bitsy_to_bits, testutils::bits, BitVecReader, BitVecWriter};
+
+    use super::*;
+
+    #[test]
+    fn it_reads_7bit_chars() {
+        let mut reader = BitVecReader::new(bits("0100001 0001011 0000000 1"));
+        reader.set_context(&context::VERSION, 96);
+
+        let name: PlayerName = reader.read().unwrap();
+
+        assert_eq!("Bh", name.as_str());
+        assert_eq!(reader.index(), 21);
+    }
+
+    #[test]
+    fn it_reads_8bit_chars() {
+        let mut reader = BitVecReader::new(bits("01000010 00010110 00000000 1"));
+        reader.set_context(&context::VERSION, 99);
+
+        let name: PlayerName = reader.read().unwrap();
+
+        assert_eq!("Bh", name.as_str());
+        assert_eq!(reader.index(), 24);
+    }
+
+    #[test]
+    fn it_fails_on_unterminated_names() {
+        let mut reader = BitVecReader::new(bits("10000110".repeat(MAX_NAME_LENGTH + 1)));
+        reader.set_context(&context::VERSION, 99);
+
+        assert!(reader.read::<PlayerName>().is_err());
+    }
+
+    #[test]
+    fn it_fails_on_unterminated_names_of_8bit_chars() {
+        // 0xE9
+        let mut reader = BitVecReader::new(bits("10010111".repeat(MAX_NAME_LENGTH + 1)));
+        reader.set_context(&context::VERSION, 99);
+
+        let error = reader.read::<PlayerName>().unwrap_err();
+
+        assert_eq!(8 * MAX_NAME_LENGTH, error.bit());
+    }
+
+    #[test]
+    fn it_rejects_long_names() {
+        assert!(PlayerName::new("a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+        assert!(PlayerName::new("a".repeat(MAX_NAME_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn roundtrips() {
+        for version in [96, 99] {
+            let name = PlayerName::new("Aleeria").unwrap();
+            let mut reader = BitVecReader::new(bitsy_to_bits(&name, version));
+            reader.set_context(&context::VERSION, version);
+
+            assert_eq!(name, reader.read().unwrap());
+            assert!(reader.read_tail().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn it_accepts_latin1_chars_of_8bit_versions() {
+        let name = PlayerName::new("Ælfrïc").unwrap();
+        let json = serde_json::to_string(&name).unwrap();
+        let imported: PlayerName = serde_json::from_str(&json).unwrap();
+        assert_eq!(name, imported);
+
+        let mut reader = BitVecReader::new(bitsy_to_bits(&imported, 99));
+        reader.set_context(&context::VERSION, 99);
+        assert_eq!(name, reader.read().unwrap());
+
+        let mut writer = BitVecWriter::new(96);
+        assert!(writer.write(&name).is_err());
+        assert!(PlayerName::new("Łukasz").is_err());
+    }
+}
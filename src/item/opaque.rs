@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+
+use crate::bitsy::{context, result::BitsyResult, BitReader, MyBitSlice, MyBitVec};
+
+use super::{NewItem, D2R_VERSION, ITEM_HEADER};
+
+const SOCKETED_LOCATION: u8 = 6;
+
+// An item that a lenient read couldn't parse. It's kept as the raw bits from where the item
+// started up to where parsing resumed, and written back unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpaqueItem {
+    // The number of parsed items in the list before this one.
+    pub(super) index: usize,
+    // How many items of the list's item count the bits stand for.
+    pub(super) entry_count: u16,
+    start: usize,
+    #[serde(with = "crate::bitsy::bit_string")]
+    bits: MyBitVec,
+}
+
+impl OpaqueItem {
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn bits(&self) -> &MyBitSlice {
+        &self.bits
+    }
+
+    pub fn entry_count(&self) -> u16 {
+        self.entry_count
+    }
+}
+
+// Skips a broken item, up to the next byte-aligned item header that starts an item which isn't
+// socketed into the broken one. D2R items have no headers, so there the rest of the list is
+// skipped up to the next header, which is usually the next item list. Returns None if there is
+// nowhere to resume.
+pub(super) fn read_opaque<R: BitReader>(
+    reader: &mut R,
+    index: usize,
+    remaining: u16,
+) -> BitsyResult<Option<OpaqueItem>> {
+    let start = reader.index();
+    let version = reader.get_context(&context::VERSION)?;
+    let header = MyBitVec::from_vec(ITEM_HEADER.to_vec());
+    let entry_count = if version < D2R_VERSION { 1 } else { remaining };
+    let is_last = entry_count == remaining;
+
+    let mut bits = MyBitVec::new();
+    // Skips the header of the broken item itself.
+    let mut offset = if version < D2R_VERSION { 16 } else { 8 };
+    loop {
+        let found = match next_header(reader, &header, offset) {
+            Some(found) => found,
+            None if is_last => {
+                bits.extend_from_bitslice(&reader.read_tail()?);
+                break;
+            }
+            None => return Ok(None),
+        };
+        bits.extend_from_bitslice(&reader.read_bits(found)?);
+        if version >= D2R_VERSION {
+            break;
+        }
+        let resumes = match reader.peek::<NewItem>() {
+            Ok(item) => item.location.value() != SOCKETED_LOCATION,
+            // Whatever follows the list.
+            Err(_) => is_last,
+        };
+        if resumes {
+            break;
+        }
+        offset = 16;
+    }
+
+    Ok(Some(OpaqueItem {
+        index,
+        entry_count,
+        start,
+        bits,
+    }))
+}
+
+fn next_header<R: BitReader>(reader: &R, header: &MyBitVec, mut offset: usize) -> Option<usize> {
+    loop {
+        let found = reader.search(header, offset)?;
+        if (reader.index() + found).is_multiple_of(8) {
+            return Some(found);
+        }
+        offset = found + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        bitsy::{
+            bitsy_to_bits, compare_bitslices, lenient::read_lenient, BitReader, BitVecReader,
+            StreamReader,
+        },
+        item::{info::MapItemDb, properties::MapPropertyDb},
+        player::Player,
+        stash::{NewStash, STASH_VERSION},
+    };
+
+    use super::*;
+
+    fn stash_reader(bytes: Vec<u8>) -> BitVecReader {
+        let mut reader = BitVecReader::with_item_db(
+            MyBitVec::from_vec(bytes),
+            Rc::new(MapItemDb::from_data_dir("data/items")),
+        );
+        reader.set_property_db(Rc::new(MapPropertyDb::new()));
+        reader
+    }
+
+    fn corrupt(path: &str, bytes: std::ops::Range<usize>) -> Vec<u8> {
+        let mut data = std::fs::read(path).unwrap();
+        data[bytes].iter_mut().for_each(|byte| *byte = 0xFF);
+        data
+    }
+
+    #[test]
+    fn it_resumes_at_the_next_item() {
+        let bytes = corrupt("small_stash.sss", 51..63);
+        assert!(stash_reader(bytes.clone()).read::<NewStash>().is_err());
+
+        let (stash, diagnostics) =
+            read_lenient::<NewStash, _>(&mut stash_reader(bytes.clone())).unwrap();
+
+        assert_eq!(1, diagnostics.len());
+        assert_eq!((67 * 8, 84 * 8), (diagnostics[0].start, diagnostics[0].end));
+        let list = stash.pages()[2].items();
+        assert_eq!(15, list.items().len());
+        assert_eq!(1, list.opaque_items()[0].entry_count());
+        assert_eq!(67 * 8, list.opaque_items()[0].start());
+        assert_eq!(11, stash.pages()[3].items().items().len());
+        compare_bitslices(
+            &MyBitVec::from_vec(bytes),
+            &bitsy_to_bits(&stash, STASH_VERSION),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn it_resumes_while_streaming() {
+        let bytes = corrupt("stash_example.sss", 1097..1109);
+        let (expected, _) = read_lenient::<NewStash, _>(&mut stash_reader(bytes.clone())).unwrap();
+        let mut reader =
+            StreamReader::with_item_db(&bytes[..], Rc::new(MapItemDb::from_data_dir("data/items")));
+        reader.set_property_db(Rc::new(MapPropertyDb::new()));
+
+        let (stash, diagnostics) = read_lenient::<NewStash, _>(&mut reader).unwrap();
+
+        assert!(!diagnostics.is_empty());
+        assert_eq!(
+            bitsy_to_bits(&expected, STASH_VERSION),
+            bitsy_to_bits(&stash, STASH_VERSION)
+        );
+        compare_bitslices(
+            &MyBitVec::from_vec(bytes),
+            &bitsy_to_bits(&stash, STASH_VERSION),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn it_skips_the_rest_of_d2r_lists() {
+        let bytes = corrupt("examples/LaCope2.d2s", 900..905);
+        let mut reader = stash_reader(bytes.clone());
+
+        let (player, diagnostics) = read_lenient::<Player, _>(&mut reader).unwrap();
+
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(886 * 8, diagnostics[0].start);
+        assert!(diagnostics[0]
+            .to_string()
+            .contains("[2].extended_info.quality_id"));
+        compare_bitslices(
+            &MyBitVec::from_vec(bytes),
+            &bitsy_to_bits(&player, player.version),
+        )
+        .unwrap();
+    }
+}
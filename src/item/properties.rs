@@ -3,11 +3,14 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::ops::Deref;
 
+use serde::{Deserialize, Serialize};
+
+use crate::bitsy::result::BitsyResult;
 use crate::bitsy::*;
 use crate::item::reader::ItemReader;
 
-const TERMINATOR_ID: u16 = 0b111111111;
-const PROPERTY_ID_SIZE: usize = 9;
+pub(crate) const TERMINATOR_ID: u16 = 0b111111111;
+pub(crate) const PROPERTY_ID_SIZE: usize = 9;
 
 pub struct PropertyList {
     pub properties: Vec<Property>,
@@ -72,15 +75,62 @@ impl PropertyList {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Property {
     definition: PropertyDef,
     values: Values,
 }
 
 impl Property {
+    pub fn new(definition: PropertyDef, values: Values) -> Self {
+        Property { definition, values }
+    }
+
     pub fn definition(&self) -> &PropertyDef {
         &self.definition
     }
+
+    pub fn values(&self) -> &Values {
+        &self.values
+    }
+
+    // The text of the definition with the values filled in, e.g. "+20 to Strength". Placeholders
+    // are `{:d}` or `{:+d}`, optionally with the index of the value before the colon.
+    pub fn description(&self) -> String {
+        let mut description = String::new();
+        let mut rest = self.definition.text.as_str();
+        let mut next_index = 0;
+        while let Some((start, end)) = rest
+            .find('{')
+            .and_then(|start| Some((start, start + rest[start..].find('}')?)))
+        {
+            description.push_str(&rest[..start]);
+            let placeholder = &rest[start + 1..end];
+            let (index, format) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+            let index = index.parse().unwrap_or(next_index);
+            let value = self.values.get(index).copied().unwrap_or_default();
+            if format.starts_with('+') {
+                description.push_str(&format!("{:+}", value));
+            } else {
+                description.push_str(&value.to_string());
+            }
+            next_index = index + 1;
+            rest = &rest[end + 1..];
+        }
+        description.push_str(rest);
+        description
+    }
+
+    // The value shown first in the text, e.g. 30 for "Fire Resist +30%".
+    pub fn main_value(&self) -> i32 {
+        let text = &self.definition.text;
+        let index = text
+            .find('{')
+            .and_then(|start| text[start + 1..].split(&[':', '}'][..]).next())
+            .and_then(|index| index.parse().ok())
+            .unwrap_or(0);
+        self.values.get(index).copied().unwrap_or_default()
+    }
 }
 
 impl Display for Property {
@@ -97,9 +147,9 @@ impl Display for Property {
 
 const MAX_PROPERTY_VALUES: usize = 4;
 
-type Values = [i32; MAX_PROPERTY_VALUES];
+pub type Values = [i32; MAX_PROPERTY_VALUES];
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PropertyDef {
     id: u16,
     text: String,
@@ -110,7 +160,31 @@ impl PropertyDef {
     pub fn id(&self) -> u16 {
         self.id
     }
-    
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn read_values<R: BitReader>(&self, reader: &mut R) -> BitsyResult<Values> {
+        let mut result = [0i32; MAX_PROPERTY_VALUES];
+        for (value, definition) in result.iter_mut().zip(self.values.iter()) {
+            if definition.size > 0 {
+                let raw: u32 = reader.read_int(definition.size)?;
+                *value = raw as i32 - definition.offset as i32;
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn write_values<W: BitWriter>(&self, values: &Values, writer: &mut W) -> BitsyResult<()> {
+        for (value, definition) in values.iter().zip(self.values.iter()) {
+            if definition.size > 0 {
+                writer.write_int((value + definition.offset as i32) as u32, definition.size)?;
+            }
+        }
+        Ok(())
+    }
+
     fn parse_values(&self, reader: &mut ItemReader) -> Values {
         let mut result = [0i32; MAX_PROPERTY_VALUES];
         for index in 0..MAX_PROPERTY_VALUES {
@@ -144,7 +218,7 @@ impl PropertyDef {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 struct ValueDef {
     size: usize,
     offset: usize,
@@ -189,6 +263,12 @@ impl PropertyDb for MapPropertyDb {
 }
 
 impl MapPropertyDb {
+    pub fn empty() -> Self {
+        MapPropertyDb {
+            properties: HashMap::new(),
+        }
+    }
+
     #[allow(unused_assignments)]
     #[rustfmt::skip]
     pub fn new() -> Self {
@@ -373,7 +453,223 @@ impl MapPropertyDb {
         return db;
     }
 
+    // Save sizes of the unmodded D2R ItemStatCost.txt, used by the D2R save files.
+    #[allow(unused_assignments)]
+    #[rustfmt::skip]
+    pub fn d2r() -> Self {
+        let mut db = MapPropertyDb {
+            properties: HashMap::new(),
+        };
+
+        db.add(PropertyDef::new(0, "{:+d} to Strength", defs![8(32)]));
+        db.add(PropertyDef::new(1, "{:+d} to Energy", defs![7(32)]));
+        db.add(PropertyDef::new(2, "{:+d} to Dexterity", defs![7(32)]));
+        db.add(PropertyDef::new(3, "{:+d} to Vitality", defs![7(32)]));
+        db.add(PropertyDef::new(7, "{:+d} to Life", defs![9(32)]));
+        db.add(PropertyDef::new(9, "{:+d} to Mana", defs![8(32)]));
+        db.add(PropertyDef::new(11, "{:+d} Maximum Stamina", defs![8(32)]));
+        db.add(PropertyDef::new(16, "{:+d}% Enhanced Defense", defs![9]));
+        db.add(PropertyDef::new(17, "{:+d}% Enhanced Damage", defs![9, 9]));
+        db.add(PropertyDef::new(19, "{:+d} to Attack Rating", defs![10]));
+        db.add(PropertyDef::new(20, "{:+d}% Increased Chance of Blocking", defs![6]));
+        db.add(PropertyDef::new(21, "{:+d} to Minimum Damage", defs![6]));
+        db.add(PropertyDef::new(22, "{:+d} to Maximum Damage", defs![7]));
+        db.add(PropertyDef::new(23, "{:+d} to Minimum Damage", defs![6]));
+        db.add(PropertyDef::new(24, "{:+d} to Maximum Damage", defs![7]));
+        db.add(PropertyDef::new(25, "{:+d}% Damage", defs![8]));
+        db.add(PropertyDef::new(26, "Mana Recovery {:+d}", defs![8]));
+        db.add(PropertyDef::new(27, "Regenerate Mana {:d}%", defs![8]));
+        db.add(PropertyDef::new(28, "Heal Stamina Plus {:d}%", defs![8]));
+        db.add(PropertyDef::new(31, "{:+d} Defense", defs![11(10)]));
+        db.add(PropertyDef::new(32, "{:+d} Defense vs. Missile", defs![9]));
+        db.add(PropertyDef::new(33, "{:+d} Defense vs. Melee", defs![8]));
+        db.add(PropertyDef::new(34, "Damage Reduced by {:d}", defs![6]));
+        db.add(PropertyDef::new(35, "Magic Damage Reduced by {:d}", defs![6]));
+        db.add(PropertyDef::new(36, "Damage Reduced by {:+d}%", defs![8]));
+        db.add(PropertyDef::new(37, "Magic Resist {:+d}%", defs![8(50)]));
+        db.add(PropertyDef::new(38, "+{:d}% to Maximum Magic Resist", defs![5]));
+        db.add(PropertyDef::new(39, "Fire Resist {:+d}%", defs![8(50)]));
+        db.add(PropertyDef::new(40, "+{:d}% to max fire resist", defs![5]));
+        db.add(PropertyDef::new(41, "Lightning Resist {:+d}%", defs![8(50)]));
+        db.add(PropertyDef::new(42, "+{:d}% to max lightning resist", defs![5]));
+        db.add(PropertyDef::new(43, "Cold Resist {:+d}%", defs![8(50)]));
+        db.add(PropertyDef::new(44, "+{:d}% to max cold resist", defs![5]));
+        db.add(PropertyDef::new(45, "Poison Resist {:+d}%", defs![8(50)]));
+        db.add(PropertyDef::new(46, "{:+d} to max Poison Resist", defs![5]));
+        db.add(PropertyDef::new(48, "Adds {:d}-{:d} fire damage", defs![8, 9]));
+        db.add(PropertyDef::new(50, "Adds {:d}-{:d} lightning damage", defs![6, 10]));
+        db.add(PropertyDef::new(52, "Adds {:d}-{:d} magic damage", defs![8, 9]));
+        db.add(PropertyDef::new(54, "Adds {:d}-{:d} cold damage", defs![8, 9, 8]));
+        db.add(PropertyDef::new(57, "+({:d}-{:d})/256 poison damage over {:d}/25 s", defs![10, 10, 9]));
+        db.add(PropertyDef::new(60, "{:d}% Life Stolen per Hit", defs![7]));
+        db.add(PropertyDef::new(62, "{:d}% Mana Stolen per Hit", defs![7]));
+        db.add(PropertyDef::new(67, "{:+d}% Walk Velocity", defs![7(30)]));
+        db.add(PropertyDef::new(68, "{:+d}% Attack Rate", defs![7(30)]));
+        db.add(PropertyDef::new(74, "{:+d} Replenish Life", defs![6(30)]));
+        db.add(PropertyDef::new(75, "Increased Maximum Durability {:d}%", defs![7(20)]));
+        db.add(PropertyDef::new(76, "Increase Maximum Life {:d}%", defs![6(10)]));
+        db.add(PropertyDef::new(77, "Increase Maximum Mana {:d}%", defs![6(10)]));
+        db.add(PropertyDef::new(78, "Attacker takes damage of {:d}", defs![7]));
+        db.add(PropertyDef::new(79, "{:d}% Extra Gold from Monsters", defs![9(100)]));
+        db.add(PropertyDef::new(80, "{:d}% Better Chance of Getting Magic Items", defs![8(100)]));
+        db.add(PropertyDef::new(81, "Knockback", defs![7]));
+        db.add(PropertyDef::new(82, "Time Duration {:+d}", defs![9(20)]));
+        db.add(PropertyDef::new(83, "+{1:d} to Class<{0:d}> Skill Levels", defs![3, 3]));
+        db.add(PropertyDef::new(85, "{:d}% to Experience Gained", defs![9(50)]));
+        db.add(PropertyDef::new(86, "{:+d} Life after each Kill", defs![7]));
+        db.add(PropertyDef::new(87, "Reduces all Vendor Prices {:d}%", defs![7]));
+        db.add(PropertyDef::new(89, "{:+d} to Light Radius", defs![4(4)]));
+        db.add(PropertyDef::new(91, "Requirements {:+d}%", defs![8(100)]));
+        db.add(PropertyDef::new(93, "{:+d}% Increased Attack Speed", defs![7(20)]));
+        db.add(PropertyDef::new(96, "{:+d}% Faster Run/Walk", defs![7(20)]));
+        db.add(PropertyDef::new(97, "+{1:d} to Skill<{0:d}>", defs![9, 6]));
+        db.add(PropertyDef::new(99, "{:+d}% Faster Hit Recovery", defs![7(20)]));
+        db.add(PropertyDef::new(102, "{:+d}% Faster Block Rate", defs![7(20)]));
+        db.add(PropertyDef::new(105, "{:+d}% Faster Cast Rate", defs![7(20)]));
+        db.add(PropertyDef::new(107, "+{1:d} to Skill<{0:d}> (Class Only)", defs![9, 3]));
+        db.add(PropertyDef::new(108, "Slain Monsters Rest in Peace", defs![1]));
+        db.add(PropertyDef::new(109, "Shorter Curse Duration {:+d}%", defs![9]));
+        db.add(PropertyDef::new(110, "Poison Length Reduced by {:d}%", defs![8(20)]));
+        db.add(PropertyDef::new(111, "Damage {:+d}", defs![9(20)]));
+        db.add(PropertyDef::new(113, "Hit Blinds Target ({:d})", defs![7]));
+        db.add(PropertyDef::new(114, "{:d}% Damage Taken Goes To Mana", defs![6]));
+        db.add(PropertyDef::new(115, "Ignore Target's Defense", defs![1]));
+        db.add(PropertyDef::new(116, "-{:d}% Target Defense", defs![7]));
+        db.add(PropertyDef::new(117, "Prevent Monster Heal", defs![7]));
+        db.add(PropertyDef::new(118, "Half Freeze Duration", defs![1]));
+        db.add(PropertyDef::new(119, "{:+d}% Bonus to Attack Rating", defs![9(20)]));
+        db.add(PropertyDef::new(120, "{:+d} to Monster Defense Per Hit", defs![7(128)]));
+        db.add(PropertyDef::new(121, "{:+d}% Damage to Demons", defs![9(20)]));
+        db.add(PropertyDef::new(122, "{:+d}% Damage to Undead", defs![9(20)]));
+        db.add(PropertyDef::new(123, "{:+d} to Attack Rating against Demons", defs![10(128)]));
+        db.add(PropertyDef::new(124, "{:+d} to Attack Rating against Undead", defs![10(128)]));
+        db.add(PropertyDef::new(126, "+{1:d} to SkillTree<{0:d}>", defs![3, 3]));
+        db.add(PropertyDef::new(127, "+{:d} to All Skills", defs![3]));
+        db.add(PropertyDef::new(128, "Attacker Takes Lightning Damage of {:+d}", defs![5]));
+        db.add(PropertyDef::new(134, "Freezes Target <{:d}>", defs![5]));
+        db.add(PropertyDef::new(135, "{:d}% Chance of Open Wounds", defs![7]));
+        db.add(PropertyDef::new(136, "{:d}% Chance of Crushing Blow", defs![7]));
+        db.add(PropertyDef::new(137, "{:+d} Kick Damage", defs![7]));
+        db.add(PropertyDef::new(138, "{:+d} to Mana after each Kill", defs![7]));
+        db.add(PropertyDef::new(139, "{:+d} to Life after each Demon Kill", defs![7]));
+        db.add(PropertyDef::new(140, "Extra Blood <{:d}>", defs![7]));
+        db.add(PropertyDef::new(141, "{:d}% Deadly Strike", defs![7]));
+        db.add(PropertyDef::new(142, "Fire Absorb {:d}%", defs![7]));
+        db.add(PropertyDef::new(143, "{:d} Fire Absorb", defs![7]));
+        db.add(PropertyDef::new(144, "Lightning Absorb {:d}%", defs![7]));
+        db.add(PropertyDef::new(145, "{:d} Lightning Absorb", defs![7]));
+        db.add(PropertyDef::new(146, "Magic Absorb {:d}%", defs![7]));
+        db.add(PropertyDef::new(147, "{:d} Magic Absorb", defs![7]));
+        db.add(PropertyDef::new(148, "Cold Absorb {:d}%", defs![7]));
+        db.add(PropertyDef::new(149, "{:d} Cold Absorb", defs![7]));
+        db.add(PropertyDef::new(150, "Slows Target by {:d}%", defs![7]));
+        db.add(PropertyDef::new(151, "Level {1:d} Aura<{0:d}> When Equipped", defs![9, 5]));
+        db.add(PropertyDef::new(152, "Indestructible", defs![1]));
+        db.add(PropertyDef::new(153, "Cannot Be Frozen", defs![1]));
+        db.add(PropertyDef::new(154, "{:+d}% Slower Stamina Drain", defs![7(20)]));
+        db.add(PropertyDef::new(155, "{1:d}% reanimate as: Mob<{0:d}>", defs![10, 7]));
+        db.add(PropertyDef::new(156, "Piercing Attack <{:d}>", defs![7]));
+        db.add(PropertyDef::new(157, "Fires Magic Arrows <{:d}>", defs![7]));
+        db.add(PropertyDef::new(158, "Fires Explosive Arrows or Bolts <{:d}>", defs![7]));
+        db.add(PropertyDef::new(159, "{:+d} to Minimum Throw Damage", defs![6]));
+        db.add(PropertyDef::new(160, "{:+d} to Maximum Throw Damage", defs![7]));
+        db.add(PropertyDef::new(188, "+{1:d} to SkillTab<{0:d}>", defs![16, 3]));
+        db.add(PropertyDef::new(194, "Adds {:d} extra sockets", defs![4]));
+        db.add(PropertyDef::new(195, "{2:d}% Chance to cast Level {0:d} Skill<{1:d}> on attack", defs![6, 10, 7]));
+        db.add(PropertyDef::new(196, "{2:d}% Chance to cast Level {0:d} Skill<{1:d}> when you Kill an Enemy", defs![6, 10, 7]));
+        db.add(PropertyDef::new(197, "{2:d}% Chance to cast Level {0:d} Skill<{1:d}> when you Die", defs![6, 10, 7]));
+        db.add(PropertyDef::new(198, "{2:d}% Chance to cast Level {0:d} Skill<{1:d}> on striking", defs![6, 10, 7]));
+        db.add(PropertyDef::new(199, "{2:d}% Chance to cast Level {0:d} Skill<{1:d}> when you Level-Up", defs![6, 10, 7]));
+        db.add(PropertyDef::new(201, "{2:d}% Chance to cast Level {0:d} Skill<{1:d}> when struck", defs![6, 10, 7]));
+        db.add(PropertyDef::new(204, "Level {:d} Skill<{:d}> ({:d}/{:d} charges)", defs![6, 10, 8, 8]));
+        db.add(PropertyDef::new(214, "{:+d}/8 to Defense (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(215, "{:+d}/16% Enhanced Defense (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(216, "{:+d}/16 to Life (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(217, "{:+d}/16 to Mana (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(218, "{:+d}/16 to Maximum Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(219, "{:+d}/16% Enhanced Maximum Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(220, "{:+d}/16 to Strength (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(221, "{:+d}/16 to Dexterity (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(222, "{:+d}/16 to Energy (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(223, "{:+d}/16 to Vitality (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(224, "{:+d}/2 to Attack Rating (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(225, "{:+d}/8% Bonus to Attack Rating (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(226, "{:+d}/16 to Maximum Cold Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(227, "{:+d}/16 to Maximum Fire Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(228, "{:+d}/16 to Maximum Lightning Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(229, "{:+d}/16 to Maximum Poison Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(230, "Cold Resist {:d}/16 (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(231, "Fire Resist {:d}/16 (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(232, "{:+d}/16 to Lightning Resist (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(233, "{:+d}/16 to Poison Resist (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(234, "{:+d}/16 Absorbs Cold Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(235, "{:+d}/16 Absorbs Fire Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(236, "{:+d}/16 Absorbs Lightning Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(237, "{:+d}/16 Absorbs Poison (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(238, "{:+d}/16 Attacker Takes Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(239, "{:+d}/16 Extra Gold from Monsters (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(240, "{:+d}/16 Better Chance of Getting Magic Items (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(241, "{:+d}/16 Heal Stamina Plus (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(242, "{:+d}/16 Maximum Stamina (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(243, "{:+d}/16% Damage to Demons (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(244, "{:+d}/16% Damage to Undead (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(245, "{:+d}/2 to Attack Rating against Demons (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(246, "{:+d}/2 to Attack Rating against Undead (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(247, "{:+d}/16% Chance of Crushing Blow (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(248, "{:+d}/16% Chance of Open Wounds (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(249, "{:+d}/16 Kick Damage (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(250, "{:+d}/16% Deadly Strike (Based on Character Level)", defs![6]));
+        db.add(PropertyDef::new(252, "Repairs 1 durability in 100/{:d} seconds", defs![6]));
+        db.add(PropertyDef::new(253, "Replenishes Quantity ({:+d})", defs![6]));
+        db.add(PropertyDef::new(254, "Increased Stack Size ({:+d})", defs![8]));
+        db.add(PropertyDef::new(329, "{:+d}% to Fire Skill Damage", defs![9(50)]));
+        db.add(PropertyDef::new(330, "{:+d}% to Lightning Skill Damage", defs![9(50)]));
+        db.add(PropertyDef::new(331, "{:+d}% to Cold Skill Damage", defs![9(50)]));
+        db.add(PropertyDef::new(332, "{:+d}% to Poison Skill Damage", defs![9(50)]));
+        db.add(PropertyDef::new(333, "-{:d}% to Enemy Fire Resistance", defs![8]));
+        db.add(PropertyDef::new(334, "-{:d}% to Enemy Lightning Resistance", defs![8]));
+        db.add(PropertyDef::new(335, "-{:d}% to Enemy Cold Resistance", defs![8]));
+        db.add(PropertyDef::new(336, "-{:d}% to Enemy Poison Resistance", defs![8]));
+        db.add(PropertyDef::new(356, "Quest Item Difficulty <{:d}>", defs![2]));
+
+        db
+    }
+
     fn add(&mut self, def: PropertyDef) {
         self.properties.insert(def.id, def);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_describes_properties() {
+        let property = |text: &str, values| {
+            Property::new(PropertyDef::new(0, text, defs![8]), values).description()
+        };
+
+        assert_eq!("+20 to Strength", property("{:+d} to Strength", [20, 0, 0, 0]));
+        assert_eq!("-5% Fire Resist", property("{:+d}% Fire Resist", [-5, 0, 0, 0]));
+        assert_eq!(
+            "10% Chance to cast Level 3 Skill<52> on attack",
+            property(
+                "{2:d}% Chance to cast Level {0:d} Skill<{1:d}> on attack",
+                [3, 52, 10, 0]
+            )
+        );
+        assert_eq!("Indestructible", property("Indestructible", [1, 0, 0, 0]));
+    }
+
+    #[test]
+    fn it_finds_the_main_value() {
+        let property = |text: &str, values| {
+            Property::new(PropertyDef::new(0, text, defs![8]), values).main_value()
+        };
+
+        assert_eq!(30, property("Fire Resist {:+d}%", [30, 0, 0, 0]));
+        assert_eq!(2, property("+{1:d} to Class<{0:d}> Skill Levels", [1, 2, 0, 0]));
+        assert_eq!(1, property("Indestructible", [1, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::bitsy::{
+    error::BitsyErrorKind,
+    result::BitsyResult,
+    structs::{Bits, BitsyInt},
+    Bitsy,
+};
+
+const RUNEWORDS_CSV: &str = include_str!("../../data/runewords.csv");
+
+// The runeword id is stored as 12 bits followed by 4 bits that are always 5 in the saves we have
+// seen. Ids match the row of the runeword in Runes.txt plus 26, except for Delirium that is saved
+// as 2718. The ids of the table are checked against the runewords of the example saves.
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
+pub struct RunewordId {
+    id: BitsyInt<u16, 12>,
+    unknown: Bits<4>,
+}
+
+impl RunewordId {
+    pub fn value(&self) -> u16 {
+        self.id.value()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunewordInfo {
+    pub id: u16,
+    pub name: String,
+    pub runes: Vec<String>,
+    pub types: Vec<String>,
+}
+
+impl RunewordInfo {
+    pub fn rune_string(&self) -> String {
+        self.runes.join(" ")
+    }
+}
+
+pub trait RunewordDb {
+    fn get_runeword(&self, id: u16) -> Option<RunewordInfo>;
+}
+
+#[derive(Debug)]
+pub struct MapRunewordDb {
+    runewords: HashMap<u16, RunewordInfo>,
+}
+
+impl MapRunewordDb {
+    pub fn new() -> MapRunewordDb {
+        MapRunewordDb {
+            runewords: HashMap::new(),
+        }
+    }
+
+    // The runewords of the game up to D2R 2.4, from data/runewords.csv.
+    pub fn builtin() -> MapRunewordDb {
+        Self::from_reader(RUNEWORDS_CSV.as_bytes(), "data/runewords.csv")
+            .expect("The built-in runeword table is valid")
+    }
+
+    // Reads a Runes.txt-like csv with `id,name,runes,types` columns, where runes and types are
+    // space separated lists.
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> BitsyResult<MapRunewordDb> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|error| {
+            BitsyErrorKind::Io(format!("Could not read {}: {}", path.display(), error)).at_bit(0)
+        })?;
+        Self::from_reader(file, &path.display().to_string())
+    }
+
+    fn from_reader<R: Read>(source: R, name: &str) -> BitsyResult<MapRunewordDb> {
+        let invalid = |row: usize, message: String| {
+            BitsyErrorKind::InvalidData(format!("Row {} of {}: {}", row, name, message)).at_bit(0)
+        };
+        let mut runeword_db = MapRunewordDb::new();
+        let mut reader = csv::Reader::from_reader(source);
+        for (index, result) in reader.records().enumerate() {
+            // Counted from 1 after the header, like in a spreadsheet.
+            let row_number = index + 2;
+            let row = result.map_err(|error| invalid(row_number, error.to_string()))?;
+            let column = |index: usize| {
+                row.get(index)
+                    .ok_or_else(|| invalid(row_number, format!("Missing column {}", index + 1)))
+            };
+            let split = |text: &str| text.split_whitespace().map(|x| x.to_string()).collect();
+            let id = column(0)?;
+            runeword_db.add(RunewordInfo {
+                id: id
+                    .parse()
+                    .map_err(|_| invalid(row_number, format!("Invalid id '{}'", id)))?,
+                name: column(1)?.to_string(),
+                runes: split(column(2)?),
+                types: split(column(3)?),
+            });
+        }
+        Ok(runeword_db)
+    }
+
+    pub fn add(&mut self, runeword: RunewordInfo) {
+        self.runewords.insert(runeword.id, runeword);
+    }
+}
+
+impl Default for MapRunewordDb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunewordDb for MapRunewordDb {
+    fn get_runeword(&self, id: u16) -> Option<RunewordInfo> {
+        self.runewords.get(&id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        bitsy::{bitsy_to_bits, testutils::bits, BitReader, BitVecReader},
+        item::info::MapItemDb,
+        save::SaveFile,
+    };
+
+    use super::*;
+
+    #[test]
+    fn it_reads_ids() {
+        let mut reader = BitVecReader::new(bits("100111000000 1010"));
+
+        let id: RunewordId = reader.read().unwrap();
+
+        assert_eq!(57, id.value());
+        assert_eq!(bits("100111000000 1010"), bitsy_to_bits(&id, 99));
+    }
+
+    #[test]
+    fn it_loads_csv() {
+        let runeword_db = MapRunewordDb::from_csv("data/runewords.csv").unwrap();
+
+        let enigma = runeword_db.get_runeword(59).unwrap();
+        assert_eq!("Enigma", enigma.name);
+        assert_eq!("Jah Ith Ber", enigma.rune_string());
+        assert_eq!(vec!["tors"], enigma.types);
+        assert_eq!("Delirium", runeword_db.get_runeword(2718).unwrap().name);
+        assert!(runeword_db.get_runeword(1).is_none());
+        assert_eq!(85, runeword_db.runewords.len());
+    }
+
+    #[test]
+    fn it_reports_invalid_tables() {
+        let error = MapRunewordDb::from_csv("data/no-runewords.csv").unwrap_err();
+        assert!(matches!(error.kind(), BitsyErrorKind::Io(_)));
+
+        let csv = "id,name,runes,types\n27,Ancient's Pledge,Ral Ort Tal,shie\nx,Black,Thul,club\n";
+        let error = MapRunewordDb::from_reader(csv.as_bytes(), "runewords.csv").unwrap_err();
+        assert_eq!(
+            &BitsyErrorKind::InvalidData("Row 3 of runewords.csv: Invalid id 'x'".to_string()),
+            error.kind()
+        );
+    }
+
+    #[test]
+    fn it_resolves_the_runewords_of_the_examples() {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        let save = SaveFile::read(Path::new("examples/LaCope2.d2s"), item_db)
+            .unwrap()
+            .1;
+
+        let mut runewords: Vec<(u16, String, String)> = save
+            .item_lists()
+            .into_iter()
+            .flat_map(|(_, items)| items.items())
+            .filter_map(|item| {
+                let runeword = item.runeword()?;
+                let runes: Vec<String> = item
+                    .socketed_items()
+                    .iter()
+                    .map(|rune| rune.description().trim_end_matches(" Rune").to_string())
+                    .collect();
+                Some((runeword.id, runeword.name.clone(), runes.join(" ")))
+            })
+            .collect();
+        runewords.sort();
+
+        assert_eq!(
+            vec![
+                (
+                    27,
+                    "Ancient's Pledge".to_string(),
+                    "Ral Ort Tal".to_string()
+                ),
+                (88, "Insight".to_string(), "Ral Tir Tal Sol".to_string()),
+                (101, "Lore".to_string(), "Ort Sol".to_string()),
+                (155, "Spirit".to_string(), "Tal Thul Ort Amn".to_string()),
+                (158, "Stealth".to_string(), "Tal Eth".to_string()),
+            ],
+            runewords
+        );
+    }
+}
@@ -0,0 +1,393 @@
+// A record of the files written by each operation, so that the last one can be undone. Every
+// write adds a line of JSON to the journal file, with the hashes of the file before and after and
+// where `safe_write` backed it up. Lines of the same operation share its id, and undoing restores
+// all its files and drops its lines.
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitsy::{
+        error::{BitsyError, BitsyErrorKind},
+        result::BitsyResult,
+    },
+    safe_write::{replace_file, timestamp, write_file, WriteOptions, Written},
+    save::hash,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub operation: String,
+    pub time: String,
+    // The command and what it did, like "move" and "Moved Ring from a.d2s to b.d2s".
+    pub command: String,
+    pub description: String,
+    pub path: PathBuf,
+    // Hashes of the file, see `save::hash`. There is no hash before for files that were created.
+    pub before: Option<String>,
+    pub after: String,
+    // Where the file as it was before is kept.
+    pub backup: Option<PathBuf>,
+}
+
+// An operation that writes files, which are recorded together.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    pub id: String,
+    pub command: String,
+    pub description: String,
+}
+
+impl Operation {
+    pub fn new(command: &str, description: &str) -> Self {
+        Self {
+            id: format!("{}-{}", timestamp(SystemTime::now()), std::process::id()),
+            command: command.to_string(),
+            description: description.to_string(),
+        }
+    }
+
+    pub fn entry(&self, written: &Written) -> JournalEntry {
+        JournalEntry {
+            operation: self.id.clone(),
+            time: timestamp(SystemTime::now()),
+            command: self.command.clone(),
+            description: self.description.clone(),
+            path: fs::canonicalize(&written.path).unwrap_or_else(|_| written.path.clone()),
+            before: written.before.clone(),
+            after: hash(&written.bytes),
+            backup: written
+                .backup
+                .as_ref()
+                .map(|backup| fs::canonicalize(backup).unwrap_or_else(|_| backup.clone())),
+        }
+    }
+}
+
+// What undoing did to a file.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Undone {
+    Restored(PathBuf),
+    // The operation created the file.
+    Removed(PathBuf),
+}
+
+fn io_error(action: &str, path: &Path, error: std::io::Error) -> BitsyError {
+    BitsyErrorKind::Io(format!(
+        "Could not {} {}: {}",
+        action,
+        path.display(),
+        error
+    ))
+    .at_bit(0)
+}
+
+fn refuse(message: String) -> BitsyError {
+    BitsyErrorKind::InvalidAction(message).at_bit(0)
+}
+
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Every entry, oldest first. A journal that doesn't exist yet is empty.
+    pub fn entries(&self) -> BitsyResult<Vec<JournalEntry>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(io_error("read", &self.path, error)),
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|error| {
+                    BitsyErrorKind::InvalidData(format!(
+                        "Invalid entry {} in {}: {}",
+                        index + 1,
+                        self.path.display(),
+                        error
+                    ))
+                    .at_bit(0)
+                })
+            })
+            .collect()
+    }
+
+    // The backups that undoing needs, which must not be removed.
+    pub fn backups(&self) -> BitsyResult<Vec<PathBuf>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter_map(|entry| entry.backup)
+            .collect())
+    }
+
+    // The options with the backups of the journal kept.
+    pub fn write_options(&self, options: &WriteOptions) -> BitsyResult<WriteOptions> {
+        let mut options = options.clone();
+        options.kept_backups.extend(self.backups()?);
+        Ok(options)
+    }
+
+    pub fn record(&self, entry: &JournalEntry) -> BitsyResult<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|error| io_error("create", dir, error))?;
+        }
+        let line = serde_json::to_string(entry).map_err(|error| {
+            BitsyErrorKind::InvalidData(format!("Could not record {}: {}", entry.operation, error))
+                .at_bit(0)
+        })?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|error| io_error("write", &self.path, error))
+    }
+
+    // The entries of the last operation.
+    pub fn last_operation(&self) -> BitsyResult<Vec<JournalEntry>> {
+        let entries = self.entries()?;
+        let last = match entries.last() {
+            Some(last) => last.operation.clone(),
+            None => return Ok(Vec::new()),
+        };
+        Ok(entries
+            .into_iter()
+            .filter(|entry| entry.operation == last)
+            .collect())
+    }
+
+    // Checks that the files of the last operation can be put back as they were: they must not
+    // have changed since, unless `force` is given, and their backups must still be there.
+    // Returns the entries and the content to restore, None for files to remove.
+    fn prepare_undo(&self, force: bool) -> BitsyResult<Vec<(JournalEntry, Option<Vec<u8>>)>> {
+        let entries = self.last_operation()?;
+        let mut prepared = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            // Only the last write of a file is what it should be now.
+            let written_again = entries[index + 1..]
+                .iter()
+                .any(|later| later.path == entry.path);
+            let current = fs::read(&entry.path).ok().map(|bytes| hash(&bytes));
+            if !force && !written_again && current.as_deref() != Some(entry.after.as_str()) {
+                return Err(refuse(format!(
+                    "{} changed since it was written by {}",
+                    entry.path.display(),
+                    entry.command
+                )));
+            }
+            let original = match (&entry.before, &entry.backup) {
+                (None, _) => None,
+                (Some(_), None) => {
+                    return Err(refuse(format!(
+                        "{} was written without a backup",
+                        entry.path.display()
+                    )))
+                }
+                (Some(before), Some(backup)) => {
+                    let original =
+                        fs::read(backup).map_err(|error| io_error("read", backup, error))?;
+                    if &hash(&original) != before {
+                        return Err(refuse(format!(
+                            "The backup {} is not the file that was replaced",
+                            backup.display()
+                        )));
+                    }
+                    Some(original)
+                }
+            };
+            prepared.push((entry.clone(), original));
+        }
+        Ok(prepared)
+    }
+
+    // What undoing would do, without doing it.
+    pub fn plan_undo(&self, force: bool) -> BitsyResult<Vec<JournalEntry>> {
+        Ok(self
+            .prepare_undo(force)?
+            .into_iter()
+            .map(|(entry, _)| entry)
+            .collect())
+    }
+
+    // Puts the files of the last operation back as they were and removes it from the journal.
+    // The files are backed up again before, so undoing can be undone by hand.
+    pub fn undo(&self, force: bool, options: &WriteOptions) -> BitsyResult<Vec<Undone>> {
+        let prepared = self.prepare_undo(force)?;
+        let options = self.write_options(options)?;
+        let operation = match prepared.first() {
+            Some((entry, _)) => entry.operation.clone(),
+            None => return Ok(Vec::new()),
+        };
+        // When an operation wrote a file twice, the first backup is the file before it.
+        let mut undone = Vec::new();
+        for (entry, original) in prepared.into_iter().rev() {
+            match original {
+                Some(original) => {
+                    write_file(&entry.path, original, &options)?;
+                    undone.push(Undone::Restored(entry.path));
+                }
+                None => {
+                    if entry.path.exists() {
+                        fs::remove_file(&entry.path)
+                            .map_err(|error| io_error("remove", &entry.path, error))?;
+                    }
+                    undone.push(Undone::Removed(entry.path));
+                }
+            }
+        }
+        undone.reverse();
+
+        let kept: Vec<String> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.operation != operation)
+            .map(|entry| serde_json::to_string(&entry))
+            .collect::<serde_json::Result<_>>()
+            .map_err(|error| {
+                BitsyErrorKind::InvalidData(format!("Could not write the journal: {}", error))
+                    .at_bit(0)
+            })?;
+        let text: String = kept.iter().map(|line| format!("{}\n", line)).collect();
+        replace_file(&self.path, text.as_bytes())?;
+        Ok(undone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        item::info::MapItemDb,
+        safe_write::write_save,
+        save::SaveFile,
+        transfer::{move_item, Destination, PanelSizes},
+    };
+
+    use super::*;
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("d2items-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn it_undoes_the_last_operation() {
+        let dir = temporary_dir("journal");
+        let journal = Journal::new(dir.join("journal.jsonl"));
+        let options = WriteOptions::default();
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        let (source, destination) = (dir.join("a.sss"), dir.join("b.sss"));
+        fs::copy("small_stash.sss", &source).unwrap();
+        let (kind, mut a) = SaveFile::read(&source, item_db.clone()).unwrap();
+        let mut b = SaveFile::read(Path::new("small_stash.sss"), item_db.clone())
+            .unwrap()
+            .1;
+        let sizes = PanelSizes::for_version(a.version());
+        move_item(&mut a, 3, 0, Some(&mut b), Destination::Page(None), &sizes)
+            .unwrap()
+            .unwrap();
+
+        let operation = Operation::new("move", "Moved an item from a.sss to b.sss");
+        for (path, save) in [(&source, &a), (&destination, &b)] {
+            let written = write_save(path, kind, save, item_db.clone(), &options).unwrap();
+            journal.record(&operation.entry(&written)).unwrap();
+        }
+
+        let entries = journal.last_operation().unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!(None, entries[1].before);
+        assert_eq!(hash(&fs::read(&destination).unwrap()), entries[1].after);
+        assert_eq!(entries, journal.plan_undo(false).unwrap());
+        assert_eq!(
+            vec![
+                Undone::Restored(fs::canonicalize(&source).unwrap()),
+                Undone::Removed(fs::canonicalize(&destination).unwrap()),
+            ],
+            journal.undo(false, &options).unwrap()
+        );
+        assert_eq!(
+            fs::read("small_stash.sss").unwrap(),
+            fs::read(&source).unwrap()
+        );
+        assert!(!destination.exists());
+        assert!(journal.entries().unwrap().is_empty());
+        assert!(journal.undo(false, &options).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn it_refuses_to_undo_files_changed_since() {
+        let dir = temporary_dir("journal-changed");
+        let journal = Journal::new(dir.join("journal.jsonl"));
+        let options = WriteOptions::default();
+        let path = dir.join("a.sss");
+        fs::write(&path, b"before").unwrap();
+        let written = write_file(&path, b"after".to_vec(), &options).unwrap();
+        journal
+            .record(&Operation::new("sort", "Sorted a.sss").entry(&written))
+            .unwrap();
+        fs::write(&path, b"changed").unwrap();
+
+        assert!(matches!(
+            journal.undo(false, &options).unwrap_err().kind(),
+            BitsyErrorKind::InvalidAction(_)
+        ));
+        assert_eq!(b"changed".to_vec(), fs::read(&path).unwrap());
+
+        journal.undo(true, &options).unwrap();
+        assert_eq!(b"before".to_vec(), fs::read(&path).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn it_undoes_more_operations_than_backups_kept() {
+        let dir = temporary_dir("journal-rotated");
+        let journal = Journal::new(dir.join("journal.jsonl"));
+        let options = WriteOptions {
+            backups: 2,
+            ..WriteOptions::default()
+        };
+        let path = dir.join("a.sss");
+        fs::write(&path, b"0").unwrap();
+        for count in 1..=4 {
+            // Operations started in the same millisecond would share their id.
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            let written = write_file(
+                &path,
+                count.to_string().into_bytes(),
+                &journal.write_options(&options).unwrap(),
+            )
+            .unwrap();
+            journal
+                .record(&Operation::new("sort", "Sorted a.sss").entry(&written))
+                .unwrap();
+        }
+
+        for count in (0..4).rev() {
+            journal.undo(false, &options).unwrap();
+            assert_eq!(count.to_string().into_bytes(), fs::read(&path).unwrap());
+        }
+        assert!(journal.entries().unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,15 @@
+use serde::{de::Error, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::{
     bitsy::{
         context,
         error::{BitsyError, BitsyErrorExt, BitsyErrorKind},
-        macros::{bitsy_cond_read, bitsy_read, bitsy_write},
         result::BitsyResult,
         structs::{BitsyBytes, BitsyChars, BitsyInt},
-        BitReader, BitSized, BitWriter, Bitsy, MyBitVec,
+        BitReader, BitSized, BitVecWriter, BitWriter, Bitsy,
     },
     constants::{IRON_GOLEM_HEADER, ITEM_HEADER, MERC_HEADER},
-    item::{ItemList, NewItem},
+    item::{ear::class_name, ItemList, NewItem, D2R_VERSION},
 };
 
 const ATTRIBUTES_HEADER: [u8; 2] = [0x67, 0x66];
@@ -40,6 +41,26 @@ pub struct Attributes {
     values: Vec<(AttributeId, u32)>,
 }
 
+impl Attributes {
+    // The name and value of every attribute, in file order.
+    pub fn values(&self) -> Vec<(&'static str, u32)> {
+        self.values
+            .iter()
+            .filter_map(|(attribute_id, value)| {
+                let name = ATTRIBUTE_NAMES.get(attribute_id.value() as usize)?;
+                Some((*name, *value))
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.values()
+            .into_iter()
+            .find(|(attribute, _)| *attribute == name)
+            .map(|(_, value)| value)
+    }
+}
+
 impl std::fmt::Debug for Attributes {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut debug = f.debug_struct("Attributes");
@@ -50,6 +71,61 @@ impl std::fmt::Debug for Attributes {
     }
 }
 
+// Serialized as a map from attribute names to values, in file order.
+impl Serialize for Attributes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        for (attribute_id, value) in &self.values {
+            let name = ATTRIBUTE_NAMES
+                .get(attribute_id.value() as usize)
+                .ok_or_else(|| serde::ser::Error::custom("Invalid attribute id"))?;
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Attributes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AttributesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for AttributesVisitor {
+            type Value = Attributes;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a map of attribute names to values")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut values = Vec::new();
+                while let Some((name, value)) = map.next_entry::<String, u32>()? {
+                    let id = ATTRIBUTE_NAMES
+                        .iter()
+                        .position(|n| *n == name)
+                        .ok_or_else(|| A::Error::custom(format!("Unknown attribute '{}'", name)))?;
+                    let size = ATTRIBUTE_SIZES[id];
+                    if size < 32 && value >> size != 0 {
+                        return Err(A::Error::custom(format!(
+                            "{} {} doesn't fit in {} bits",
+                            name, value, size
+                        )));
+                    }
+                    values.push((
+                        AttributeId::new(id as u16).map_err(A::Error::custom)?,
+                        value,
+                    ));
+                }
+                Ok(Attributes { values })
+            }
+        }
+
+        deserializer.deserialize_map(AttributesVisitor)
+    }
+}
+
 impl Bitsy for Attributes {
     fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
         let mut values = Vec::new();
@@ -109,9 +185,10 @@ impl Bitsy for Attributes {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
 pub struct Player {
     header: BitsyBytes<4>,
+    #[bitsy(context = "context::VERSION")]
     pub version: u32,
     file_size: u32,
     checksum: u32,
@@ -143,143 +220,15 @@ pub struct Player {
     golem_info: IronGolem,
 }
 
-impl Bitsy for Player {
-    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
-        bitsy_read!(reader, header, version);
-        let _version_guard = reader.set_context(&context::VERSION, version);
-
-        bitsy_read!(
-            reader,
-            file_size,
-            checksum,
-            active_weapon,
-            old_name,
-            status,
-            progression,
-            unknown1,
-            class,
-            unknown2,
-            level,
-            created_at,
-            last_played_at,
-            unknown3,
-            skill_stuff,
-            appearance,
-            merc_stuff,
-            menu_appearance,
-            new_name,
-            unknown4,
-            quests,
-            waypoints,
-            npcs,
-            attributes,
-            skills,
-            items,
-            corpse_info,
-            mercenary_items,
-            golem_info,
-        );
-        Ok(Self {
-            header,
-            version,
-            file_size,
-            checksum,
-            active_weapon,
-            old_name,
-            status,
-            progression,
-            unknown1,
-            class,
-            unknown2,
-            level,
-            created_at,
-            last_played_at,
-            unknown3,
-            skill_stuff,
-            appearance,
-            merc_stuff,
-            menu_appearance,
-            new_name,
-            unknown4,
-            quests,
-            waypoints,
-            npcs,
-            attributes,
-            skills,
-            items,
-            corpse_info,
-            mercenary_items,
-            golem_info,
-        })
-    }
-
-    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
-        bitsy_write!(
-            writer,
-            &self.header,
-            &self.version,
-            &self.file_size,
-            &self.checksum,
-            &self.active_weapon,
-            &self.old_name,
-            &self.status,
-            &self.progression,
-            &self.unknown1,
-            &self.class,
-            &self.unknown2,
-            &self.level,
-            &self.created_at,
-            &self.last_played_at,
-            &self.unknown3,
-            &self.skill_stuff,
-            &self.appearance,
-            &self.merc_stuff,
-            &self.menu_appearance,
-            &self.new_name,
-            &self.unknown4,
-            &self.quests,
-            &self.waypoints,
-            &self.npcs,
-            &self.attributes,
-            &self.skills,
-            &self.items,
-            &self.corpse_info,
-            &self.mercenary_items,
-            &self.golem_info,
-        );
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
+#[bitsy(magic = "ITEM_HEADER")]
 pub struct Corpse {
     is_dead: u16,
+    #[bitsy(if = "is_dead != 0")]
     info: Option<CorpseInfo>,
 }
 
-impl Bitsy for Corpse {
-    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
-        let header: [u8; 2] = reader.read()?;
-        if header != ITEM_HEADER {
-            return Err(BitsyErrorKind::InvalidData(format!(
-                "Invalid corpse header {:?} (expected {:?})",
-                header, ITEM_HEADER
-            ))
-            .at_bit(reader.index() - header.bit_size()));
-        }
-        bitsy_read!(reader, is_dead: u16);
-        bitsy_cond_read!(reader, is_dead != 0, info);
-        Ok(Self { is_dead, info })
-    }
-
-    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
-        writer.write(&ITEM_HEADER)?;
-        bitsy_write!(writer, &self.is_dead, &self.info);
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
 pub struct CorpseInfo {
     unknown: BitsyBytes<4>,
     x: u32,
@@ -287,80 +236,122 @@ pub struct CorpseInfo {
     items: ItemList,
 }
 
-impl Bitsy for CorpseInfo {
-    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
-        bitsy_read!(reader, unknown, x, y, items);
-        Ok(Self {
-            unknown,
-            x,
-            y,
-            items,
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
+#[bitsy(magic = "MERC_HEADER")]
+pub struct MercenaryItems {
+    #[bitsy(if = "reader.peek::<[u8; 2]>()? == ITEM_HEADER")]
+    items: Option<ItemList>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
+#[bitsy(magic = "IRON_GOLEM_HEADER")]
+pub struct IronGolem {
+    has_iron_golem: u8,
+    #[bitsy(if = "has_iron_golem != 0")]
+    golem_info: Option<NewItem>,
+}
+
+// The header stores the size of the file and a checksum of it, taken with the checksum as 0.
+const FILE_SIZE_OFFSET: usize = 8;
+const CHECKSUM_OFFSET: usize = 12;
+
+pub fn checksum(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .enumerate()
+        .fold(0u32, |checksum, (index, byte)| {
+            let byte = if (CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4).contains(&index) {
+                0
+            } else {
+                *byte
+            };
+            checksum.rotate_left(1).wrapping_add(u32::from(byte))
         })
+}
+
+impl Player {
+    pub fn name(&self) -> String {
+        let name = if self.version < D2R_VERSION {
+            &self.old_name
+        } else {
+            &self.new_name
+        };
+        name.as_string().trim_end_matches('\0').to_string()
     }
 
-    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
-        bitsy_write!(writer, &self.unknown, &self.x, &self.y, &self.items);
-        Ok(())
+    pub fn class(&self) -> u8 {
+        self.class
     }
-}
 
-#[derive(Debug)]
-pub struct MercenaryItems {
-    items: Option<ItemList>,
-}
+    pub fn class_name(&self) -> &'static str {
+        class_name(self.class)
+    }
 
-impl Bitsy for MercenaryItems {
-    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
-        let header: [u8; 2] = reader.read()?;
-        if header != MERC_HEADER {
-            return Err(BitsyErrorKind::InvalidData(format!(
-                "Invalid merc header {:?} (expected {:?})",
-                header, MERC_HEADER
-            ))
-            .at_bit(reader.index() - header.bit_size()));
-        }
-        let peek = reader.peek::<[u8; 2]>()?;
-        bitsy_cond_read!(reader, peek == ITEM_HEADER, items);
-        Ok(Self { items })
+    pub fn level(&self) -> u8 {
+        self.level
     }
 
-    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
-        writer.write(&MERC_HEADER)?;
-        bitsy_write!(writer, &self.items);
-        Ok(())
+    // The quest state: "Woo!", 6 more header bytes and 96 bytes for each difficulty.
+    pub fn quests(&self) -> &[u8] {
+        &self.quests[..]
     }
-}
 
-#[derive(Debug)]
-pub struct IronGolem {
-    has_iron_golem: u8,
-    golem_info: Option<NewItem>,
-}
+    // The waypoints: "WS", 6 more header bytes and 24 bytes for each difficulty.
+    pub fn waypoints(&self) -> &[u8] {
+        &self.waypoints[..]
+    }
 
-impl Bitsy for IronGolem {
-    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
-        let header: [u8; 2] = reader.read()?;
-        if header != IRON_GOLEM_HEADER {
-            return Err(BitsyErrorKind::InvalidData(format!(
-                "Invalid iron golem header {:?} (expected {:?})",
-                header, IRON_GOLEM_HEADER
-            ))
-            .at_bit(reader.index() - header.bit_size()));
-        }
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
 
-        bitsy_read!(reader, has_iron_golem);
-        bitsy_cond_read!(reader, has_iron_golem != 0, golem_info);
+    // The items on the character, in its inventory, belt, cube and stash.
+    pub fn items(&self) -> &ItemList {
+        &self.items
+    }
 
-        Ok(Self {
-            has_iron_golem,
-            golem_info,
-        })
+    pub fn items_mut(&mut self) -> &mut ItemList {
+        &mut self.items
     }
 
-    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
-        writer.write(&IRON_GOLEM_HEADER)?;
-        bitsy_write!(writer, &self.has_iron_golem, &self.golem_info);
-        Ok(())
+    pub fn corpse_items(&self) -> Option<&ItemList> {
+        self.corpse_info.info.as_ref().map(|info| &info.items)
+    }
+
+    pub fn mercenary_items(&self) -> Option<&ItemList> {
+        self.mercenary_items.items.as_ref()
+    }
+
+    // The items of the character, corpse and mercenary, borrowed together.
+    pub fn item_lists_mut(
+        &mut self,
+    ) -> (&mut ItemList, Option<&mut ItemList>, Option<&mut ItemList>) {
+        (
+            &mut self.items,
+            self.corpse_info.info.as_mut().map(|info| &mut info.items),
+            self.mercenary_items.items.as_mut(),
+        )
+    }
+
+    pub fn has_valid_checksum(&self, bytes: &[u8]) -> bool {
+        checksum(bytes) == self.checksum
+    }
+
+    pub fn has_valid_file_size(&self, bytes: &[u8]) -> bool {
+        self.file_size as usize == bytes.len()
+    }
+
+    // Writes the player with the file size and checksum updated, as the game rejects files where
+    // they don't match.
+    pub fn to_bytes(&self) -> BitsyResult<Vec<u8>> {
+        let mut writer = BitVecWriter::new(self.version);
+        writer.write(self)?;
+        let mut bytes = writer.into_bits().into_vec();
+        let file_size = bytes.len() as u32;
+        bytes[FILE_SIZE_OFFSET..FILE_SIZE_OFFSET + 4].copy_from_slice(&file_size.to_le_bytes());
+        let checksum = checksum(&bytes);
+        bytes[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4].copy_from_slice(&checksum.to_le_bytes());
+        Ok(bytes)
     }
 }
 
@@ -369,7 +360,9 @@ mod tests {
     use std::rc::Rc;
 
     use crate::{
-        bitsy::{bitsy_to_bits, compare_bitslices, BitVecReader, BitVecWriter, HuffmanChars},
+        bitsy::{
+            bitsy_to_bits, compare_bitslices, BitVecReader, BitVecWriter, HuffmanChars, MyBitVec,
+        },
         item::info::{ItemDb, MapItemDb},
     };
 
@@ -399,8 +392,6 @@ mod tests {
     fn it_works() {
         let item_db: Rc<dyn ItemDb> = Rc::new(MapItemDb::from_data_dir("data/items"));
         let bytes = std::fs::read("examples/LaCope2.d2s").unwrap();
-
-        std::fs::write("examples/HoradricCubeAndNextItem.bin", &bytes[1020..1088]).unwrap();
         let bits = MyBitVec::from_vec(bytes);
 
         let mut reader = BitVecReader::with_item_db(bits.clone(), item_db);
@@ -423,4 +414,64 @@ mod tests {
 
         compare_bitslices(&bits, &new_bits).unwrap();
     }
+
+    #[test]
+    fn it_roundtrips_through_json() {
+        let item_db: Rc<dyn ItemDb> = Rc::new(MapItemDb::from_data_dir("data/items"));
+        for path in ["examples/LaCope2.d2s", "examples/PlasticSurgeon.d2s"] {
+            let bits = MyBitVec::from_vec(std::fs::read(path).unwrap());
+            let mut reader = BitVecReader::with_item_db(bits.clone(), item_db.clone());
+            let player: Player = reader.read().unwrap();
+
+            let json = serde_json::to_string(&player).unwrap();
+            let imported: Player = serde_json::from_str(&json).unwrap();
+
+            compare_bitslices(&bits, &bitsy_to_bits(&imported, imported.version)).unwrap();
+        }
+    }
+
+    #[test]
+    fn it_exports_readable_json() {
+        let bits = MyBitVec::from_vec(std::fs::read("examples/LaCope2.d2s").unwrap());
+        let player: Player = BitVecReader::new(bits).read().unwrap();
+
+        let json = serde_json::to_value(&player).unwrap();
+
+        assert_eq!("55aa55aa", json["header"]);
+        assert!(json["attributes"]["Level"].is_u64());
+        let item = &json["items"]["items"][0];
+        assert!(item["x"].is_u64());
+        assert!(item["unknown1"].as_str().unwrap().len() == 3);
+
+        let mut edited = json.clone();
+        edited["attributes"]["Level"] = serde_json::json!(200);
+        assert!(serde_json::from_value::<Player>(edited).is_err());
+    }
+
+    #[test]
+    fn it_updates_the_checksum() {
+        let item_db: Rc<dyn ItemDb> = Rc::new(MapItemDb::from_data_dir("data/items"));
+        for path in ["examples/LaCope2.d2s", "examples/StartingD2R.d2s"] {
+            let bytes = std::fs::read(path).unwrap();
+            let mut reader =
+                BitVecReader::with_item_db(MyBitVec::from_vec(bytes.clone()), item_db.clone());
+            let mut player: Player = reader.read().unwrap();
+            assert!(player.has_valid_checksum(&bytes));
+            assert!(player.has_valid_file_size(&bytes));
+            assert_eq!(bytes, player.to_bytes().unwrap());
+
+            player.file_size = 0;
+            player.checksum = 0;
+            assert!(!player.has_valid_checksum(&bytes));
+            assert!(!player.has_valid_file_size(&bytes));
+            assert_eq!(bytes, player.to_bytes().unwrap());
+        }
+    }
+
+    #[test]
+    fn it_reads_the_name() {
+        let bits = MyBitVec::from_vec(std::fs::read("examples/LaCope2.d2s").unwrap());
+        let player: Player = BitVecReader::new(bits).read().unwrap();
+        assert_eq!("LaCopperfield", player.name());
+    }
 }
@@ -4,6 +4,7 @@ use crate::bitsy::{
     context,
     error::{BitsyError, BitsyErrorKind},
     macros::{bitsy_read, bitsy_write},
+    parse_int,
     result::BitsyResult,
     structs::{Bits, BitsyInt, BitsyOption},
     BitReader, BitSized, BitWriter, Bitsy, MyBitVec, OldBitReader, OldBitWriter,
@@ -248,7 +249,7 @@ impl Display for UniqueQuality {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum QualityId {
     Normal,
     Low,
@@ -295,9 +296,9 @@ impl Bitsy for QualityId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum ItemQuality {
-    Low(Bits<4>),
+    Low(Bits<3>),
     Normal,
     Superior(Bits<3>),
     Magic {
@@ -333,6 +334,16 @@ pub enum ItemQuality {
 }
 
 impl ItemQuality {
+    // The row of the item in SetItems.txt or UniqueItems.txt.
+    pub fn set_or_unique_id(&self) -> Option<u16> {
+        match self {
+            ItemQuality::Set { id } | ItemQuality::Unique { id } => {
+                parse_int(id.as_bitslice()).ok().map(|id| id as u16)
+            }
+            _ => None,
+        }
+    }
+
     pub fn get_quality_id(&self) -> QualityId {
         match self {
             ItemQuality::Low(_) => QualityId::Low,
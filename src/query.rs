@@ -0,0 +1,521 @@
+// A query language for finding items, e.g.
+//
+//     quality:unique name~"shako" eth:no sockets>=2 prop:"Fire Resist">=20
+//
+// A query is a list of terms separated by spaces, which all have to match. A term is a field, an
+// operator and a value, and a `-` in front of it negates it. Values with spaces are quoted. A word
+// on its own looks for items whose name or code contain it.
+//
+// - `name`, `code`, `guid`: `:` for the whole text and `~` for a part of it, ignoring case. Names
+//   are those of the base, the runeword or the personalization. There is no table of set and
+//   unique names, so those items are found by their `id` or base, like a Harlequin Crest with
+//   `name~shako`.
+// - `quality`: low, normal, superior, magic, set, rare, unique or crafted.
+// - `container`: inventory, cube, stash, equipped, belt or socket.
+// - `eth`, `runeword`, `identified`, `personalized`: yes or no.
+// - `sockets`, `ilvl`, `defense`, `quantity`, `id` (of the set or unique item): numbers, compared
+//   with `:` or `=`, `!=`, `<`, `<=`, `>` and `>=`.
+// - `prop`: a property whose text contains the value, like `prop:"Faster Cast Rate"` or
+//   `prop:fcr`, followed by an optional comparison of its value, like `prop:fcr>=10`. Properties
+//   after one the game version's table doesn't know aren't decoded, so they never match.
+use crate::{
+    bitsy::{error::BitsyErrorKind, result::BitsyResult},
+    item::{Container, NewItem},
+    quality::QualityId,
+};
+
+// Short names for common properties, for `prop`.
+const PROPERTY_ALIASES: [(&str, &str); 8] = [
+    ("fcr", "faster cast rate"),
+    ("fhr", "faster hit recovery"),
+    ("fbr", "faster block rate"),
+    ("frw", "faster run/walk"),
+    ("ias", "increased attack speed"),
+    ("mf", "better chance of getting magic items"),
+    ("ed", "enhanced damage"),
+    ("allskills", "to all skills"),
+];
+
+const QUALITIES: [(&str, QualityId); 9] = [
+    ("low", QualityId::Low),
+    ("normal", QualityId::Normal),
+    ("superior", QualityId::High),
+    ("high", QualityId::High),
+    ("magic", QualityId::Magic),
+    ("set", QualityId::Set),
+    ("rare", QualityId::Rare),
+    ("unique", QualityId::Unique),
+    ("crafted", QualityId::Crafted),
+];
+
+const CONTAINERS: [Container; 6] = [
+    Container::Inventory,
+    Container::Cube,
+    Container::Stash,
+    Container::Equipped,
+    Container::Belt,
+    Container::Socket,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    // `:`
+    Is,
+    // `~`
+    Contains,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Operator {
+    // Longest first, so that `>=` isn't read as `>`.
+    const ALL: [(&'static str, Operator); 8] = [
+        ("!=", Operator::NotEqual),
+        ("<=", Operator::LessOrEqual),
+        (">=", Operator::GreaterOrEqual),
+        (":", Operator::Is),
+        ("~", Operator::Contains),
+        ("=", Operator::Equal),
+        ("<", Operator::Less),
+        (">", Operator::Greater),
+    ];
+
+    fn compare(self, value: i64, expected: i64) -> bool {
+        match self {
+            Operator::Is | Operator::Equal => value == expected,
+            Operator::NotEqual => value != expected,
+            Operator::Less => value < expected,
+            Operator::LessOrEqual => value <= expected,
+            Operator::Greater => value > expected,
+            Operator::GreaterOrEqual => value >= expected,
+            Operator::Contains => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Name,
+    Code,
+    Guid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberField {
+    Sockets,
+    ItemLevel,
+    Defense,
+    Quantity,
+    Id,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagField {
+    Ethereal,
+    Runeword,
+    Identified,
+    Personalized,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    // A word on its own.
+    Word(String),
+    Text(TextField, bool, String),
+    Number(NumberField, Operator, i64),
+    Flag(FlagField, bool),
+    Quality(QualityId),
+    Container(Container),
+    Property(String, Option<(Operator, i64)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+fn invalid<T>(message: String) -> BitsyResult<T> {
+    Err(BitsyErrorKind::InvalidData(message).at_bit(0))
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        let found = self.rest().starts_with(prefix);
+        if found {
+            self.position += prefix.len();
+        }
+        found
+    }
+
+    fn operator(&mut self) -> Option<Operator> {
+        let (symbol, operator) = Operator::ALL
+            .iter()
+            .find(|(symbol, _)| self.rest().starts_with(symbol))?;
+        self.position += symbol.len();
+        Some(*operator)
+    }
+
+    // A quoted value, or one that ends at a space or at an operator.
+    fn value(&mut self) -> BitsyResult<String> {
+        let start = self.position;
+        if self.eat("\"") {
+            let end = match self.rest().find('"') {
+                Some(end) => end,
+                None => return invalid(format!("Unclosed quote at character {}", start + 1)),
+            };
+            let value = self.rest()[..end].to_string();
+            self.position += end + 1;
+            return Ok(value);
+        }
+        let end = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || "!<>=:~\"".contains(c))
+            .unwrap_or_else(|| self.rest().len());
+        if end == 0 {
+            return invalid(format!("Missing value at character {}", start + 1));
+        }
+        self.position += end;
+        Ok(self.text[start..self.position].to_string())
+    }
+
+    fn number(&mut self) -> BitsyResult<i64> {
+        let start = self.position;
+        let value = self.value()?;
+        value.parse().or_else(|_| {
+            invalid(format!(
+                "Expected a number at character {}, got '{}'",
+                start + 1,
+                value
+            ))
+        })
+    }
+
+    fn term(&mut self) -> BitsyResult<Term> {
+        let negated = self.eat("-");
+        let start = self.position;
+        let word = self.value()?;
+        let operator = match self.operator() {
+            Some(operator) => operator,
+            None => {
+                return Ok(Term {
+                    negated,
+                    condition: Condition::Word(word.to_lowercase()),
+                })
+            }
+        };
+        let field = word.to_lowercase();
+        let condition = match field.as_str() {
+            "name" | "code" | "guid" => {
+                let field = match field.as_str() {
+                    "name" => TextField::Name,
+                    "code" => TextField::Code,
+                    _ => TextField::Guid,
+                };
+                let partial = match operator {
+                    Operator::Is | Operator::Equal => false,
+                    Operator::Contains => true,
+                    _ => return invalid(format!("{} can only be used with : or ~", word)),
+                };
+                Condition::Text(field, partial, self.value()?.to_lowercase())
+            }
+            "sockets" | "ilvl" | "defense" | "quantity" | "id" => {
+                let field = match field.as_str() {
+                    "sockets" => NumberField::Sockets,
+                    "ilvl" => NumberField::ItemLevel,
+                    "defense" => NumberField::Defense,
+                    "quantity" => NumberField::Quantity,
+                    _ => NumberField::Id,
+                };
+                if operator == Operator::Contains {
+                    return invalid(format!("{} can't be used with ~", word));
+                }
+                Condition::Number(field, operator, self.number()?)
+            }
+            "eth" | "runeword" | "identified" | "personalized" => {
+                let field = match field.as_str() {
+                    "eth" => FlagField::Ethereal,
+                    "runeword" => FlagField::Runeword,
+                    "identified" => FlagField::Identified,
+                    _ => FlagField::Personalized,
+                };
+                let value = self.value()?;
+                let value = match value.to_lowercase().as_str() {
+                    "yes" | "true" => true,
+                    "no" | "false" => false,
+                    _ => return invalid(format!("{} is yes or no, not '{}'", word, value)),
+                };
+                Condition::Flag(field, value)
+            }
+            "quality" => {
+                let value = self.value()?;
+                match QUALITIES
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&value))
+                {
+                    Some((_, quality)) => Condition::Quality(*quality),
+                    None => return invalid(format!("Unknown quality '{}'", value)),
+                }
+            }
+            "container" => {
+                let value = self.value()?;
+                match CONTAINERS
+                    .iter()
+                    .find(|container| container.name().eq_ignore_ascii_case(&value))
+                {
+                    Some(container) => Condition::Container(*container),
+                    None => return invalid(format!("Unknown container '{}'", value)),
+                }
+            }
+            "prop" => {
+                let name = self.value()?.to_lowercase();
+                let name = PROPERTY_ALIASES
+                    .iter()
+                    .find(|(alias, _)| *alias == name)
+                    .map_or(name, |(_, text)| text.to_string());
+                let comparison = match self.operator() {
+                    Some(Operator::Is) | Some(Operator::Contains) => {
+                        return invalid(format!(
+                            "Expected a comparison after prop at character {}",
+                            self.position
+                        ))
+                    }
+                    Some(operator) => Some((operator, self.number()?)),
+                    None => None,
+                };
+                Condition::Property(name, comparison)
+            }
+            _ => {
+                return invalid(format!(
+                    "Unknown field '{}' at character {}",
+                    word,
+                    start + 1
+                ))
+            }
+        };
+        Ok(Term { negated, condition })
+    }
+}
+
+impl Query {
+    pub fn parse(text: &str) -> BitsyResult<Self> {
+        let mut parser = Parser { text, position: 0 };
+        let mut terms = Vec::new();
+        parser.skip_spaces();
+        while !parser.rest().is_empty() {
+            terms.push(parser.term()?);
+            let position = parser.position;
+            parser.skip_spaces();
+            if parser.position == position && !parser.rest().is_empty() {
+                return invalid(format!(
+                    "Expected a space at character {}",
+                    parser.position + 1
+                ));
+            }
+        }
+        if terms.is_empty() {
+            return invalid("The query is empty".to_string());
+        }
+        Ok(Query { terms })
+    }
+
+    pub fn matches(&self, item: &NewItem) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.negated != term.condition.matches(item))
+    }
+}
+
+impl Condition {
+    fn matches(&self, item: &NewItem) -> bool {
+        match self {
+            Condition::Word(word) => {
+                names(item).iter().any(|name| name.contains(word.as_str()))
+                    || code(item).is_some_and(|code| code.contains(word.as_str()))
+            }
+            Condition::Text(field, partial, value) => {
+                let texts = match field {
+                    TextField::Name => names(item),
+                    TextField::Code => code(item).into_iter().collect(),
+                    TextField::Guid => item.guid().into_iter().collect(),
+                };
+                texts.iter().any(|text| {
+                    if *partial {
+                        text.contains(value.as_str())
+                    } else {
+                        text == value
+                    }
+                })
+            }
+            Condition::Number(field, operator, expected) => {
+                let value = match field {
+                    NumberField::Sockets => item.socket_count().map(i64::from),
+                    NumberField::ItemLevel => item.drop_level().map(i64::from),
+                    NumberField::Defense => item.defense().map(i64::from),
+                    NumberField::Quantity => item.quantity().map(i64::from),
+                    NumberField::Id => item.set_or_unique_id().map(i64::from),
+                };
+                value.is_some_and(|value| operator.compare(value, *expected))
+            }
+            Condition::Flag(field, expected) => {
+                let value = match field {
+                    FlagField::Ethereal => item.is_ethereal(),
+                    FlagField::Runeword => item.has_runeword(),
+                    FlagField::Identified => item.is_identified(),
+                    FlagField::Personalized => item.is_personalized(),
+                };
+                value == *expected
+            }
+            Condition::Quality(quality) => item.quality() == Some(*quality),
+            Condition::Container(container) => item.container() == *container,
+            Condition::Property(name, comparison) => item.properties().iter().any(|property| {
+                property
+                    .definition()
+                    .text()
+                    .to_lowercase()
+                    .contains(name.as_str())
+                    && comparison.is_none_or(|(operator, expected)| {
+                        operator.compare(i64::from(property.main_value()), expected)
+                    })
+            }),
+        }
+    }
+}
+
+// Everything an item is called, in lower case.
+fn names(item: &NewItem) -> Vec<String> {
+    let mut names = vec![item.description().to_lowercase()];
+    if let Some(name) = item.personalized_name() {
+        names.push(name.to_lowercase());
+    }
+    names
+}
+
+fn code(item: &NewItem) -> Option<String> {
+    item.code().map(|code| code.trim_end().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, rc::Rc};
+
+    use crate::{item::info::MapItemDb, save::SaveFile};
+
+    use super::*;
+
+    fn stash() -> SaveFile {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        SaveFile::read(Path::new("Aleeria.d2x"), item_db).unwrap().1
+    }
+
+    fn find(save: &SaveFile, query: &str) -> Vec<String> {
+        let query = Query::parse(query).unwrap();
+        save.item_lists()
+            .into_iter()
+            .flat_map(|(_, list)| list.items())
+            .filter(|item| query.matches(item))
+            .map(|item| item.code().unwrap().trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn it_parses_queries() {
+        let query = Query::parse(r#"quality:unique name~"crest" -eth:no sockets>=2"#).unwrap();
+        assert_eq!(
+            vec![
+                Condition::Quality(QualityId::Unique),
+                Condition::Text(TextField::Name, true, "crest".to_string()),
+                Condition::Flag(FlagField::Ethereal, false),
+                Condition::Number(NumberField::Sockets, Operator::GreaterOrEqual, 2),
+            ],
+            query
+                .terms
+                .iter()
+                .map(|term| term.condition.clone())
+                .collect::<Vec<_>>()
+        );
+        assert!(query.terms[2].negated);
+
+        let query = Query::parse(r#"prop:"Fire Resist">=20 prop:fcr shako"#).unwrap();
+        assert_eq!(
+            Condition::Property(
+                "fire resist".to_string(),
+                Some((Operator::GreaterOrEqual, 20))
+            ),
+            query.terms[0].condition
+        );
+        assert_eq!(
+            Condition::Property("faster cast rate".to_string(), None),
+            query.terms[1].condition
+        );
+        assert_eq!(
+            Condition::Word("shako".to_string()),
+            query.terms[2].condition
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_queries() {
+        for query in [
+            "",
+            "  ",
+            "color:red",
+            "quality:shiny",
+            "sockets>=many",
+            "eth:maybe",
+            "name>3",
+            r#"name:"open"#,
+            "prop:fcr:3",
+            r#"name:"a"b"#,
+        ] {
+            assert!(Query::parse(query).is_err(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn it_finds_items() {
+        let save = stash();
+
+        let rares = find(&save, "quality:rare");
+        assert!(rares.contains(&"lbb".to_string()));
+        let count: usize = save
+            .item_lists()
+            .iter()
+            .map(|(_, list)| list.items().len())
+            .sum();
+        assert_eq!(count, rares.len() + find(&save, "-quality:rare").len());
+        assert_eq!(
+            vec!["lbb"],
+            find(&save, "quality:rare code:lbb sockets:1 eth:no")
+        );
+        assert_eq!(
+            vec!["lbb"],
+            find(&save, r#"name~"battle bow" prop:ias>=20"#)
+        );
+        assert!(find(&save, "prop:ias>20 code:lbb").is_empty());
+        assert_eq!(vec!["tbl"], find(&save, r#"heavy prop:"Poison Resist">=7"#));
+    }
+}
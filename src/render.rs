@@ -0,0 +1,242 @@
+// Draws the items of a panel as a grid of text, each item filling its cells with a letter that the
+// legend below it explains.
+use crate::{
+    item::{Container, ItemList, NewItem},
+    quality::QualityId,
+    validate::{describe, item_path},
+};
+
+const RESET: &str = "\x1b[0m";
+const RED: &str = "\x1b[31m";
+const LABELS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+// For items beyond the labels.
+const NO_LABEL: char = '*';
+const OVERLAP: char = '!';
+// Slots of the belt, four a row, the first row at the bottom.
+pub const BELT_SIZE: (u8, u8) = (4, 4);
+
+#[derive(Debug, Clone)]
+pub struct GridOptions {
+    // Colours the items after their quality, with ANSI escapes.
+    pub color: bool,
+    // Draws the frame with box drawing characters instead of ASCII.
+    pub unicode: bool,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        Self {
+            color: false,
+            unicode: true,
+        }
+    }
+}
+
+// The colours of the game's item names.
+fn quality_color(quality: Option<QualityId>) -> &'static str {
+    match quality {
+        Some(QualityId::Low) => "\x1b[2m",
+        Some(QualityId::Magic) => "\x1b[34m",
+        Some(QualityId::Set) => "\x1b[32m",
+        Some(QualityId::Rare) => "\x1b[93m",
+        Some(QualityId::Unique) => "\x1b[33m",
+        Some(QualityId::Crafted) => "\x1b[91m",
+        _ => "",
+    }
+}
+
+fn paint(text: &str, color: &str, options: &GridOptions) -> String {
+    if options.color && !color.is_empty() {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+struct Entry<'a> {
+    index: usize,
+    item: &'a NewItem,
+    label: char,
+    position: (u8, u8),
+    size: Option<(u8, u8)>,
+}
+
+impl Entry<'_> {
+    // Items of unknown size are drawn in one cell.
+    fn cells(&self) -> impl Iterator<Item = (u8, u8)> {
+        let ((x, y), (width, height)) = (self.position, self.size.unwrap_or((1, 1)));
+        (y..y.saturating_add(height))
+            .flat_map(move |y| (x..x.saturating_add(width)).map(move |x| (x, y)))
+    }
+}
+
+// Draws the items of `list` that are in `container`, in a panel of `size`.
+pub fn render_grid(
+    list: usize,
+    items: &ItemList,
+    container: Container,
+    (width, height): (u8, u8),
+    options: &GridOptions,
+) -> String {
+    let entries: Vec<Entry> = items
+        .items()
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| item.container() == container)
+        .enumerate()
+        .map(|(number, (index, item))| {
+            let (x, y) = item.position();
+            let (position, size) = if container == Container::Belt {
+                ((x % width, x / width), Some((1, 1)))
+            } else {
+                ((x, y), item.size())
+            };
+            Entry {
+                index,
+                item,
+                label: LABELS.chars().nth(number).unwrap_or(NO_LABEL),
+                position,
+                size,
+            }
+        })
+        .collect();
+
+    let mut cells: Vec<Vec<Option<&Entry>>> =
+        vec![vec![None; usize::from(width)]; usize::from(height)];
+    let mut overlaps = vec![vec![false; usize::from(width)]; usize::from(height)];
+    let mut outside = Vec::new();
+    for entry in &entries {
+        for (x, y) in entry.cells() {
+            if x >= width || y >= height {
+                if !outside.contains(&entry.index) {
+                    outside.push(entry.index);
+                }
+                continue;
+            }
+            let cell = &mut cells[usize::from(y)][usize::from(x)];
+            if cell.is_some() {
+                overlaps[usize::from(y)][usize::from(x)] = true;
+            }
+            *cell = Some(entry);
+        }
+    }
+    if container == Container::Belt {
+        cells.reverse();
+        overlaps.reverse();
+    }
+
+    let (horizontal, vertical, corners) = if options.unicode {
+        ("───", '│', ['┌', '┐', '└', '┘'])
+    } else {
+        ("---", '|', ['+', '+', '+', '+'])
+    };
+    let empty = if options.unicode { '·' } else { '.' };
+    let border = horizontal.repeat(usize::from(width));
+    let mut text = format!("{}{}{}\n", corners[0], border, corners[1]);
+    for (row, row_overlaps) in cells.iter().zip(&overlaps) {
+        text.push(vertical);
+        for (cell, &overlap) in row.iter().zip(row_overlaps) {
+            text.push_str(&match cell {
+                _ if overlap => paint(&format!(" {} ", OVERLAP), RED, options),
+                Some(entry) => paint(
+                    &format!(" {} ", entry.label),
+                    quality_color(entry.item.quality()),
+                    options,
+                ),
+                None => format!(" {} ", empty),
+            });
+        }
+        text.push(vertical);
+        text.push('\n');
+    }
+    text.push_str(&format!("{}{}{}\n", corners[2], border, corners[3]));
+
+    for entry in &entries {
+        let mut line = format!(
+            "{} {} {}",
+            entry.label,
+            item_path(list, entry.index, None),
+            describe(entry.item)
+        );
+        let (x, y) = entry.item.position();
+        match (container, entry.size) {
+            (Container::Belt, _) => line.push_str(&format!(" slot {}", x)),
+            (_, Some((item_width, item_height))) => {
+                line.push_str(&format!(" ({}, {}) {}x{}", x, y, item_width, item_height))
+            }
+            (_, None) => line.push_str(&format!(" ({}, {}) size unknown", x, y)),
+        }
+        if outside.contains(&entry.index) {
+            line.push_str(", outside the panel");
+        }
+        if entry.cells().any(|(x, y)| {
+            overlaps
+                .get(usize::from(y))
+                .and_then(|row| row.get(usize::from(x)))
+                == Some(&true)
+        }) {
+            line.push_str(", overlapping");
+        }
+        text.push_str(&paint(&line, quality_color(entry.item.quality()), options));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, rc::Rc};
+
+    use crate::{item::info::MapItemDb, save::SaveFile};
+
+    use super::*;
+
+    fn page_four() -> SaveFile {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        SaveFile::read(Path::new("small_stash.sss"), item_db)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn it_draws_items_with_a_legend() {
+        let mut save = page_four();
+        let items = save.item_lists_mut().swap_remove(3);
+        let options = GridOptions {
+            color: false,
+            unicode: false,
+        };
+
+        let text = render_grid(3, items, Container::Stash, (10, 10), &options);
+
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(12 + items.items().len(), lines.len());
+        assert_eq!(format!("+{}+", "-".repeat(30)), lines[0]);
+        assert!(lines[1].starts_with('|') && lines[1].ends_with('|'));
+        assert_eq!(32, lines[1].len());
+        assert_eq!("B 4:2 Mythril Gauntlets [mgu] (6, 5) 2x2", lines[13]);
+        let (x, y) = items.items()[1].position();
+        let row = lines[1 + usize::from(y)];
+        assert_eq!(" B ", &row[1 + 3 * usize::from(x)..4 + 3 * usize::from(x)]);
+        assert!(!text.contains(OVERLAP));
+    }
+
+    #[test]
+    fn it_shows_overlapping_items() {
+        let mut save = page_four();
+        let items = save.item_lists_mut().swap_remove(3);
+        let (x, y) = items.items()[0].position();
+        items.items_mut()[1].set_position(x, y).unwrap();
+
+        let text = render_grid(
+            3,
+            items,
+            Container::Stash,
+            (10, 10),
+            &GridOptions::default(),
+        );
+
+        assert!(text.contains(" ! "));
+        assert!(text.lines().nth(13).unwrap().ends_with(", overlapping"));
+    }
+}
@@ -0,0 +1,397 @@
+// Writing save files without risking the ones on disk. A save is only written if what would be
+// written parses back to the same save, it replaces the file in one rename so that a crash leaves
+// either the old or the new file, and the old file is kept as a backup first.
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value;
+
+use crate::{
+    bitsy::{
+        error::{BitsyError, BitsyErrorKind},
+        result::BitsyResult,
+    },
+    item::info::ItemDb,
+    save::{hash, SaveFile, SaveKind},
+};
+
+pub const BACKUP_DIR: &str = "backups";
+pub const DEFAULT_BACKUPS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    // How many backups of a file are kept, the oldest are removed. 0 makes none.
+    pub backups: usize,
+    // Where backups go, the `backups` directory next to the file by default.
+    pub backup_dir: Option<PathBuf>,
+    // Backups that are never removed, whatever their number, like those the journal needs to undo.
+    // The paths are canonical.
+    pub kept_backups: Vec<PathBuf>,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            backups: DEFAULT_BACKUPS,
+            backup_dir: None,
+            kept_backups: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Written {
+    pub path: PathBuf,
+    // The hash of the file that was replaced, see `save::hash`.
+    pub before: Option<String>,
+    pub bytes: Vec<u8>,
+    // The copy of the file that was replaced, if there was one.
+    pub backup: Option<PathBuf>,
+}
+
+fn io_error(action: &str, path: &Path, error: std::io::Error) -> BitsyError {
+    BitsyErrorKind::Io(format!(
+        "Could not {} {}: {}",
+        action,
+        path.display(),
+        error
+    ))
+    .at_bit(0)
+}
+
+// The save as JSON, without what `to_bytes` computes anew.
+fn model(save: &SaveFile) -> BitsyResult<Value> {
+    let mut value = serde_json::to_value(save).map_err(|error| {
+        BitsyErrorKind::InvalidData(format!("Could not compare the save: {}", error)).at_bit(0)
+    })?;
+    if let Some(player) = value.get_mut("Player").and_then(Value::as_object_mut) {
+        player.remove("file_size");
+        player.remove("checksum");
+    }
+    Ok(value)
+}
+
+// Where two values first differ, as a path like `.Stash.pages[3].items`.
+fn first_difference(path: String, old: &Value, new: &Value) -> Option<String> {
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields))
+            if old_fields.len() == new_fields.len() =>
+        {
+            old_fields.iter().find_map(|(key, old_value)| {
+                let field = format!("{}.{}", path, key);
+                match new_fields.get(key) {
+                    Some(new_value) => first_difference(field, old_value, new_value),
+                    None => Some(field),
+                }
+            })
+        }
+        (Value::Array(old_values), Value::Array(new_values))
+            if old_values.len() == new_values.len() =>
+        {
+            old_values.iter().zip(new_values).enumerate().find_map(
+                |(index, (old_value, new_value))| {
+                    first_difference(format!("{}[{}]", path, index), old_value, new_value)
+                },
+            )
+        }
+        _ if old == new => None,
+        _ => Some(path),
+    }
+}
+
+// The bytes of the save, once they were checked to parse back to the same save.
+pub fn verified_bytes(
+    kind: SaveKind,
+    save: &SaveFile,
+    item_db: Rc<dyn ItemDb>,
+) -> BitsyResult<Vec<u8>> {
+    let bytes = save.to_bytes()?;
+    let has_opaque_items = save
+        .item_lists()
+        .iter()
+        .any(|(_, list)| !list.opaque_items().is_empty());
+    let reparsed = if has_opaque_items {
+        SaveFile::parse_lenient(kind, &bytes, item_db).map(|(save, _)| save)
+    } else {
+        SaveFile::parse(kind, &bytes, item_db)
+    }
+    .map_err(|error| {
+        BitsyErrorKind::InvalidData(format!("The file to write doesn't parse: {}", error.kind()))
+            .at_bit(0)
+    })?;
+    if let Some(path) = first_difference(String::new(), &model(save)?, &model(&reparsed)?) {
+        return Err(BitsyErrorKind::InvalidData(format!(
+            "The file to write parses differently at {}",
+            path
+        ))
+        .at_bit(0));
+    }
+    Ok(bytes)
+}
+
+// Days since 1970-01-01 to year, month and day, in the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// UTC, down to milliseconds, so that backups sort by name.
+pub fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let second_of_day = seconds.rem_euclid(86_400);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day / 60 % 60,
+        second_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+fn backup_dir(path: &Path, options: &WriteOptions) -> PathBuf {
+    options.backup_dir.clone().unwrap_or_else(|| {
+        path.parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(BACKUP_DIR)
+    })
+}
+
+// The backups of a file, oldest first.
+pub fn backups(path: &Path, options: &WriteOptions) -> BitsyResult<Vec<PathBuf>> {
+    let dir = backup_dir(path, options);
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => format!("{}.", name),
+        None => return Ok(Vec::new()),
+    };
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(io_error("read", &dir, error)),
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|backup| {
+            backup
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .is_some_and(|file_name| {
+                    file_name.starts_with(&name) && file_name.ends_with(".bak")
+                })
+        })
+        .collect();
+    backups.sort();
+    Ok(backups)
+}
+
+// Keeps the content of the file in a new backup, and removes the oldest ones beyond the number
+// kept, except for those in `kept_backups`.
+fn back_up(path: &Path, original: &[u8], options: &WriteOptions) -> BitsyResult<Option<PathBuf>> {
+    if options.backups == 0 {
+        return Ok(None);
+    }
+    let dir = backup_dir(path, options);
+    fs::create_dir_all(&dir).map_err(|error| io_error("create", &dir, error))?;
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("save");
+    let stamp = timestamp(SystemTime::now());
+    // Two writes in the same millisecond get a letter, which still sorts after the first one.
+    let backup = (0..=26u8)
+        .map(|count| match count {
+            0 => dir.join(format!("{}.{}.bak", name, stamp)),
+            count => dir.join(format!(
+                "{}.{}{}.bak",
+                name,
+                stamp,
+                (b'a' + count - 1) as char
+            )),
+        })
+        .find(|backup| !backup.exists())
+        .ok_or_else(|| {
+            BitsyErrorKind::Io(format!("Too many backups of {} at once", path.display())).at_bit(0)
+        })?;
+    fs::write(&backup, original).map_err(|error| io_error("back up", path, error))?;
+
+    let all = backups(path, options)?;
+    let is_kept = |old: &PathBuf| {
+        fs::canonicalize(old).is_ok_and(|old| options.kept_backups.contains(&old))
+    };
+    for old in all[..all.len().saturating_sub(options.backups)]
+        .iter()
+        .filter(|old| !is_kept(old))
+    {
+        fs::remove_file(old).map_err(|error| io_error("remove", old, error))?;
+    }
+    Ok(Some(backup))
+}
+
+// Replaces the file with the bytes in one rename, through a temporary file in the same directory.
+pub fn replace_file(path: &Path, bytes: &[u8]) -> BitsyResult<()> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("save");
+    let temporary = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    let written = File::create(&temporary).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    if let Err(error) = written.and_then(|_| fs::rename(&temporary, path)) {
+        let _ = fs::remove_file(&temporary);
+        return Err(io_error("write", path, error));
+    }
+    Ok(())
+}
+
+// Replaces the file, backing it up first.
+pub fn write_file(path: &Path, bytes: Vec<u8>, options: &WriteOptions) -> BitsyResult<Written> {
+    let original = match fs::read(path) {
+        Ok(original) => Some(original),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+        Err(error) => return Err(io_error("read", path, error)),
+    };
+    let backup = match &original {
+        Some(original) => back_up(path, original, options)?,
+        None => None,
+    };
+    replace_file(path, &bytes)?;
+    Ok(Written {
+        path: path.to_path_buf(),
+        before: original.as_deref().map(hash),
+        bytes,
+        backup,
+    })
+}
+
+// Writes a save after checking it, backing up the file it replaces.
+pub fn write_save(
+    path: &Path,
+    kind: SaveKind,
+    save: &SaveFile,
+    item_db: Rc<dyn ItemDb>,
+    options: &WriteOptions,
+) -> BitsyResult<Written> {
+    let bytes = verified_bytes(kind, save, item_db)?;
+    write_file(path, bytes, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{export::Export, item::info::MapItemDb};
+
+    use super::*;
+
+    fn item_db() -> Rc<dyn ItemDb> {
+        Rc::new(MapItemDb::from_data_dir("data/items"))
+    }
+
+    fn temporary_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("d2items-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn it_formats_timestamps() {
+        assert_eq!("19700101-000000-000", timestamp(UNIX_EPOCH));
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!("20240229-123456-789", timestamp(time));
+    }
+
+    #[test]
+    fn it_verifies_every_sample() {
+        for path in [
+            "examples/LaCope2.d2s",
+            "examples/StartingD2R.d2s",
+            "small_stash.sss",
+            "Aleeria.d2x",
+        ] {
+            let (kind, save) = SaveFile::read(Path::new(path), item_db()).unwrap();
+            let bytes = verified_bytes(kind, &save, item_db()).unwrap();
+            assert_eq!(fs::read(path).unwrap(), bytes, "{}", path);
+        }
+    }
+
+    #[test]
+    fn it_refuses_saves_that_parse_differently() {
+        let bytes = fs::read("small_stash.sss").unwrap();
+        let (kind, save) = SaveFile::read(Path::new("small_stash.sss"), item_db()).unwrap();
+        let mut json: Value =
+            serde_json::from_str(&Export::new(kind, &bytes, save).to_json().unwrap()).unwrap();
+        // Item bases are looked up again when parsing.
+        json["save"]["Stash"]["pages"][3]["items"]["items"][0]["body"]["Regular"]["item_info"]
+            ["name"] = "Elixir of Wisdom".into();
+        let save = Export::from_json(&json.to_string()).unwrap().save;
+
+        let error = verified_bytes(kind, &save, item_db()).unwrap_err();
+
+        assert_eq!(
+            &BitsyErrorKind::InvalidData(
+                "The file to write parses differently at \
+                 .Stash.pages[3].items.items[0].body.Regular.item_info.name"
+                    .to_string()
+            ),
+            error.kind()
+        );
+    }
+
+    #[test]
+    fn it_writes_and_keeps_backups() {
+        let dir = temporary_dir("safe-write");
+        let path = dir.join("stash.sss");
+        let (kind, save) = SaveFile::read(Path::new("small_stash.sss"), item_db()).unwrap();
+        let options = WriteOptions {
+            backups: 2,
+            backup_dir: None,
+            kept_backups: Vec::new(),
+        };
+
+        let first = write_save(&path, kind, &save, item_db(), &options).unwrap();
+        let mut made = Vec::new();
+        for _ in 0..3 {
+            made.push(
+                write_save(&path, kind, &save, item_db(), &options)
+                    .unwrap()
+                    .backup
+                    .unwrap(),
+            );
+        }
+
+        assert_eq!((None, None), (first.before, first.backup));
+        assert_eq!(
+            fs::read("small_stash.sss").unwrap(),
+            fs::read(&path).unwrap()
+        );
+        assert_eq!(made[1..].to_vec(), backups(&path, &options).unwrap());
+        // No temporary file is left behind.
+        assert_eq!(2, fs::read_dir(&dir).unwrap().count());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
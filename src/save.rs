@@ -0,0 +1,363 @@
+// Reading and writing whole save files, whatever their kind.
+use std::{
+    convert::TryInto,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    bitsy::{
+        error::{BitsyErrorExt, BitsyErrorKind},
+        lenient::{read_lenient, Diagnostic},
+        result::BitsyResult,
+        BitReader, BitVecReader, BitVecWriter, BitWriter, MyBitVec,
+    },
+    item::{
+        info::ItemDb, properties::MapPropertyDb, runeword::MapRunewordDb, ItemList, D2R_VERSION,
+    },
+    player::Player,
+    stash::{NewStash, STASH_VERSION},
+};
+
+const PLAYER_MAGIC: [u8; 4] = [0x55, 0xAA, 0x55, 0xAA];
+const SHARED_STASH_MAGIC: [u8; 4] = *b"SSS\0";
+const PERSONAL_STASH_MAGIC: [u8; 4] = *b"CSTM";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SaveKind {
+    // A character, .d2s.
+    Player,
+    // A PlugY shared stash, .sss.
+    SharedStash,
+    // A PlugY personal stash, .d2x.
+    PersonalStash,
+}
+
+impl SaveKind {
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "d2s" => Some(SaveKind::Player),
+            "sss" => Some(SaveKind::SharedStash),
+            "d2x" => Some(SaveKind::PersonalStash),
+            _ => None,
+        }
+    }
+
+    pub fn from_magic(bytes: &[u8]) -> Option<Self> {
+        match bytes.get(..4)? {
+            magic if magic == PLAYER_MAGIC => Some(SaveKind::Player),
+            magic if magic == SHARED_STASH_MAGIC => Some(SaveKind::SharedStash),
+            magic if magic == PERSONAL_STASH_MAGIC => Some(SaveKind::PersonalStash),
+            _ => None,
+        }
+    }
+
+    // The first bytes of the file are trusted over its name.
+    pub fn detect(path: &Path, bytes: &[u8]) -> BitsyResult<Self> {
+        Self::from_magic(bytes)
+            .or_else(|| Self::from_extension(path))
+            .ok_or_else(|| {
+                BitsyErrorKind::InvalidData(format!(
+                    "{} is not a .d2s, .sss or .d2x file",
+                    path.display()
+                ))
+                .at_bit(0)
+            })
+    }
+
+    // The version of the items in a file of this kind, which for characters is in the header. Too
+    // short a header fails to parse later, so any version will do for it.
+    pub fn version(&self, bytes: &[u8]) -> u32 {
+        match self {
+            SaveKind::Player => bytes
+                .get(4..8)
+                .map_or(0, |version| u32::from_le_bytes(version.try_into().unwrap())),
+            SaveKind::SharedStash | SaveKind::PersonalStash => STASH_VERSION,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SaveKind::Player => "d2s",
+            SaveKind::SharedStash => "sss",
+            SaveKind::PersonalStash => "d2x",
+        }
+    }
+}
+
+// Only a few saves are ever loaded at once, so boxing players is not worth it.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Serialize, Deserialize)]
+pub enum SaveFile {
+    Player(Player),
+    Stash(NewStash),
+}
+
+impl SaveFile {
+    // A reader set up for the kind of file. Reading from it with `read_with` doesn't check that
+    // the whole input was used, so the rest can be inspected.
+    pub fn reader(kind: SaveKind, bytes: &[u8], item_db: Rc<dyn ItemDb>) -> BitVecReader {
+        let mut reader = BitVecReader::with_item_db(MyBitVec::from_vec(bytes.to_vec()), item_db);
+        reader.set_runeword_db(Rc::new(MapRunewordDb::builtin()));
+        // Properties the db doesn't know are kept as bits, from the first of them on.
+        reader.set_property_db(Rc::new(if kind.version(bytes) < D2R_VERSION {
+            MapPropertyDb::new()
+        } else {
+            MapPropertyDb::d2r()
+        }));
+        reader
+    }
+
+    pub fn read_with<R: BitReader>(kind: SaveKind, reader: &mut R) -> BitsyResult<Self> {
+        match kind {
+            SaveKind::Player => reader.read().map(SaveFile::Player),
+            SaveKind::SharedStash | SaveKind::PersonalStash => reader.read().map(SaveFile::Stash),
+        }
+    }
+
+    fn check_fully_read(reader: &BitVecReader, bytes: &[u8]) -> BitsyResult<()> {
+        if reader.index() < bytes.len() * 8 {
+            return Err(BitsyErrorKind::InvalidData(format!(
+                "{} unexpected bytes after the end of the file",
+                bytes.len() - reader.index() / 8
+            ))
+            .at_bit(reader.index()));
+        }
+        Ok(())
+    }
+
+    pub fn parse(kind: SaveKind, bytes: &[u8], item_db: Rc<dyn ItemDb>) -> BitsyResult<Self> {
+        let mut reader = Self::reader(kind, bytes, item_db);
+        let save = Self::read_with(kind, &mut reader)?;
+        Self::check_fully_read(&reader, bytes)?;
+        Ok(save)
+    }
+
+    // Keeps items that can't be parsed as bits, see `lenient`.
+    pub fn parse_lenient(
+        kind: SaveKind,
+        bytes: &[u8],
+        item_db: Rc<dyn ItemDb>,
+    ) -> BitsyResult<(Self, Vec<Diagnostic>)> {
+        let mut reader = Self::reader(kind, bytes, item_db);
+        let (save, diagnostics) = match kind {
+            SaveKind::Player => {
+                let (player, diagnostics) = read_lenient(&mut reader)?;
+                (SaveFile::Player(player), diagnostics)
+            }
+            SaveKind::SharedStash | SaveKind::PersonalStash => {
+                let (stash, diagnostics) = read_lenient(&mut reader)?;
+                (SaveFile::Stash(stash), diagnostics)
+            }
+        };
+        Self::check_fully_read(&reader, bytes)?;
+        Ok((save, diagnostics))
+    }
+
+    pub fn read(path: &Path, item_db: Rc<dyn ItemDb>) -> BitsyResult<(SaveKind, Self)> {
+        let bytes = read_file(path)?;
+        let kind = SaveKind::detect(path, &bytes)?;
+        let save = Self::parse(kind, &bytes, item_db)
+            .map_err(|error| error.with_source(&MyBitVec::from_vec(bytes)))?;
+        Ok((kind, save))
+    }
+
+    pub fn version(&self) -> u32 {
+        match self {
+            SaveFile::Player(player) => player.version,
+            SaveFile::Stash(_) => STASH_VERSION,
+        }
+    }
+
+    // The bits of the file exactly as they were parsed.
+    pub fn to_bits(&self) -> BitsyResult<MyBitVec> {
+        let mut writer = BitVecWriter::new(self.version());
+        match self {
+            SaveFile::Player(player) => writer.write(player)?,
+            SaveFile::Stash(stash) => writer.write(stash)?,
+        }
+        Ok(writer.into_bits())
+    }
+
+    // The bytes to save, with anything derived from the content, like checksums, updated.
+    pub fn to_bytes(&self) -> BitsyResult<Vec<u8>> {
+        match self {
+            SaveFile::Player(player) => player.to_bytes(),
+            SaveFile::Stash(_) => Ok(self.to_bits()?.into_vec()),
+        }
+    }
+
+    // Every item list in the file, with a name for where it is.
+    pub fn item_lists(&self) -> Vec<(String, &ItemList)> {
+        match self {
+            SaveFile::Player(player) => vec![
+                Some(("character".to_string(), player.items())),
+                player
+                    .corpse_items()
+                    .map(|items| ("corpse".to_string(), items)),
+                player
+                    .mercenary_items()
+                    .map(|items| ("mercenary".to_string(), items)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            SaveFile::Stash(stash) => stash
+                .pages()
+                .iter()
+                .enumerate()
+                .map(|(index, page)| (page_label(index, page.name()), page.items()))
+                .collect(),
+        }
+    }
+
+    // The same lists as `item_lists`, in the same order.
+    pub fn item_lists_mut(&mut self) -> Vec<&mut ItemList> {
+        match self {
+            SaveFile::Player(player) => {
+                let (items, corpse, mercenary) = player.item_lists_mut();
+                vec![Some(items), corpse, mercenary]
+                    .into_iter()
+                    .flatten()
+                    .collect()
+            }
+            SaveFile::Stash(stash) => stash
+                .pages_mut()
+                .iter_mut()
+                .map(|page| page.items_mut())
+                .collect(),
+        }
+    }
+}
+
+pub fn page_label(index: usize, name: &str) -> String {
+    if name.is_empty() {
+        format!("page {}", index + 1)
+    } else {
+        format!("page {} ({})", index + 1, name)
+    }
+}
+
+// The save files in a directory and its subdirectories, sorted, going by their extension. A path
+// to a file is returned as it is.
+pub fn find_saves(path: &Path) -> BitsyResult<Vec<PathBuf>> {
+    let io_error = |error: std::io::Error| {
+        BitsyErrorKind::Io(format!("Could not list {}: {}", path.display(), error)).at_bit(0)
+    };
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut saves = Vec::new();
+    for entry in std::fs::read_dir(path).map_err(io_error)? {
+        let entry_path = entry.map_err(io_error)?.path();
+        if entry_path.is_dir() {
+            saves.extend(find_saves(&entry_path)?);
+        } else if SaveKind::from_extension(&entry_path).is_some() {
+            saves.push(entry_path);
+        }
+    }
+    saves.sort();
+    Ok(saves)
+}
+
+// The SHA-256 of a file's bytes, in hex.
+pub fn hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn read_file(path: &Path) -> BitsyResult<Vec<u8>> {
+    std::fs::read(path).map_err(|error| {
+        BitsyErrorKind::Io(format!("Could not read {}: {}", path.display(), error)).at_bit(0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::item::info::MapItemDb;
+
+    use super::*;
+
+    fn item_db() -> Rc<dyn ItemDb> {
+        Rc::new(MapItemDb::from_data_dir("data/items"))
+    }
+
+    #[test]
+    fn it_detects_kinds() {
+        for (path, kind) in [
+            ("examples/LaCope2.d2s", SaveKind::Player),
+            ("small_stash.sss", SaveKind::SharedStash),
+            ("Aleeria.d2x", SaveKind::PersonalStash),
+        ] {
+            let bytes = std::fs::read(path).unwrap();
+            assert_eq!(
+                kind,
+                SaveKind::detect(Path::new("renamed.bin"), &bytes).unwrap()
+            );
+            assert_eq!(Some(kind), SaveKind::from_extension(Path::new(path)));
+        }
+        assert_eq!(
+            SaveKind::Player,
+            SaveKind::detect(Path::new("NEW.D2S"), &[]).unwrap()
+        );
+        assert!(SaveKind::detect(Path::new("notes.txt"), b"hello").is_err());
+    }
+
+    #[test]
+    fn it_reads_and_writes_saves() {
+        for path in [
+            "examples/PlasticSurgeon.d2s",
+            "small_stash.sss",
+            "Aleeria.d2x",
+        ] {
+            let bytes = std::fs::read(path).unwrap();
+            let (_, save) = SaveFile::read(Path::new(path), item_db()).unwrap();
+
+            assert_eq!(bytes, save.to_bytes().unwrap());
+            assert!(!save.item_lists().is_empty());
+        }
+    }
+
+    #[test]
+    fn it_decodes_character_properties() {
+        let (_, save) = SaveFile::read(Path::new("examples/LaCope2.d2s"), item_db()).unwrap();
+        let amulet = save.item_lists()[0]
+            .1
+            .items()
+            .iter()
+            .find(|item| item.code().as_deref() == Some("amu "))
+            .unwrap();
+
+        assert_eq!("+28 to Life", amulet.properties()[0].description());
+    }
+
+    #[test]
+    fn it_finds_saves() {
+        let saves = find_saves(Path::new("examples")).unwrap();
+
+        assert!(saves.contains(&PathBuf::from("examples/LaCope2.d2s")));
+        assert!(saves.iter().all(|path| path.extension().unwrap() == "d2s"));
+        assert!(saves.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            vec![PathBuf::from("small_stash.sss")],
+            find_saves(Path::new("small_stash.sss")).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_rejects_trailing_bytes() {
+        let mut bytes = std::fs::read("small_stash.sss").unwrap();
+        bytes.extend_from_slice(b"ST\0");
+
+        let error = SaveFile::parse(SaveKind::SharedStash, &bytes, item_db()).unwrap_err();
+
+        assert_eq!("E002", error.code());
+        let error = SaveFile::read(Path::new("missing.sss"), item_db()).unwrap_err();
+        assert_eq!("E006", error.code());
+    }
+}
@@ -0,0 +1,138 @@
+// Lays out the items of a stash page again, so that items of the same type end up together.
+use std::cmp::Reverse;
+
+use crate::{
+    bitsy::{error::BitsyErrorKind, result::BitsyResult},
+    grid::Grid,
+    item::{ItemList, NewItem, MODE_STORED},
+};
+
+// Big items go first, as they are the hardest to fit. Items of the same size are grouped by base
+// type, and then by quality.
+fn sort_key(item: &NewItem) -> impl Ord {
+    let (width, height) = item.size().unwrap_or((1, 1));
+    (
+        Reverse(u16::from(width) * u16::from(height)),
+        item.item_info().map(|info| info.name.clone()),
+        item.code(),
+        item.quality().map(|quality| quality as u8),
+        item.description(),
+    )
+}
+
+fn layout(
+    items: &[NewItem],
+    order: &[usize],
+    width: u8,
+    height: u8,
+    by_columns: bool,
+) -> BitsyResult<Vec<(u8, u8)>> {
+    let mut grid = Grid::new(width, height);
+    order
+        .iter()
+        .map(|&index| {
+            let item = &items[index];
+            let (item_width, item_height) = item.size().ok_or_else(|| {
+                BitsyErrorKind::InvalidData(format!(
+                    "Unknown size for {} ({})",
+                    item.description(),
+                    item.code().unwrap_or_default().trim_end()
+                ))
+                .at_bit(0)
+            })?;
+            grid.place(item_width, item_height, by_columns)
+                .ok_or_else(|| {
+                    BitsyErrorKind::InvalidAction(format!(
+                        "No room left for {}",
+                        item.description()
+                    ))
+                    .at_bit(0)
+                })
+        })
+        .collect()
+}
+
+// Moves the stored items of a list so that, in sorted order, they fill a `width` by `height` grid
+// from the top left. Only positions change, and nothing does if any item can't be placed.
+pub fn sort_items(list: &mut ItemList, width: u8, height: u8) -> BitsyResult<()> {
+    if !list.opaque_items().is_empty() {
+        return Err(BitsyErrorKind::InvalidAction(
+            "Lists with unparsed items can't be sorted".to_string(),
+        )
+        .at_bit(0));
+    }
+    let mut order: Vec<usize> = (0..list.items().len())
+        .filter(|&index| list.items()[index].mode() == MODE_STORED)
+        .collect();
+    order.sort_by_cached_key(|&index| sort_key(&list.items()[index]));
+
+    let positions = layout(list.items(), &order, width, height, false)
+        .or_else(|_| layout(list.items(), &order, width, height, true))?;
+    for (index, (x, y)) in order.into_iter().zip(positions) {
+        list.items_mut()[index].set_position(x, y)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, rc::Rc};
+
+    use crate::{
+        item::info::MapItemDb,
+        save::SaveFile,
+        stash::{NewStash, PAGE_HEIGHT, PAGE_WIDTH},
+    };
+
+    use super::*;
+
+    fn stash() -> NewStash {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        match SaveFile::read(Path::new("small_stash.sss"), item_db)
+            .unwrap()
+            .1
+        {
+            SaveFile::Stash(stash) => stash,
+            SaveFile::Player(_) => unreachable!(),
+        }
+    }
+
+    fn positions(list: &ItemList) -> Vec<(u8, u8)> {
+        list.items().iter().map(NewItem::position).collect()
+    }
+
+    #[test]
+    fn it_lays_out_items_without_overlaps() {
+        let mut stash = stash();
+        let list = stash.pages_mut()[3].items_mut();
+        let before = positions(list);
+
+        sort_items(list, PAGE_WIDTH, PAGE_HEIGHT).unwrap();
+
+        assert_ne!(before, positions(list));
+        let mut grid = Grid::new(PAGE_WIDTH, PAGE_HEIGHT);
+        for item in list.items() {
+            let (x, y) = item.position();
+            let (width, height) = item.size().unwrap();
+            assert!(grid.occupy(x, y, width, height));
+        }
+        let sorted = positions(list);
+        sort_items(list, PAGE_WIDTH, PAGE_HEIGHT).unwrap();
+        assert_eq!(sorted, positions(list));
+        assert!(SaveFile::Stash(stash).to_bytes().is_ok());
+    }
+
+    #[test]
+    fn it_leaves_lists_it_cant_sort_as_they_are() {
+        let mut stash = stash();
+        for (page, width, code) in [(2, PAGE_WIDTH, "E002"), (3, 2, "E005")] {
+            let list = stash.pages_mut()[page].items_mut();
+            let before = positions(list);
+
+            let error = sort_items(list, width, PAGE_HEIGHT).unwrap_err();
+
+            assert_eq!(code, error.code());
+            assert_eq!(before, positions(list));
+        }
+    }
+}
@@ -1,11 +1,20 @@
 use std::fmt::{Display, Formatter};
 
 use bitvec::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use bitsy::*;
 
 use crate::bitsy;
+use crate::bitsy::{
+    context,
+    error::BitsyErrorExt,
+    macros::{bitsy_cond_read, bitsy_read, bitsy_write},
+    result::BitsyResult,
+    structs::BitsyBytes,
+};
 use crate::item::reader::ItemReader;
+use crate::item::ItemList;
 use crate::page::Page;
 
 pub struct Stash {
@@ -64,3 +73,215 @@ impl Display for Stash {
         );
     }
 }
+
+// PlugY stash pages start with "ST" and a NUL-terminated page name.
+const PAGE_MAGIC: [u8; 2] = [0x53, 0x54];
+// Stashes don't store a file version, and their items use the 1.10 format.
+pub const STASH_VERSION: u32 = 96;
+
+// The size of a stash page, in inventory cells.
+pub const PAGE_WIDTH: u8 = 10;
+pub const PAGE_HEIGHT: u8 = 10;
+
+// A PlugY shared (.sss) or personal (.d2x) stash.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewStash {
+    // "SSS\0" followed by the format version ("01" or "02") for shared stashes, "CSTM01" for
+    // personal ones.
+    header: BitsyBytes<6>,
+    // Only shared stashes from version "02" have gold.
+    gold: Option<u32>,
+    // Personal stashes have 4 bytes there instead, which PlugY leaves as 0.
+    reserved: Option<BitsyBytes<4>>,
+    pages: Vec<NewPage>,
+}
+
+impl NewStash {
+    pub fn gold(&self) -> Option<u32> {
+        self.gold
+    }
+
+    pub fn pages(&self) -> &[NewPage] {
+        &self.pages
+    }
+
+    pub fn pages_mut(&mut self) -> &mut [NewPage] {
+        &mut self.pages
+    }
+}
+
+impl Bitsy for NewStash {
+    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+        reader.set_context(&context::VERSION, STASH_VERSION);
+        bitsy_read!(reader, header: BitsyBytes<6>);
+        let shared = header.starts_with(b"SSS");
+        bitsy_cond_read!(reader, shared && header[4..] != *b"01", gold);
+        bitsy_cond_read!(reader, !shared, reserved);
+        bitsy_read!(reader, page_count: u32);
+        let pages = reader.read_named("pages", |reader| {
+            (0..page_count as usize)
+                .map(|index| reader.read_element(index))
+                .collect::<BitsyResult<Vec<_>>>()
+        })?;
+        Ok(Self {
+            header,
+            gold,
+            reserved,
+            pages,
+        })
+    }
+
+    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        let page_count = self.pages.len() as u32;
+        bitsy_write!(writer, &self.header, &self.gold, &self.reserved);
+        bitsy_write!(writer, &page_count);
+        writer.write(&self.pages).prepend_path("pages")?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Bitsy)]
+#[bitsy(magic = "PAGE_MAGIC")]
+pub struct NewPage {
+    name: PageName,
+    items: ItemList,
+}
+
+impl NewPage {
+    pub fn name(&self) -> &str {
+        &self.name.0
+    }
+
+    pub fn items(&self) -> &ItemList {
+        &self.items
+    }
+
+    pub fn items_mut(&mut self) -> &mut ItemList {
+        &mut self.items
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+struct PageName(String);
+
+impl Bitsy for PageName {
+    fn parse<R: BitReader>(reader: &mut R) -> BitsyResult<Self> {
+        let mut name = String::new();
+        loop {
+            match reader.read::<u8>()? {
+                0 => break,
+                c => name.push(c as char),
+            }
+        }
+        Ok(PageName(name))
+    }
+
+    fn write_to<W: BitWriter>(&self, writer: &mut W) -> BitsyResult<()> {
+        for c in self.0.chars() {
+            writer.write(&(c as u8))?;
+        }
+        writer.write(&0u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::{
+        bitsy::dump::{render_dump, DumpOptions},
+        item::{info::MapItemDb, properties::MapPropertyDb},
+    };
+
+    use super::*;
+
+    #[test]
+    fn it_roundtrips_stashes() {
+        for path in ["small_stash.sss", "stash_example.sss", "Aleeria.d2x"] {
+            let bits = MyBitVec::from_vec(std::fs::read(path).unwrap());
+            let mut reader = BitVecReader::with_item_db(
+                bits.clone(),
+                Rc::new(MapItemDb::from_data_dir("data/items")),
+            );
+            reader.set_property_db(Rc::new(MapPropertyDb::new()));
+
+            let stash: NewStash = reader.read().unwrap();
+
+            assert!(reader.read_tail().unwrap().is_empty());
+            compare_bitslices(&bits, &bitsy_to_bits(&stash, STASH_VERSION)).unwrap();
+        }
+    }
+
+    #[test]
+    fn it_roundtrips_stashes_through_json() {
+        let bits = MyBitVec::from_vec(std::fs::read("Aleeria.d2x").unwrap());
+        let mut reader = BitVecReader::with_item_db(
+            bits.clone(),
+            Rc::new(MapItemDb::from_data_dir("data/items")),
+        );
+        reader.set_property_db(Rc::new(MapPropertyDb::new()));
+        let stash: NewStash = reader.read().unwrap();
+
+        let json = serde_json::to_string_pretty(&stash).unwrap();
+        let imported: NewStash = serde_json::from_str(&json).unwrap();
+
+        compare_bitslices(&bits, &bitsy_to_bits(&imported, STASH_VERSION)).unwrap();
+    }
+
+    #[test]
+    fn it_reads_gold_by_stash_version() {
+        let read = |bytes: &[u8]| {
+            let bits = MyBitVec::from_vec(bytes.to_vec());
+            let mut reader = BitVecReader::with_item_db(
+                bits.clone(),
+                Rc::new(MapItemDb::from_data_dir("data/items")),
+            );
+            reader.set_property_db(Rc::new(MapPropertyDb::new()));
+            let stash: NewStash = reader.read().unwrap();
+            compare_bitslices(&bits, &bitsy_to_bits(&stash, STASH_VERSION)).unwrap();
+            stash
+        };
+        let bytes = std::fs::read("small_stash.sss").unwrap();
+        assert_eq!(Some(0x282f0), read(&bytes).gold());
+
+        // Version "01" shared stashes have no gold before the page count.
+        let mut old = b"SSS\x0001".to_vec();
+        old.extend_from_slice(&bytes[10..]);
+        let stash = read(&old);
+        assert_eq!(None, stash.gold());
+        assert_eq!(4, stash.pages().len());
+
+        assert_eq!(None, read(&std::fs::read("Aleeria.d2x").unwrap()).gold());
+    }
+
+    #[test]
+    fn it_annotates_page_magics() {
+        let bits = MyBitVec::from_vec(std::fs::read("small_stash.sss").unwrap());
+        let mut reader = BitVecReader::with_item_db(
+            bits.clone(),
+            Rc::new(MapItemDb::from_data_dir("data/items")),
+        );
+        reader.set_property_db(Rc::new(MapPropertyDb::new()));
+        reader.record_annotations();
+        let _: NewStash = reader.read().unwrap();
+        let annotations = reader.take_annotations().unwrap();
+
+        let page = annotations.find(".pages[0]").unwrap();
+        let start = page.start / 8;
+        let dump = render_dump(
+            &bits,
+            Some(&annotations),
+            &DumpOptions {
+                color: false,
+                bytes: Some(start..start + 2),
+            },
+        );
+
+        let lines: Vec<_> = dump.lines().collect();
+        assert!(lines[1].ends_with(
+            "53  S  .pages (7056 bits), .pages[0] (56 bits), .pages[0].magic (16 bits)"
+        ));
+        assert!(lines[2].ends_with("54  T"));
+    }
+}
@@ -0,0 +1,438 @@
+// Moving an item, with what is socketed into it, from a panel of stored items to a free spot of
+// another one: the inventory, cube or stash of a character, or a page of a PlugY stash. The file
+// it comes from and the one it goes to can be the same.
+use crate::{
+    bitsy::{
+        error::{BitsyError, BitsyErrorKind},
+        result::BitsyResult,
+    },
+    grid::Grid,
+    item::{
+        Container, ItemList, NewItem, D2R_VERSION, LOCATION_CUBE, LOCATION_INVENTORY,
+        LOCATION_STASH,
+    },
+    save::SaveFile,
+    stash::{PAGE_HEIGHT, PAGE_WIDTH},
+};
+
+const CUBE_CODE: &str = "box";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    // The panels of a character.
+    Inventory,
+    Cube,
+    Stash,
+    // A page of a stash file, or the first one with room.
+    Page(Option<usize>),
+}
+
+// The width and height of each panel. Mods can make them larger.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelSizes {
+    pub inventory: (u8, u8),
+    pub cube: (u8, u8),
+    pub stash: (u8, u8),
+    pub page: (u8, u8),
+}
+
+impl PanelSizes {
+    // The sizes of the game that wrote files of the version.
+    pub fn for_version(version: u32) -> Self {
+        Self {
+            inventory: (10, 4),
+            cube: (3, 4),
+            stash: if version < D2R_VERSION {
+                (6, 8)
+            } else {
+                (10, 10)
+            },
+            page: (PAGE_WIDTH, PAGE_HEIGHT),
+        }
+    }
+}
+
+// Where an item goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    // Index of the list in `SaveFile::item_lists`, which is the page for stashes.
+    pub list: usize,
+    pub container: Container,
+    pub x: u8,
+    pub y: u8,
+}
+
+fn refuse(message: String) -> BitsyError {
+    BitsyErrorKind::InvalidAction(message).at_bit(0)
+}
+
+fn is_cube(item: &NewItem) -> bool {
+    item.code().is_some_and(|code| code.trim_end() == CUBE_CODE)
+}
+
+fn get_item(save: &SaveFile, list: usize, index: usize) -> BitsyResult<&NewItem> {
+    save.item_lists()
+        .get(list)
+        .and_then(|(_, items)| items.items().get(index))
+        .ok_or_else(|| {
+            refuse(format!(
+                "There is no item {} in list {}",
+                index + 1,
+                list + 1
+            ))
+        })
+}
+
+// The cells taken in a panel, leaving out the item at `skip`. None if some item has an unknown
+// size.
+fn panel_grid(
+    items: &ItemList,
+    container: Container,
+    (width, height): (u8, u8),
+    skip: Option<usize>,
+) -> Option<Grid> {
+    let mut grid = Grid::new(width, height);
+    for (index, item) in items.items().iter().enumerate() {
+        if item.container() != container || Some(index) == skip {
+            continue;
+        }
+        let (x, y) = item.position();
+        let (width, height) = item.size()?;
+        grid.occupy(x, y, width, height);
+    }
+    Some(grid)
+}
+
+// Finds a spot for item `index` of list `list` of `source`, in `destination` or, without one,
+// elsewhere in `source`. Returns None if there is no room, and an error if the move isn't
+// possible at all. Nothing is changed, so this is also a dry run of `move_item`.
+pub fn plan(
+    source: &SaveFile,
+    list: usize,
+    index: usize,
+    destination: Option<&SaveFile>,
+    to: Destination,
+    sizes: &PanelSizes,
+) -> BitsyResult<Option<Placement>> {
+    let item = get_item(source, list, index)?;
+    let same_file = destination.is_none();
+    let target = destination.unwrap_or(source);
+    let description = item.description();
+
+    if !matches!(
+        item.container(),
+        Container::Inventory | Container::Cube | Container::Stash
+    ) {
+        return Err(refuse(format!(
+            "{} is {}, only items in an inventory, cube or stash can be moved",
+            description,
+            item.container().name()
+        )));
+    }
+    // The items of older files are written differently.
+    if source.version() != target.version() {
+        return Err(refuse(format!(
+            "Items of version {} files can't be moved to version {} files",
+            source.version(),
+            target.version()
+        )));
+    }
+    let (width, height) = item
+        .size()
+        .ok_or_else(|| refuse(format!("The size of {} is unknown", description)))?;
+    if is_cube(item) {
+        let mut items = source.item_lists()[list].1.items().iter();
+        if items.any(|item| item.container() == Container::Cube) {
+            return Err(refuse(
+                "The cube can only be moved when it is empty".to_string(),
+            ));
+        }
+    }
+
+    // Panels to look at, as list, container and size.
+    let panels: Vec<(usize, Container, (u8, u8))> = match (target, to) {
+        (SaveFile::Player(player), Destination::Cube) => {
+            let has_cube = player
+                .items()
+                .items()
+                .iter()
+                .enumerate()
+                .any(|(i, item)| is_cube(item) && !(same_file && (list, index) == (0, i)));
+            if !has_cube {
+                return Err(refuse("The character has no cube".to_string()));
+            }
+            vec![(0, Container::Cube, sizes.cube)]
+        }
+        (SaveFile::Player(_), Destination::Inventory) => {
+            vec![(0, Container::Inventory, sizes.inventory)]
+        }
+        (SaveFile::Player(_), Destination::Stash) => vec![(0, Container::Stash, sizes.stash)],
+        (SaveFile::Player(_), Destination::Page(_)) => {
+            return Err(refuse(
+                "Characters have an inventory, a cube and a stash, but no pages".to_string(),
+            ))
+        }
+        (SaveFile::Stash(stash), Destination::Page(Some(page))) => {
+            if page >= stash.pages().len() {
+                return Err(refuse(format!("There is no page {}", page + 1)));
+            }
+            vec![(page, Container::Stash, sizes.page)]
+        }
+        (SaveFile::Stash(stash), Destination::Page(None)) => (0..stash.pages().len())
+            .map(|page| (page, Container::Stash, sizes.page))
+            .collect(),
+        (SaveFile::Stash(_), _) => {
+            return Err(refuse("Stashes only have pages".to_string()));
+        }
+    };
+
+    let lists = target.item_lists();
+    let searching = panels.len() > 1;
+    for (list_index, container, size) in panels {
+        let skip = Some(index).filter(|_| same_file && list_index == list);
+        let mut grid = match panel_grid(lists[list_index].1, container, size, skip) {
+            Some(grid) => grid,
+            // Pages with items of unknown size can't be trusted to have room, so other pages
+            // are tried.
+            None if searching => continue,
+            None => {
+                return Err(refuse(format!(
+                    "The {} of {} has items of unknown size",
+                    container.name(),
+                    lists[list_index].0
+                )))
+            }
+        };
+        if let Some((x, y)) = grid.place(width, height, false) {
+            return Ok(Some(Placement {
+                list: list_index,
+                container,
+                x,
+                y,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+// Moves the item to a placement found with `plan`.
+pub fn apply(
+    source: &mut SaveFile,
+    list: usize,
+    index: usize,
+    destination: Option<&mut SaveFile>,
+    placement: &Placement,
+) -> BitsyResult<()> {
+    get_item(source, list, index)?;
+    let target_lists = match &destination {
+        Some(destination) => destination.item_lists().len(),
+        None => source.item_lists().len(),
+    };
+    if placement.list >= target_lists {
+        return Err(refuse(format!("There is no list {}", placement.list + 1)));
+    }
+    let location = match placement.container {
+        Container::Inventory => LOCATION_INVENTORY,
+        Container::Cube => LOCATION_CUBE,
+        Container::Stash => LOCATION_STASH,
+        container => {
+            return Err(refuse(format!(
+                "Items can't be put in the {}",
+                container.name()
+            )))
+        }
+    };
+
+    let mut item = source.item_lists_mut().swap_remove(list).remove(index);
+    item.store(location, placement.x, placement.y)?;
+    let target = destination.unwrap_or(source);
+    target
+        .item_lists_mut()
+        .swap_remove(placement.list)
+        .push(item);
+    Ok(())
+}
+
+// Moves an item if there is room for it. See `plan`.
+pub fn move_item(
+    source: &mut SaveFile,
+    list: usize,
+    index: usize,
+    destination: Option<&mut SaveFile>,
+    to: Destination,
+    sizes: &PanelSizes,
+) -> BitsyResult<Option<Placement>> {
+    let placement = plan(source, list, index, destination.as_deref(), to, sizes)?;
+    if let Some(placement) = &placement {
+        apply(source, list, index, destination, placement)?;
+    }
+    Ok(placement)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, rc::Rc};
+
+    use crate::item::info::MapItemDb;
+
+    use super::*;
+
+    fn read(path: &str) -> SaveFile {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        SaveFile::read(Path::new(path), item_db).unwrap().1
+    }
+
+    fn find(save: &SaveFile, code: &str) -> (usize, usize) {
+        save.item_lists()
+            .iter()
+            .enumerate()
+            .find_map(|(list, (_, items))| {
+                let index = items
+                    .items()
+                    .iter()
+                    .position(|item| item.code().as_deref().map(str::trim_end) == Some(code))?;
+                Some((list, index))
+            })
+            .unwrap()
+    }
+
+    fn reread(save: &SaveFile) -> SaveFile {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        let kind = match save {
+            SaveFile::Player(_) => crate::save::SaveKind::Player,
+            SaveFile::Stash(_) => crate::save::SaveKind::SharedStash,
+        };
+        SaveFile::parse(kind, &save.to_bytes().unwrap(), item_db).unwrap()
+    }
+
+    #[test]
+    fn it_moves_items_within_a_character() {
+        let mut player = read("examples/LaCope2.d2s");
+        let sizes = PanelSizes::for_version(player.version());
+        let (list, index) = find(&player, "cm1");
+        let count = player.item_lists()[0].1.items().len();
+
+        let placement = move_item(&mut player, list, index, None, Destination::Stash, &sizes)
+            .unwrap()
+            .unwrap();
+
+        // The war staff takes the first two columns of the stash.
+        assert_eq!(
+            Placement {
+                list: 0,
+                container: Container::Stash,
+                x: 2,
+                y: 0
+            },
+            placement
+        );
+        let player = reread(&player);
+        let items = player.item_lists()[0].1.items();
+        assert_eq!(count, items.len());
+        let charm = items.last().unwrap();
+        assert_eq!(Container::Stash, charm.container());
+        assert_eq!((2, 0), charm.position());
+        if let SaveFile::Player(player) = &player {
+            assert!(player.has_valid_checksum(&player.to_bytes().unwrap()));
+        }
+    }
+
+    #[test]
+    fn it_moves_items_between_stashes() {
+        let mut source = read("small_stash.sss");
+        let mut destination = read("stash_example.sss");
+        let sizes = PanelSizes::for_version(source.version());
+        let (list, index) = find(&source, "mgu");
+        let moved = get_item(&source, list, index).unwrap().description();
+        let counts = |save: &SaveFile| -> usize {
+            save.item_lists()
+                .iter()
+                .map(|(_, items)| items.items().len())
+                .sum()
+        };
+        let (before, before_destination) = (counts(&source), counts(&destination));
+
+        let dry_run = plan(
+            &source,
+            list,
+            index,
+            Some(&destination),
+            Destination::Page(None),
+            &sizes,
+        )
+        .unwrap();
+        let placement = move_item(
+            &mut source,
+            list,
+            index,
+            Some(&mut destination),
+            Destination::Page(None),
+            &sizes,
+        )
+        .unwrap();
+
+        assert_eq!(dry_run, placement);
+        let (source, destination) = (reread(&source), reread(&destination));
+        assert_eq!(before - 1, counts(&source));
+        assert_eq!(before_destination + 1, counts(&destination));
+        let page = destination.item_lists()[placement.unwrap().list].1.items();
+        assert_eq!(moved, page.last().unwrap().description());
+    }
+
+    #[test]
+    fn it_refuses_impossible_moves() {
+        let mut player = read("examples/LaCope2.d2s");
+        let mut stash = read("small_stash.sss");
+        let sizes = PanelSizes::for_version(player.version());
+        let (list, index) = find(&player, "cm1");
+        let ring = find(&player, "rin");
+
+        let refused = [
+            // Items of players and stashes are written differently.
+            move_item(
+                &mut player,
+                list,
+                index,
+                Some(&mut stash),
+                Destination::Page(None),
+                &sizes,
+            ),
+            move_item(
+                &mut player,
+                ring.0,
+                ring.1,
+                None,
+                Destination::Stash,
+                &sizes,
+            ),
+            move_item(
+                &mut player,
+                list,
+                index,
+                None,
+                Destination::Page(None),
+                &sizes,
+            ),
+            move_item(&mut player, 0, 1000, None, Destination::Stash, &sizes),
+        ];
+
+        for result in refused {
+            assert!(matches!(
+                result.unwrap_err().kind(),
+                BitsyErrorKind::InvalidAction(_)
+            ));
+        }
+        let tiny = PanelSizes {
+            stash: (1, 1),
+            ..sizes
+        };
+        assert_eq!(
+            None,
+            move_item(&mut player, list, index, None, Destination::Stash, &tiny).unwrap()
+        );
+        assert_eq!(
+            Container::Inventory,
+            get_item(&player, list, index).unwrap().container()
+        );
+    }
+}
@@ -0,0 +1,416 @@
+// Checks of what a save must look like for the game to accept it, beyond being parseable. Every
+// problem found is reported with the item it is about, as LIST:ITEM counted from 1 like in the
+// commands, and SOCKET after a slash for socketed items.
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    bitsy::{compare_bitslices, MyBitVec},
+    item::{Container, ItemList, NewItem},
+    save::SaveFile,
+    transfer::PanelSizes,
+};
+
+// Stack sizes of the unmodded game that the item data doesn't give. Throwing weapons are left out,
+// as rolled ones hold more than their base stack.
+const STACK_LIMITS: [(&str, u16); 5] = [
+    ("tbk", 20),
+    ("ibk", 20),
+    ("key", 12),
+    ("aqv", 500),
+    ("cqv", 500),
+];
+// Gold a character can carry per level.
+const GOLD_PER_LEVEL: u32 = 10_000;
+const MAX_STASHED_GOLD: u32 = 2_500_000;
+// Stat points: 5 a level and 5 from each of the three Lam Esen's Tome quests.
+const STATS_PER_LEVEL: u32 = 5;
+const QUEST_STATS: u32 = 15;
+// Strength, dexterity, vitality and energy at level 1 by class, in class order.
+const BASE_STATS: [u32; 7] = [80, 80, 80, 85, 85, 80, 85];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    // The item, if the problem is with one.
+    pub item: Option<String>,
+    pub message: String,
+}
+
+impl Violation {
+    fn file(message: String) -> Self {
+        Self {
+            item: None,
+            message,
+        }
+    }
+
+    fn item(path: &str, message: String) -> Self {
+        Self {
+            item: Some(path.to_string()),
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.item {
+            Some(item) => write!(f, "{}: {}", item, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn item_path(list: usize, index: usize, socket: Option<usize>) -> String {
+    match socket {
+        Some(socket) => format!("{}:{}/{}", list + 1, index + 1, socket + 1),
+        None => format!("{}:{}", list + 1, index + 1),
+    }
+}
+
+pub(crate) fn describe(item: &NewItem) -> String {
+    match item.code() {
+        Some(code) => format!("{} [{}]", item.description(), code.trim_end()),
+        None => item.description(),
+    }
+}
+
+// Every item with its path, socketed items after the item they are in.
+fn all_items(save: &SaveFile) -> Vec<(String, &NewItem)> {
+    let mut items = Vec::new();
+    for (list, (_, item_list)) in save.item_lists().into_iter().enumerate() {
+        for (index, item) in item_list.items().iter().enumerate() {
+            items.push((item_path(list, index, None), item));
+            for (socket, socketed) in item.socketed_items().iter().enumerate() {
+                items.push((item_path(list, index, Some(socket)), socketed));
+            }
+        }
+    }
+    items
+}
+
+// Items must be inside their panel and not share cells. Items of unknown size are not checked.
+fn check_panel(
+    list: usize,
+    items: &ItemList,
+    container: Container,
+    (width, height): (u8, u8),
+    violations: &mut Vec<Violation>,
+) {
+    let mut owners: HashMap<(u8, u8), usize> = HashMap::new();
+    for (index, item) in items.items().iter().enumerate() {
+        let size = item.size();
+        let ((x, y), (item_width, item_height)) = match size {
+            Some(size) if item.container() == container => (item.position(), size),
+            _ => continue,
+        };
+        let path = item_path(list, index, None);
+        if u16::from(x) + u16::from(item_width) > u16::from(width)
+            || u16::from(y) + u16::from(item_height) > u16::from(height)
+        {
+            violations.push(Violation::item(
+                &path,
+                format!(
+                    "{} at ({}, {}) doesn't fit in the {}x{} {}",
+                    describe(item),
+                    x,
+                    y,
+                    width,
+                    height,
+                    container.name()
+                ),
+            ));
+        }
+        let mut overlapped = Vec::new();
+        for cell_y in y..y.saturating_add(item_height) {
+            for cell_x in x..x.saturating_add(item_width) {
+                if let Some(owner) = owners.insert((cell_x, cell_y), index) {
+                    if !overlapped.contains(&owner) {
+                        overlapped.push(owner);
+                    }
+                }
+            }
+        }
+        for owner in overlapped {
+            violations.push(Violation::item(
+                &path,
+                format!(
+                    "{} overlaps {}",
+                    describe(item),
+                    item_path(list, owner, None)
+                ),
+            ));
+        }
+    }
+}
+
+fn check_panels(save: &SaveFile, sizes: &PanelSizes, violations: &mut Vec<Violation>) {
+    match save {
+        SaveFile::Player(player) => {
+            for (container, size) in [
+                (Container::Inventory, sizes.inventory),
+                (Container::Cube, sizes.cube),
+                (Container::Stash, sizes.stash),
+            ] {
+                check_panel(0, player.items(), container, size, violations);
+            }
+        }
+        SaveFile::Stash(stash) => {
+            for (page, items) in stash.pages().iter().enumerate() {
+                check_panel(
+                    page,
+                    items.items(),
+                    Container::Stash,
+                    sizes.page,
+                    violations,
+                );
+            }
+        }
+    }
+}
+
+fn check_item(path: &str, item: &NewItem, violations: &mut Vec<Violation>) {
+    let socketed = item.socketed_items().len();
+    if socketed != usize::from(item.gem_count()) {
+        violations.push(Violation::item(
+            path,
+            format!(
+                "{} has {} socketed items, but says {}",
+                describe(item),
+                socketed,
+                item.gem_count()
+            ),
+        ));
+    }
+    if let Some(sockets) = item.socket_count() {
+        if socketed > usize::from(sockets) {
+            violations.push(Violation::item(
+                path,
+                format!(
+                    "{} has {} socketed items in {} sockets",
+                    describe(item),
+                    socketed,
+                    sockets
+                ),
+            ));
+        }
+    }
+    if let Some((current, max)) = item.durability() {
+        if current > max {
+            violations.push(Violation::item(
+                path,
+                format!(
+                    "{} has a durability of {} out of {}",
+                    describe(item),
+                    current,
+                    max
+                ),
+            ));
+        }
+    }
+    let code = item.code();
+    let limit = STACK_LIMITS
+        .iter()
+        .find(|(limit_code, _)| code.as_deref().map(str::trim_end) == Some(*limit_code));
+    if let (Some(quantity), Some((_, limit))) = (item.quantity(), limit) {
+        if quantity > *limit {
+            violations.push(Violation::item(
+                path,
+                format!(
+                    "{} has a quantity of {}, more than {}",
+                    describe(item),
+                    quantity,
+                    limit
+                ),
+            ));
+        }
+    }
+}
+
+fn check_attributes(save: &SaveFile, violations: &mut Vec<Violation>) {
+    let player = match save {
+        SaveFile::Player(player) => player,
+        SaveFile::Stash(_) => return,
+    };
+    let attributes = player.attributes();
+    let level = u32::from(player.level());
+    if let Some(attribute_level) = attributes.get("Level") {
+        if attribute_level != level {
+            violations.push(Violation::file(format!(
+                "The level attribute is {}, but the character is level {}",
+                attribute_level, level
+            )));
+        }
+    }
+    if let Some(base) = BASE_STATS.get(usize::from(player.class())) {
+        let stats: u32 = [
+            "Strength",
+            "Dexterity",
+            "Vitality",
+            "Energy",
+            "Unused stats",
+        ]
+        .iter()
+        .filter_map(|name| attributes.get(name))
+        .sum();
+        let max = base + STATS_PER_LEVEL * level.saturating_sub(1) + QUEST_STATS;
+        if stats > max {
+            violations.push(Violation::file(format!(
+                "The character has {} stat points, but at most {} at level {}",
+                stats, max, level
+            )));
+        }
+    }
+    if let Some(gold) = attributes.get("Gold") {
+        if gold > GOLD_PER_LEVEL * level {
+            violations.push(Violation::file(format!(
+                "The character carries {} gold, but at most {} at level {}",
+                gold,
+                GOLD_PER_LEVEL * level,
+                level
+            )));
+        }
+    }
+    if let Some(gold) = attributes.get("Stashed Gold") {
+        if gold > MAX_STASHED_GOLD {
+            violations.push(Violation::file(format!(
+                "The stash holds {} gold, but at most {}",
+                gold, MAX_STASHED_GOLD
+            )));
+        }
+    }
+}
+
+// Checks one file, given the bytes it was read from.
+pub fn validate(save: &SaveFile, bytes: &[u8], sizes: &PanelSizes) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    match save.to_bits() {
+        Ok(bits) => {
+            if let Err(error) = compare_bitslices(&MyBitVec::from_vec(bytes.to_vec()), &bits) {
+                violations.push(Violation::file(format!(
+                    "It is not written back unchanged: {}",
+                    error
+                )));
+            }
+        }
+        Err(error) => violations.push(Violation::file(format!(
+            "It can't be written back: {}",
+            error
+        ))),
+    }
+    if let SaveFile::Player(player) = save {
+        if !player.has_valid_file_size(bytes) {
+            violations.push(Violation::file(
+                "The file size in the header is wrong".to_string(),
+            ));
+        }
+        if !player.has_valid_checksum(bytes) {
+            violations.push(Violation::file("The checksum is wrong".to_string()));
+        }
+    }
+    check_panels(save, sizes, &mut violations);
+    for (path, item) in all_items(save) {
+        check_item(&path, item, &mut violations);
+    }
+    check_attributes(save, &mut violations);
+    violations
+}
+
+// GUIDs used by more than one item, in any of the files, with the file names and item paths of
+// the items.
+pub fn duplicate_guids(files: &[(String, &SaveFile)]) -> Vec<(String, Vec<(String, String)>)> {
+    let mut users: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for (file, save) in files {
+        for (path, item) in all_items(save) {
+            if let Some(guid) = item.guid() {
+                users.entry(guid).or_default().push((file.clone(), path));
+            }
+        }
+    }
+    users
+        .into_iter()
+        .filter(|(_, users)| users.len() > 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, rc::Rc};
+
+    use crate::item::info::MapItemDb;
+
+    use super::*;
+
+    fn read(path: &str) -> (SaveFile, Vec<u8>) {
+        let item_db = Rc::new(MapItemDb::from_data_dir("data/items"));
+        let save = SaveFile::read(Path::new(path), item_db).unwrap().1;
+        (save, std::fs::read(path).unwrap())
+    }
+
+    #[test]
+    fn it_accepts_valid_saves() {
+        for path in ["examples/StartingD2R.d2s", "small_stash.sss", "Aleeria.d2x"] {
+            let (save, bytes) = read(path);
+            let sizes = PanelSizes::for_version(save.version());
+            assert_eq!(
+                Vec::<Violation>::new(),
+                validate(&save, &bytes, &sizes),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn it_reports_items_out_of_place() {
+        let (mut save, _) = read("small_stash.sss");
+        let sizes = PanelSizes::for_version(save.version());
+        let items = save.item_lists_mut().swap_remove(3);
+        let (x, y) = items.items()[0].position();
+        items.items_mut()[2].set_position(x, y).unwrap();
+        items.items_mut()[1].set_position(9, 9).unwrap();
+        let bytes = save.to_bytes().unwrap();
+
+        let violations = validate(&save, &bytes, &sizes);
+
+        assert_eq!(
+            vec![
+                Violation::item(
+                    "4:2",
+                    "Mythril Gauntlets [mgu] at (9, 9) doesn't fit in the 10x10 stash".to_string()
+                ),
+                Violation::item("4:3", "Summoner Robe [rb2] overlaps 4:1".to_string()),
+                // The robe now covers where these were.
+                Violation::item("4:4", "Greaves [hbt] overlaps 4:3".to_string()),
+                Violation::item("4:5", "Mythril Boots [mbo] overlaps 4:3".to_string()),
+                Violation::item("4:5", "Mythril Boots [mbo] overlaps 4:2".to_string()),
+                Violation::item("4:11", "Grim Wand [gwn] overlaps 4:3".to_string()),
+            ],
+            violations
+        );
+    }
+
+    #[test]
+    fn it_finds_duplicate_guids() {
+        let (save, _) = read("examples/LaCope2.d2s");
+        let (copy, _) = read("examples/LaCope2.d2s");
+
+        let duplicates = duplicate_guids(&[("a".to_string(), &save), ("b".to_string(), &copy)]);
+
+        let with_guid = all_items(&save)
+            .into_iter()
+            .filter(|(_, item)| item.guid().is_some())
+            .count();
+        assert_eq!(with_guid, duplicates.len());
+        for (_, users) in &duplicates {
+            assert_eq!(
+                vec!["a", "b"],
+                users
+                    .iter()
+                    .map(|(file, _)| file.as_str())
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(users[0].1, users[1].1);
+        }
+        assert!(duplicate_guids(&[("a".to_string(), &save)]).is_empty());
+    }
+}
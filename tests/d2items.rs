@@ -0,0 +1,61 @@
+// Runs the d2items binary on copies of the example files.
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+const DATA_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/items");
+
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("d2items-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn d2items(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_d2items"))
+        .args(args)
+        .args(["--data", DATA_DIR])
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: Output) -> String {
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn move_within_a_file_named_two_ways() {
+    let dir = work_dir("move-same-file");
+    std::fs::copy("small_stash.sss", dir.join("a.sss")).unwrap();
+    let before = stdout(d2items(&dir, &["dump", "a.sss"])).lines().count();
+
+    stdout(d2items(
+        &dir,
+        &[
+            "move",
+            "a.sss",
+            "4:1",
+            "./a.sss",
+            "--write",
+            "--journal",
+            "journal.jsonl",
+        ],
+    ));
+
+    let after = stdout(d2items(&dir, &["dump", "a.sss"])).lines().count();
+    assert_eq!(before, after);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn commands_show_the_usage() {
+    let dir = work_dir("help");
+    for args in [["dump", "--help"], ["search", "-h"]] {
+        assert!(stdout(d2items(&dir, &args)).starts_with("Usage: d2items"));
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}